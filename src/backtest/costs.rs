@@ -0,0 +1,312 @@
+//! Commission and slippage models applied by the backtester to every fill.
+
+use super::enums::OrderSide;
+use crate::errors::CalculationError;
+
+// SGX clearing and trading access fees, as fractions of the traded value, and the Singapore GST rate
+const SGX_CLEARING_FEE_RATE: f32 = 0.000325;
+const SGX_ACCESS_FEE_RATE: f32 = 0.000075;
+const SGD_GST_RATE: f32 = 0.09;
+// US regulatory fees on sales: the SEC fee as a fraction of the traded value, and the FINRA TAF per share with its cap
+const US_SEC_FEE_RATE: f32 = 0.0000278;
+const US_FINRA_TAF_PER_SHARE: f32 = 0.000166;
+const US_FINRA_TAF_MAXIMUM: f32 = 8.30;
+
+pub mod enums {
+    use super::structs::CommissionTier;
+
+    #[derive(Clone, Debug, PartialEq)]
+    pub enum CommissionModel {
+        Zero,
+        PerShare {
+            rate: f32,
+            minimum: f32,
+        },
+        PerTrade(f32),
+        /// Rate as a fraction of the traded value.
+        Percentage {
+            rate: f32,
+            minimum: f32,
+        },
+        /// Marginal rates over the tiers of traded value, in ascending order.
+        Tiered(Vec<CommissionTier>),
+        /// Brokerage as a fraction of the traded value, plus the clearing and access fees, all subject to GST.
+        SgxSchedule {
+            brokerage_rate: f32,
+            minimum_brokerage: f32,
+        },
+        /// Per share brokerage capped at a fraction of the traded value, plus the SEC fee and FINRA TAF on sales.
+        UsSchedule {
+            per_share: f32,
+            minimum: f32,
+            maximum_rate: f32,
+        },
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum SlippageModel {
+        Zero,
+        FixedBps(f32),
+        /// Fraction of the ATR as of the bar preceding the fill.
+        AtrFraction {
+            period: usize,
+            fraction: f32,
+        },
+        /// Fills capped at a fraction of the bar's volume, with a square root market impact.
+        VolumeParticipation {
+            max_participation: f32,
+            impact_coefficient: f32,
+        },
+    }
+}
+
+pub mod structs {
+
+    /// Tier of a tiered commission schedule, applying the rate to the traded value up to the upper bound.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct CommissionTier {
+        pub(super) up_to_value: f32, // f32::INFINITY for the last tier
+        pub(super) rate: f32,
+    }
+
+    impl CommissionTier {
+        pub fn new(up_to_value: f32, rate: f32) -> Self {
+            CommissionTier { up_to_value, rate }
+        }
+
+        pub fn get_up_to_value(&self) -> f32 {
+            self.up_to_value
+        }
+
+        pub fn get_rate(&self) -> f32 {
+            self.rate
+        }
+    }
+}
+
+impl enums::CommissionModel {
+    pub(crate) fn validate(&self) -> Result<(), CalculationError> {
+        let parameters: Vec<f32> = match self {
+            enums::CommissionModel::Zero => Vec::new(),
+            enums::CommissionModel::PerShare { rate, minimum } => vec![*rate, *minimum],
+            enums::CommissionModel::PerTrade(fee) => vec![*fee],
+            enums::CommissionModel::Percentage { rate, minimum } => vec![*rate, *minimum],
+            enums::CommissionModel::Tiered(tiers) => {
+                if tiers
+                    .windows(2)
+                    .any(|pair| pair[1].up_to_value <= pair[0].up_to_value)
+                {
+                    return Err(CalculationError::InvalidParameterError(
+                        "Commission tiers must be in ascending order of traded value.".to_string(),
+                    ));
+                }
+                tiers.iter().map(|tier| tier.rate).collect()
+            }
+            enums::CommissionModel::SgxSchedule {
+                brokerage_rate,
+                minimum_brokerage,
+            } => vec![*brokerage_rate, *minimum_brokerage],
+            enums::CommissionModel::UsSchedule {
+                per_share,
+                minimum,
+                maximum_rate,
+            } => vec![*per_share, *minimum, *maximum_rate],
+        };
+        if parameters.iter().any(|x| x.is_nan() || *x < 0.0) {
+            return Err(CalculationError::InvalidParameterError(format!(
+                "Commission parameters must be non-negative. Commission model provided: {:?}",
+                self
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl enums::SlippageModel {
+    pub(crate) fn validate(&self) -> Result<(), CalculationError> {
+        let is_valid = match self {
+            enums::SlippageModel::Zero => true,
+            enums::SlippageModel::FixedBps(bps) => *bps >= 0.0,
+            enums::SlippageModel::AtrFraction { period, fraction } => {
+                *period > 0 && *fraction >= 0.0
+            }
+            enums::SlippageModel::VolumeParticipation {
+                max_participation,
+                impact_coefficient,
+            } => {
+                *max_participation > 0.0 && *max_participation <= 1.0 && *impact_coefficient >= 0.0
+            }
+        };
+        if !is_valid {
+            return Err(CalculationError::InvalidParameterError(format!(
+                "Slippage parameters are out of range. Slippage model provided: {:?}",
+                self
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Commission charged on a fill of the quantity at the price.
+pub fn commission(
+    model: &enums::CommissionModel,
+    side: OrderSide,
+    quantity: f32,
+    price: f32,
+) -> f32 {
+    let value = quantity * price;
+    match model {
+        enums::CommissionModel::Zero => 0.0,
+        enums::CommissionModel::PerShare { rate, minimum } => (rate * quantity).max(*minimum),
+        enums::CommissionModel::PerTrade(fee) => *fee,
+        enums::CommissionModel::Percentage { rate, minimum } => (rate * value).max(*minimum),
+        enums::CommissionModel::Tiered(tiers) => {
+            let mut lower_bound = 0.0;
+            let mut total = 0.0;
+            for tier in tiers {
+                if value <= lower_bound {
+                    break;
+                }
+                total += tier.rate * (value.min(tier.up_to_value) - lower_bound);
+                lower_bound = tier.up_to_value;
+            }
+            total
+        }
+        enums::CommissionModel::SgxSchedule {
+            brokerage_rate,
+            minimum_brokerage,
+        } => {
+            let fees = (brokerage_rate * value).max(*minimum_brokerage)
+                + (SGX_CLEARING_FEE_RATE + SGX_ACCESS_FEE_RATE) * value;
+            fees * (1.0 + SGD_GST_RATE)
+        }
+        enums::CommissionModel::UsSchedule {
+            per_share,
+            minimum,
+            maximum_rate,
+        } => {
+            let brokerage = (per_share * quantity)
+                .max(*minimum)
+                .min(maximum_rate * value);
+            let regulatory_fees = match side {
+                OrderSide::Buy => 0.0,
+                OrderSide::Sell => {
+                    US_SEC_FEE_RATE * value
+                        + (US_FINRA_TAF_PER_SHARE * quantity).min(US_FINRA_TAF_MAXIMUM)
+                }
+            };
+            brokerage + regulatory_fees
+        }
+    }
+}
+
+/// Quantity filled and the adverse price slippage per share for an order of the quantity at the reference price.
+/// The ATR and volume are those observed without look-ahead: the ATR as of the preceding bar, and the volume of the
+/// bar being filled, which caps the quantity filled under the volume participation model.
+/// The slippage is 0 whenever the ATR is unavailable during its warm-up period.
+pub fn slippage(
+    model: &enums::SlippageModel,
+    quantity: f32,
+    reference_price: f32,
+    previous_atr: f32,
+    bar_volume: i32,
+) -> (f32, f32) {
+    match model {
+        enums::SlippageModel::Zero => (quantity, 0.0),
+        enums::SlippageModel::FixedBps(bps) => (quantity, reference_price * bps / 10000.0),
+        enums::SlippageModel::AtrFraction { fraction, .. } => {
+            if previous_atr.is_nan() {
+                return (quantity, 0.0);
+            }
+            (quantity, fraction * previous_atr)
+        }
+        enums::SlippageModel::VolumeParticipation {
+            max_participation,
+            impact_coefficient,
+        } => {
+            let bar_volume = bar_volume.max(0) as f32;
+            let filled_quantity = quantity.min(max_participation * bar_volume);
+            if filled_quantity <= 0.0 {
+                return (0.0, 0.0);
+            }
+            // Square root market impact, as a fraction of the price
+            let participation = filled_quantity / bar_volume;
+            (
+                filled_quantity,
+                reference_price * impact_coefficient * participation.sqrt(),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commission_models() {
+        let per_share = enums::CommissionModel::PerShare {
+            rate: 0.01,
+            minimum: 1.0,
+        };
+        assert!(commission(&per_share, OrderSide::Buy, 50.0, 10.0) == 1.0);
+        assert!((commission(&per_share, OrderSide::Buy, 500.0, 10.0) - 5.0).abs() < 1e-6);
+
+        // 0.1% on the first 10000, 0.05% on the remaining 10000
+        let tiered = enums::CommissionModel::Tiered(vec![
+            structs::CommissionTier::new(10000.0, 0.001),
+            structs::CommissionTier::new(f32::INFINITY, 0.0005),
+        ]);
+        assert!((commission(&tiered, OrderSide::Buy, 2000.0, 10.0) - 15.0).abs() < 1e-4);
+        assert!(enums::CommissionModel::Tiered(vec![
+            structs::CommissionTier::new(10000.0, 0.001),
+            structs::CommissionTier::new(5000.0, 0.0005),
+        ])
+        .validate()
+        .is_err());
+
+        // SGX: (max(0.08% * 10000, 10) + 0.04% * 10000) * 1.09
+        let sgx = enums::CommissionModel::SgxSchedule {
+            brokerage_rate: 0.0008,
+            minimum_brokerage: 10.0,
+        };
+        assert!((commission(&sgx, OrderSide::Buy, 1000.0, 10.0) - 15.26).abs() < 1e-4);
+
+        // US: brokerage of 0.005 * 1000, with the SEC fee and FINRA TAF on the sale
+        let us = enums::CommissionModel::UsSchedule {
+            per_share: 0.005,
+            minimum: 1.0,
+            maximum_rate: 0.01,
+        };
+        assert!((commission(&us, OrderSide::Buy, 1000.0, 10.0) - 5.0).abs() < 1e-6);
+        assert!((commission(&us, OrderSide::Sell, 1000.0, 10.0) - 5.444).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_slippage_models() {
+        assert!(
+            slippage(
+                &enums::SlippageModel::FixedBps(10.0),
+                100.0,
+                50.0,
+                f32::NAN,
+                0
+            ) == (100.0, 0.05)
+        );
+        let atr_model = enums::SlippageModel::AtrFraction {
+            period: 14,
+            fraction: 0.1,
+        };
+        assert!(slippage(&atr_model, 100.0, 50.0, 2.0, 0) == (100.0, 0.2));
+        assert!(slippage(&atr_model, 100.0, 50.0, f32::NAN, 0) == (100.0, 0.0));
+
+        // Capped at 10% of the 1000 shares traded, with an impact of 0.1 * sqrt(0.1) of the price
+        let volume_model = enums::SlippageModel::VolumeParticipation {
+            max_participation: 0.1,
+            impact_coefficient: 0.1,
+        };
+        let (quantity, per_share) = slippage(&volume_model, 500.0, 50.0, f32::NAN, 1000);
+        assert!(quantity == 100.0 && (per_share - 5.0 * 0.1_f32.sqrt()).abs() < 1e-5);
+        assert!(enums::SlippageModel::FixedBps(-1.0).validate().is_err());
+    }
+}
@@ -0,0 +1,506 @@
+//! Trade ledger pairing the fills of a backtest into round trips, and the statistics over the round trips.
+
+use std::collections::VecDeque;
+
+use super::structs::Fill;
+use crate::datasets::traits::Prices;
+use crate::errors::CalculationError;
+use crate::functions;
+
+pub mod enums {
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum TradeDirection {
+        Long,
+        Short,
+    }
+}
+
+pub mod structs {
+    use super::*;
+
+    /// Position opened by an entry fill and held until matched against exit fills. Signed quantity: positive for
+    /// long lots and negative for short lots.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub(super) struct Lot {
+        pub(super) index: usize,
+        pub(super) timestamp: chrono::DateTime<chrono::Utc>,
+        pub(super) price: f32,
+        pub(super) quantity: f32,
+        pub(super) commission_per_share: f32,
+    }
+
+    /// Entry and exit of a quantity, matched first-in first-out. Commissions of partially matched fills are
+    /// allocated pro rata to the quantity.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct RoundTrip {
+        pub(super) direction: enums::TradeDirection,
+        pub(super) quantity: f32,
+        pub(super) entry_index: usize,
+        pub(super) entry_timestamp: chrono::DateTime<chrono::Utc>,
+        pub(super) entry_price: f32,
+        pub(super) exit_index: usize,
+        pub(super) exit_timestamp: chrono::DateTime<chrono::Utc>,
+        pub(super) exit_price: f32,
+        pub(super) commission: f32,
+        pub(super) pnl: f32,                     // Net of commissions
+        pub(super) max_adverse_excursion: f32,   // Negative fraction of the entry price
+        pub(super) max_favorable_excursion: f32, // Positive fraction of the entry price
+    }
+
+    impl RoundTrip {
+        pub fn get_direction(&self) -> enums::TradeDirection {
+            self.direction
+        }
+
+        pub fn get_quantity(&self) -> f32 {
+            self.quantity
+        }
+
+        pub fn get_entry_index(&self) -> usize {
+            self.entry_index
+        }
+
+        pub fn get_entry_timestamp(&self) -> chrono::DateTime<chrono::Utc> {
+            self.entry_timestamp
+        }
+
+        pub fn get_entry_price(&self) -> f32 {
+            self.entry_price
+        }
+
+        pub fn get_exit_index(&self) -> usize {
+            self.exit_index
+        }
+
+        pub fn get_exit_timestamp(&self) -> chrono::DateTime<chrono::Utc> {
+            self.exit_timestamp
+        }
+
+        pub fn get_exit_price(&self) -> f32 {
+            self.exit_price
+        }
+
+        pub fn get_commission(&self) -> f32 {
+            self.commission
+        }
+
+        pub fn get_pnl(&self) -> f32 {
+            self.pnl
+        }
+
+        /// Net profit as a fraction of the entry value.
+        pub fn get_return(&self) -> f32 {
+            self.pnl / (self.entry_price * self.quantity)
+        }
+
+        /// Number of bars between the entry and exit fills.
+        pub fn get_holding_period(&self) -> usize {
+            self.exit_index - self.entry_index
+        }
+
+        pub fn get_max_adverse_excursion(&self) -> f32 {
+            self.max_adverse_excursion
+        }
+
+        pub fn get_max_favorable_excursion(&self) -> f32 {
+            self.max_favorable_excursion
+        }
+    }
+
+    /// Fills of a backtest, with the round trips closed so far and the lots remaining open.
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct TradeLedger {
+        pub(super) fills: Vec<Fill>,
+        pub(super) round_trips: Vec<RoundTrip>,
+        pub(super) open_lots: VecDeque<Lot>,
+    }
+
+    impl TradeLedger {
+        pub fn get_fills(&self) -> &[Fill] {
+            &self.fills
+        }
+
+        pub fn get_round_trips(&self) -> &[RoundTrip] {
+            &self.round_trips
+        }
+
+        /// Signed quantity of the lots remaining open.
+        pub fn get_open_quantity(&self) -> f32 {
+            self.open_lots.iter().map(|lot| lot.quantity).sum()
+        }
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct RoundTripStatistics {
+        pub(super) num_of_trades: usize,
+        pub(super) win_rate: f32,
+        pub(super) average_win: Option<f32>,
+        pub(super) average_loss: Option<f32>, // Negative
+        pub(super) profit_factor: f32,        // Infinite without any losing trades
+        pub(super) expectancy: f32,           // Mean net profit per trade
+        pub(super) mean_holding_period: f32,
+        pub(super) median_holding_period: f32,
+        pub(super) p90_holding_period: f32,
+        pub(super) max_holding_period: usize,
+        pub(super) mean_adverse_excursion: f32,
+        pub(super) mean_favorable_excursion: f32,
+        pub(super) max_consecutive_wins: usize,
+        pub(super) max_consecutive_losses: usize,
+    }
+
+    impl RoundTripStatistics {
+        pub fn get_num_of_trades(&self) -> usize {
+            self.num_of_trades
+        }
+
+        pub fn get_win_rate(&self) -> f32 {
+            self.win_rate
+        }
+
+        pub fn get_average_win(&self) -> Option<f32> {
+            self.average_win
+        }
+
+        pub fn get_average_loss(&self) -> Option<f32> {
+            self.average_loss
+        }
+
+        pub fn get_profit_factor(&self) -> f32 {
+            self.profit_factor
+        }
+
+        pub fn get_expectancy(&self) -> f32 {
+            self.expectancy
+        }
+
+        pub fn get_mean_holding_period(&self) -> f32 {
+            self.mean_holding_period
+        }
+
+        pub fn get_median_holding_period(&self) -> f32 {
+            self.median_holding_period
+        }
+
+        pub fn get_p90_holding_period(&self) -> f32 {
+            self.p90_holding_period
+        }
+
+        pub fn get_max_holding_period(&self) -> usize {
+            self.max_holding_period
+        }
+
+        pub fn get_mean_adverse_excursion(&self) -> f32 {
+            self.mean_adverse_excursion
+        }
+
+        pub fn get_mean_favorable_excursion(&self) -> f32 {
+            self.mean_favorable_excursion
+        }
+
+        pub fn get_max_consecutive_wins(&self) -> usize {
+            self.max_consecutive_wins
+        }
+
+        pub fn get_max_consecutive_losses(&self) -> usize {
+            self.max_consecutive_losses
+        }
+    }
+}
+
+/// Worst and best excursions of a position against its entry price over the bars it was held, as fractions of the
+/// entry price. The full ranges of the entry and exit bars are included, as the intrabar order of prices is unknown.
+fn excursions(
+    direction: enums::TradeDirection,
+    entry_price: f32,
+    high: &[f32],
+    low: &[f32],
+) -> (f32, f32) {
+    let highest = high.iter().copied().fold(f32::MIN, f32::max);
+    let lowest = low.iter().copied().fold(f32::MAX, f32::min);
+    let (adverse, favorable) = match direction {
+        enums::TradeDirection::Long => (lowest / entry_price - 1.0, highest / entry_price - 1.0),
+        enums::TradeDirection::Short => (1.0 - highest / entry_price, 1.0 - lowest / entry_price),
+    };
+    (adverse.min(0.0), favorable.max(0.0))
+}
+
+/// Ledger of the fills against the record they were filled on, pairing exits with the earliest open entries.
+/// Fills larger than the open position reverse it, opening a lot in the opposite direction with the remainder.
+/// Fill quantities must be positive and finite, as the commission is apportioned per share.
+pub fn trade_ledger<T>(record: &T, fills: &[Fill]) -> Result<structs::TradeLedger, CalculationError>
+where
+    T: Prices,
+{
+    let high = record.get_high_prices();
+    let low = record.get_low_prices();
+    if high.len() != low.len() {
+        return Err(CalculationError::InconsistentLengthError(format!(
+            "Length of the high prices array: {} \n Length of the low prices array: {}",
+            high.len(),
+            low.len()
+        )));
+    }
+    if let Some(fill) = fills.iter().find(|fill| fill.index >= high.len()) {
+        return Err(CalculationError::InconsistentLengthError(format!(
+            "Fill at index {} is beyond the record of {} bars.",
+            fill.index,
+            high.len()
+        )));
+    }
+    if let Some(fill) = fills
+        .iter()
+        .find(|fill| !fill.quantity.is_finite() || fill.quantity <= 0.0)
+    {
+        return Err(CalculationError::InvalidParameterError(format!(
+            "Fill quantities must be positive and finite. Quantity provided: {}",
+            fill.quantity
+        )));
+    }
+
+    let mut ledger = structs::TradeLedger {
+        fills: fills.to_vec(),
+        round_trips: Vec::new(),
+        open_lots: VecDeque::new(),
+    };
+    for fill in fills {
+        let mut remaining = fill.get_signed_quantity();
+        let commission_per_share = fill.commission / fill.quantity;
+        while remaining != 0.0 {
+            let lot = match ledger.open_lots.front_mut() {
+                Some(lot) if lot.quantity.signum() != remaining.signum() => lot,
+                _ => break,
+            };
+            let quantity = remaining.abs().min(lot.quantity.abs());
+            let (direction, sign) = if lot.quantity > 0.0 {
+                (enums::TradeDirection::Long, 1.0)
+            } else {
+                (enums::TradeDirection::Short, -1.0)
+            };
+            let commission = (lot.commission_per_share + commission_per_share) * quantity;
+            let (max_adverse_excursion, max_favorable_excursion) = excursions(
+                direction,
+                lot.price,
+                &high[lot.index..=fill.index],
+                &low[lot.index..=fill.index],
+            );
+            ledger.round_trips.push(structs::RoundTrip {
+                direction,
+                quantity,
+                entry_index: lot.index,
+                entry_timestamp: lot.timestamp,
+                entry_price: lot.price,
+                exit_index: fill.index,
+                exit_timestamp: fill.timestamp,
+                exit_price: fill.price,
+                commission,
+                pnl: sign * (fill.price - lot.price) * quantity - commission,
+                max_adverse_excursion,
+                max_favorable_excursion,
+            });
+
+            lot.quantity -= sign * quantity;
+            remaining += sign * quantity;
+            if lot.quantity == 0.0 {
+                ledger.open_lots.pop_front();
+            }
+        }
+        if remaining != 0.0 {
+            ledger.open_lots.push_back(structs::Lot {
+                index: fill.index,
+                timestamp: fill.timestamp,
+                price: fill.price,
+                quantity: remaining,
+                commission_per_share,
+            });
+        }
+    }
+    Ok(ledger)
+}
+
+/// Longest runs of consecutive winning and losing round trips, with breakeven trades ending both runs.
+fn max_streaks(round_trips: &[structs::RoundTrip]) -> (usize, usize) {
+    let (mut wins, mut losses) = (0, 0);
+    let (mut max_wins, mut max_losses) = (0, 0);
+    for round_trip in round_trips {
+        wins = if round_trip.pnl > 0.0 { wins + 1 } else { 0 };
+        losses = if round_trip.pnl < 0.0 { losses + 1 } else { 0 };
+        max_wins = usize::max(max_wins, wins);
+        max_losses = usize::max(max_losses, losses);
+    }
+    (max_wins, max_losses)
+}
+
+/// Statistics over the round trips in the order they were closed. Breakeven trades count towards the number of
+/// trades, but neither as wins nor losses.
+pub fn round_trip_statistics(
+    round_trips: &[structs::RoundTrip],
+) -> Result<structs::RoundTripStatistics, CalculationError> {
+    if round_trips.is_empty() {
+        return Err(CalculationError::InsufficientDataError(
+            "At least 1 round trip is required for the trade statistics.".to_string(),
+        ));
+    }
+    let num_of_trades = round_trips.len();
+    let wins = round_trips
+        .iter()
+        .map(|x| x.pnl)
+        .filter(|&x| x > 0.0)
+        .collect::<Vec<f32>>();
+    let losses = round_trips
+        .iter()
+        .map(|x| x.pnl)
+        .filter(|&x| x < 0.0)
+        .collect::<Vec<f32>>();
+    let average = |values: &[f32]| {
+        if values.is_empty() {
+            None
+        } else {
+            Some(values.iter().sum::<f32>() / values.len() as f32)
+        }
+    };
+
+    let mut holding_periods = round_trips
+        .iter()
+        .map(|x| x.get_holding_period() as f32)
+        .collect::<Vec<f32>>();
+    holding_periods.sort_by(|a, b| a.partial_cmp(b).unwrap()); // Derived from integers, hence never NaN.
+
+    // Unwraps are unreachable, as the holding periods are non-empty
+    let percentile =
+        |p: usize| functions::percentile_from_sorted_array(p, &holding_periods).unwrap();
+    let (max_consecutive_wins, max_consecutive_losses) = max_streaks(round_trips);
+
+    Ok(structs::RoundTripStatistics {
+        num_of_trades,
+        win_rate: wins.len() as f32 / num_of_trades as f32,
+        average_win: average(&wins),
+        average_loss: average(&losses),
+        profit_factor: wins.iter().sum::<f32>() / -losses.iter().sum::<f32>(),
+        expectancy: round_trips.iter().map(|x| x.pnl).sum::<f32>() / num_of_trades as f32,
+        mean_holding_period: holding_periods.iter().sum::<f32>() / num_of_trades as f32,
+        median_holding_period: percentile(50),
+        p90_holding_period: percentile(90),
+        max_holding_period: holding_periods[num_of_trades - 1] as usize,
+        mean_adverse_excursion: round_trips
+            .iter()
+            .map(|x| x.max_adverse_excursion)
+            .sum::<f32>()
+            / num_of_trades as f32,
+        mean_favorable_excursion: round_trips
+            .iter()
+            .map(|x| x.max_favorable_excursion)
+            .sum::<f32>()
+            / num_of_trades as f32,
+        max_consecutive_wins,
+        max_consecutive_losses,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backtest::enums::OrderSide;
+    use crate::datasets::structs::YahooFinancePriceRecord;
+    use chrono::{Duration, TimeZone};
+
+    const BARS: [(f32, f32, f32, f32); 8] = [
+        (10.0, 10.5, 9.5, 10.0),
+        (10.0, 11.0, 9.0, 10.5),
+        (10.5, 12.0, 10.0, 11.5),
+        (11.5, 12.5, 11.0, 12.0),
+        (12.0, 12.5, 10.5, 11.0),
+        (11.0, 11.5, 9.5, 10.0),
+        (10.0, 10.5, 8.5, 9.0),
+        (9.0, 10.0, 8.0, 9.5),
+    ];
+
+    fn test_fill(
+        index: usize,
+        side: OrderSide,
+        quantity: f32,
+        price: f32,
+        commission: f32,
+    ) -> Fill {
+        Fill {
+            index,
+            timestamp: chrono::Utc.ymd(2022, 1, 3).and_hms(0, 0, 0) + Duration::days(index as i64),
+            side,
+            quantity,
+            reference_price: price,
+            price,
+            commission,
+            slippage: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_trade_ledger() {
+        let record = YahooFinancePriceRecord::from_ohlc(&BARS);
+        let fills = [
+            test_fill(1, OrderSide::Buy, 100.0, 10.0, 2.0),
+            test_fill(2, OrderSide::Buy, 100.0, 11.0, 2.0),
+            test_fill(3, OrderSide::Sell, 150.0, 12.0, 3.0),
+            // Closes the remaining 50 shares, and reverses into a short of 50 shares
+            test_fill(5, OrderSide::Sell, 100.0, 11.0, 2.0),
+            test_fill(7, OrderSide::Buy, 50.0, 9.0, 1.0),
+        ];
+        let ledger = trade_ledger(&record, &fills).unwrap();
+        let round_trips = ledger.get_round_trips();
+        assert!(round_trips.len() == 4);
+        assert!(ledger.get_open_quantity() == 0.0);
+
+        // 100 shares from 10 to 12, with commissions of 0.02 + 0.02 per share
+        assert!(round_trips[0].get_quantity() == 100.0);
+        assert!((round_trips[0].get_pnl() - 196.0).abs() < 1e-4);
+        assert!(round_trips[0].get_holding_period() == 2);
+        // Lowest low of 9 and highest high of 12.5 over bars 1 to 3
+        assert!((round_trips[0].get_max_adverse_excursion() + 0.1).abs() < 1e-6);
+        assert!((round_trips[0].get_max_favorable_excursion() - 0.25).abs() < 1e-6);
+
+        // The second lot is split across the third and fourth fills
+        assert!(round_trips[1].get_quantity() == 50.0 && round_trips[1].get_entry_price() == 11.0);
+        assert!(round_trips[2].get_quantity() == 50.0 && round_trips[2].get_exit_index() == 5);
+        assert!((round_trips[2].get_pnl() - (0.0 - 50.0 * 0.04)).abs() < 1e-4);
+
+        // Short of 50 shares from 11 to 9
+        assert!(round_trips[3].get_direction() == enums::TradeDirection::Short);
+        assert!((round_trips[3].get_pnl() - (100.0 - 50.0 * 0.04)).abs() < 1e-4);
+        assert!((round_trips[3].get_max_favorable_excursion() - (1.0 - 8.0 / 11.0)).abs() < 1e-6);
+
+        assert!(trade_ledger(&record, &[test_fill(8, OrderSide::Buy, 1.0, 9.0, 0.0)]).is_err());
+        assert!(trade_ledger(&record, &[test_fill(1, OrderSide::Buy, 0.0, 9.0, 0.0)]).is_err());
+        assert!(
+            trade_ledger(&record, &[test_fill(1, OrderSide::Buy, f32::NAN, 9.0, 0.0)]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_round_trip_statistics() {
+        let record = YahooFinancePriceRecord::from_ohlc(&BARS);
+        let fills = [
+            test_fill(1, OrderSide::Buy, 100.0, 10.0, 0.0),
+            test_fill(2, OrderSide::Sell, 100.0, 11.0, 0.0),
+            test_fill(2, OrderSide::Buy, 100.0, 11.0, 0.0),
+            test_fill(3, OrderSide::Sell, 100.0, 12.0, 0.0),
+            test_fill(4, OrderSide::Buy, 100.0, 12.0, 0.0),
+            test_fill(5, OrderSide::Sell, 100.0, 11.0, 0.0),
+            test_fill(5, OrderSide::Buy, 100.0, 11.0, 0.0),
+            test_fill(7, OrderSide::Sell, 100.0, 9.5, 0.0),
+        ];
+        let ledger = trade_ledger(&record, &fills).unwrap();
+        let statistics = round_trip_statistics(ledger.get_round_trips()).unwrap();
+        // Net profits of 100, 100, -100 and -150
+        assert!(statistics.get_num_of_trades() == 4);
+        assert!(statistics.get_win_rate() == 0.5);
+        assert!(statistics.get_average_win() == Some(100.0));
+        assert!(statistics.get_average_loss() == Some(-125.0));
+        assert!((statistics.get_profit_factor() - 0.8).abs() < 1e-6);
+        assert!((statistics.get_expectancy() + 12.5).abs() < 1e-4);
+        assert!(statistics.get_mean_holding_period() == 1.25);
+        assert!(statistics.get_max_holding_period() == 2);
+        assert!(statistics.get_max_consecutive_wins() == 2);
+        assert!(statistics.get_max_consecutive_losses() == 2);
+        assert!(round_trip_statistics(&[]).is_err());
+    }
+}
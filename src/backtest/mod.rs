@@ -0,0 +1,633 @@
+//! Objective: Provide an event-driven backtester, replaying a record bar by bar through a strategy.
+//! The strategy only observes the bars up to the current bar, and its orders are filled against the OHLC prices of the
+//! next bar, such that no look-ahead is possible.
+
+use crate::datasets::traits::{Prices, Timestamps, Volume};
+use crate::errors::CalculationError;
+use crate::indicators::bands::atr;
+use crate::volatility::validate_ohlc_lengths;
+
+pub mod costs;
+pub mod ledger;
+pub mod optimization;
+pub mod sizing;
+
+pub mod enums {
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum OrderSide {
+        Buy,
+        Sell,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum OrderType {
+        Market,     // Filled at the open of the next bar
+        Limit(f32), // Filled at the limit price or better, if traded within the next bar
+        Stop(f32),  // Becomes a market order once the stop price trades within the next bar
+    }
+}
+
+pub mod structs {
+    use super::*;
+
+    /// Order submitted by a strategy. Orders are valid for the next bar only, hence unfilled limit and stop orders
+    /// have to be resubmitted.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct Order {
+        pub(super) side: enums::OrderSide,
+        pub(super) quantity: f32,
+        pub(super) order_type: enums::OrderType,
+    }
+
+    impl Order {
+        pub fn new(side: enums::OrderSide, quantity: f32, order_type: enums::OrderType) -> Self {
+            Order {
+                side,
+                quantity,
+                order_type,
+            }
+        }
+
+        pub fn get_side(&self) -> enums::OrderSide {
+            self.side
+        }
+
+        pub fn get_quantity(&self) -> f32 {
+            self.quantity
+        }
+
+        pub fn get_order_type(&self) -> enums::OrderType {
+            self.order_type
+        }
+    }
+
+    /// Fill of an order, with the transaction costs itemized.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct Fill {
+        pub(super) index: usize,
+        pub(super) timestamp: chrono::DateTime<chrono::Utc>,
+        pub(super) side: enums::OrderSide,
+        pub(super) quantity: f32,
+        pub(super) reference_price: f32, // Price triggered by the order, before slippage
+        pub(super) price: f32,           // Executed price, after slippage
+        pub(super) commission: f32,
+        pub(super) slippage: f32, // Total cost of the slippage, i.e. |price - reference price| * quantity
+    }
+
+    impl Fill {
+        pub fn get_index(&self) -> usize {
+            self.index
+        }
+
+        pub fn get_timestamp(&self) -> chrono::DateTime<chrono::Utc> {
+            self.timestamp
+        }
+
+        pub fn get_side(&self) -> enums::OrderSide {
+            self.side
+        }
+
+        pub fn get_quantity(&self) -> f32 {
+            self.quantity
+        }
+
+        pub fn get_reference_price(&self) -> f32 {
+            self.reference_price
+        }
+
+        pub fn get_price(&self) -> f32 {
+            self.price
+        }
+
+        pub fn get_commission(&self) -> f32 {
+            self.commission
+        }
+
+        pub fn get_slippage(&self) -> f32 {
+            self.slippage
+        }
+
+        /// Quantity signed by the side of the fill: positive for buys and negative for sells.
+        pub fn get_signed_quantity(&self) -> f32 {
+            match self.side {
+                enums::OrderSide::Buy => self.quantity,
+                enums::OrderSide::Sell => -self.quantity,
+            }
+        }
+    }
+
+    /// Bars of the record up to and including the current bar, as observed by the strategy.
+    /// Implements the record traits, such that indicators can be computed over the history.
+    #[derive(Clone, Copy, Debug)]
+    pub struct MarketView<'a> {
+        pub(super) timestamps: &'a [chrono::DateTime<chrono::Utc>],
+        pub(super) open_prices: &'a [f32],
+        pub(super) high_prices: &'a [f32],
+        pub(super) low_prices: &'a [f32],
+        pub(super) close_prices: &'a [f32],
+        pub(super) adj_close_prices: &'a [f32],
+        pub(super) volume: &'a [i32],
+        pub(super) currency: crate::enums::Currency,
+    }
+
+    impl<'a> MarketView<'a> {
+        /// Index of the current bar within the record.
+        pub fn get_current_index(&self) -> usize {
+            self.close_prices.len() - 1
+        }
+
+        pub fn get_current_close(&self) -> f32 {
+            self.close_prices[self.close_prices.len() - 1]
+        }
+    }
+
+    impl<'a> Prices for MarketView<'a> {
+        fn get_high_prices(&self) -> &[f32] {
+            self.high_prices
+        }
+
+        fn get_low_prices(&self) -> &[f32] {
+            self.low_prices
+        }
+
+        fn get_open_prices(&self) -> &[f32] {
+            self.open_prices
+        }
+
+        fn get_close_prices(&self) -> &[f32] {
+            self.close_prices
+        }
+
+        fn get_adj_close_prices(&self) -> &[f32] {
+            self.adj_close_prices
+        }
+
+        fn get_currency(&self) -> crate::enums::Currency {
+            self.currency
+        }
+    }
+
+    impl<'a> Timestamps for MarketView<'a> {
+        fn get_timestamps(&self) -> &[chrono::DateTime<chrono::Utc>] {
+            self.timestamps
+        }
+    }
+
+    impl<'a> Volume for MarketView<'a> {
+        fn get_volume(&self) -> &[i32] {
+            self.volume
+        }
+    }
+
+    /// Cash and position of the account, marked at the close of the current bar.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct AccountState {
+        pub(super) cash: f32,
+        pub(super) position: f32, // Number of shares held, negative for short positions
+        pub(super) equity: f32,
+    }
+
+    impl AccountState {
+        pub fn get_cash(&self) -> f32 {
+            self.cash
+        }
+
+        pub fn get_position(&self) -> f32 {
+            self.position
+        }
+
+        pub fn get_equity(&self) -> f32 {
+            self.equity
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct BacktestConfig {
+        pub(super) initial_cash: f32,
+        pub(super) commission_model: costs::enums::CommissionModel,
+        pub(super) slippage_model: costs::enums::SlippageModel,
+    }
+
+    impl BacktestConfig {
+        pub fn get_initial_cash(&self) -> f32 {
+            self.initial_cash
+        }
+
+        pub fn get_commission_model(&self) -> &costs::enums::CommissionModel {
+            &self.commission_model
+        }
+
+        pub fn get_slippage_model(&self) -> costs::enums::SlippageModel {
+            self.slippage_model
+        }
+    }
+
+    /// Account history aligned with the timestamps of the record, together with all the fills.
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct BacktestResult {
+        pub(super) timestamps: Vec<chrono::DateTime<chrono::Utc>>,
+        pub(super) cash: Vec<f32>,
+        pub(super) positions: Vec<f32>,
+        pub(super) equity_curve: Vec<f32>,
+        pub(super) fills: Vec<Fill>,
+    }
+
+    impl BacktestResult {
+        pub fn get_timestamps(&self) -> &[chrono::DateTime<chrono::Utc>] {
+            &self.timestamps
+        }
+
+        pub fn get_cash(&self) -> &[f32] {
+            &self.cash
+        }
+
+        pub fn get_positions(&self) -> &[f32] {
+            &self.positions
+        }
+
+        pub fn get_equity_curve(&self) -> &[f32] {
+            &self.equity_curve
+        }
+
+        pub fn get_fills(&self) -> &[Fill] {
+            &self.fills
+        }
+
+        pub fn get_total_commission(&self) -> f32 {
+            self.fills.iter().map(|fill| fill.commission).sum()
+        }
+
+        pub fn get_total_slippage(&self) -> f32 {
+            self.fills.iter().map(|fill| fill.slippage).sum()
+        }
+
+        /// Final equity divided by the initial equity, less 1.
+        pub fn get_total_return(&self) -> f32 {
+            match (self.equity_curve.first(), self.equity_curve.last()) {
+                (Some(first), Some(last)) => last / first - 1.0,
+                _ => f32::NAN,
+            }
+        }
+    }
+}
+
+pub mod traits {
+    use super::structs::{AccountState, MarketView, Order};
+
+    pub trait Strategy {
+        /// Called at the close of every bar with the history up to and including that bar.
+        /// The returned orders are filled against the next bar.
+        fn on_bar(&mut self, history: &MarketView, account: &AccountState) -> Vec<Order>;
+    }
+}
+
+impl structs::BacktestConfig {
+    pub fn new(initial_cash: f32) -> Result<Self, CalculationError> {
+        if initial_cash.is_nan() || initial_cash <= 0.0 {
+            return Err(CalculationError::InvalidParameterError(format!(
+                "Initial cash must be positive. Initial cash provided: {}",
+                initial_cash
+            )));
+        }
+        Ok(structs::BacktestConfig {
+            initial_cash,
+            commission_model: costs::enums::CommissionModel::Zero,
+            slippage_model: costs::enums::SlippageModel::Zero,
+        })
+    }
+
+    pub fn with_commission_model(
+        mut self,
+        commission_model: costs::enums::CommissionModel,
+    ) -> Result<Self, CalculationError> {
+        commission_model.validate()?;
+        self.commission_model = commission_model;
+        Ok(self)
+    }
+
+    pub fn with_slippage_model(
+        mut self,
+        slippage_model: costs::enums::SlippageModel,
+    ) -> Result<Self, CalculationError> {
+        slippage_model.validate()?;
+        self.slippage_model = slippage_model;
+        Ok(self)
+    }
+}
+
+/// Fill price of the order against the bar, None if the order is not triggered within the bar.
+/// Gaps through the limit or stop price are filled at the open.
+fn fill_price(order: &structs::Order, open: f32, high: f32, low: f32) -> Option<f32> {
+    match (order.order_type, order.side) {
+        (enums::OrderType::Market, _) => Some(open),
+        (enums::OrderType::Limit(limit), enums::OrderSide::Buy) => {
+            if open <= limit {
+                Some(open)
+            } else if low <= limit {
+                Some(limit)
+            } else {
+                None
+            }
+        }
+        (enums::OrderType::Limit(limit), enums::OrderSide::Sell) => {
+            if open >= limit {
+                Some(open)
+            } else if high >= limit {
+                Some(limit)
+            } else {
+                None
+            }
+        }
+        (enums::OrderType::Stop(stop), enums::OrderSide::Buy) => {
+            if open >= stop {
+                Some(open)
+            } else if high >= stop {
+                Some(stop)
+            } else {
+                None
+            }
+        }
+        (enums::OrderType::Stop(stop), enums::OrderSide::Sell) => {
+            if open <= stop {
+                Some(open)
+            } else if low <= stop {
+                Some(stop)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+/// Replays the record bar by bar through the strategy. Orders submitted at the close of a bar are filled against the
+/// next bar, with any orders submitted on the final bar discarded, as are orders of a non-finite or non-positive
+/// quantity. Short positions are allowed, and no margin requirements are imposed on the cash balance.
+/// Slippage is applied to market and stop orders only, as limit orders are filled at the limit price or better. The
+/// quantity filled of every order type is capped under the volume participation model.
+pub fn run_backtest<T, S>(
+    record: &T,
+    strategy: &mut S,
+    config: &structs::BacktestConfig,
+) -> Result<structs::BacktestResult, CalculationError>
+where
+    T: Prices + Timestamps + Volume,
+    S: traits::Strategy,
+{
+    let open = record.get_open_prices();
+    let high = record.get_high_prices();
+    let low = record.get_low_prices();
+    let close = record.get_close_prices();
+    let length = validate_ohlc_lengths(open, high, low, close)?;
+    let timestamps = record.get_timestamps();
+    let adj_close = record.get_adj_close_prices();
+    let volume = record.get_volume();
+    if timestamps.len() != length || adj_close.len() != length || volume.len() != length {
+        return Err(CalculationError::InconsistentLengthError(format!(
+            "Length of the timestamps array: {} \n Length of the adjusted close array: {} \n Length of the volume array: {} \n Length of the prices array: {}",
+            timestamps.len(),
+            adj_close.len(),
+            volume.len(),
+            length
+        )));
+    }
+
+    let average_true_range = match config.slippage_model {
+        costs::enums::SlippageModel::AtrFraction { period, .. } => atr(record, period)?,
+        _ => vec![f32::NAN; length],
+    };
+
+    let mut cash = config.initial_cash;
+    let mut position = 0.0_f32;
+    let mut pending_orders: Vec<structs::Order> = Vec::new();
+    let mut result = structs::BacktestResult {
+        timestamps: timestamps.to_vec(),
+        cash: Vec::with_capacity(length),
+        positions: Vec::with_capacity(length),
+        equity_curve: Vec::with_capacity(length),
+        fills: Vec::new(),
+    };
+
+    for idx in 0..length {
+        // Filling the orders submitted at the previous close
+        for order in pending_orders.drain(..) {
+            if !order.quantity.is_finite() || order.quantity <= 0.0 {
+                continue;
+            }
+            let reference_price = match fill_price(&order, open[idx], high[idx], low[idx]) {
+                Some(price) => price,
+                None => continue,
+            };
+            let (quantity, slippage_per_share) = costs::slippage(
+                &config.slippage_model,
+                order.quantity,
+                reference_price,
+                average_true_range[idx - 1], // Orders are never filled on the first bar
+                volume[idx],
+            );
+            // Limit orders fill at the limit price or better, though still capped by the volume participation
+            let slippage_per_share = match order.order_type {
+                enums::OrderType::Limit(_) => 0.0,
+                _ => slippage_per_share,
+            };
+            if quantity <= 0.0 {
+                continue;
+            }
+            let price = match order.side {
+                enums::OrderSide::Buy => reference_price + slippage_per_share,
+                enums::OrderSide::Sell => reference_price - slippage_per_share,
+            };
+            let fill = structs::Fill {
+                index: idx,
+                timestamp: timestamps[idx],
+                side: order.side,
+                quantity,
+                reference_price,
+                price,
+                commission: costs::commission(
+                    &config.commission_model,
+                    order.side,
+                    quantity,
+                    price,
+                ),
+                slippage: slippage_per_share * quantity,
+            };
+            position += fill.get_signed_quantity();
+            cash -= fill.get_signed_quantity() * price + fill.commission;
+            result.fills.push(fill);
+        }
+
+        let account = structs::AccountState {
+            cash,
+            position,
+            equity: cash + position * close[idx],
+        };
+        result.cash.push(account.cash);
+        result.positions.push(account.position);
+        result.equity_curve.push(account.equity);
+
+        let history = structs::MarketView {
+            timestamps: &timestamps[..=idx],
+            open_prices: &open[..=idx],
+            high_prices: &high[..=idx],
+            low_prices: &low[..=idx],
+            close_prices: &close[..=idx],
+            adj_close_prices: &adj_close[..=idx],
+            volume: &volume[..=idx],
+            currency: record.get_currency(),
+        };
+        let orders = strategy.on_bar(&history, &account);
+        if idx + 1 < length {
+            pending_orders = orders;
+        }
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datasets;
+
+    /// Submits a fixed list of orders on the given bars, recording the length of the history observed.
+    struct ScriptedStrategy {
+        orders: Vec<(usize, structs::Order)>,
+        observed_lengths: Vec<usize>,
+    }
+
+    impl traits::Strategy for ScriptedStrategy {
+        fn on_bar(
+            &mut self,
+            history: &structs::MarketView,
+            _account: &structs::AccountState,
+        ) -> Vec<structs::Order> {
+            self.observed_lengths.push(history.get_close_prices().len());
+            self.orders
+                .iter()
+                .filter(|(idx, _)| *idx == history.get_current_index())
+                .map(|(_, order)| *order)
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_order_fills() {
+        let buy_limit =
+            structs::Order::new(enums::OrderSide::Buy, 1.0, enums::OrderType::Limit(9.5));
+        let sell_stop =
+            structs::Order::new(enums::OrderSide::Sell, 1.0, enums::OrderType::Stop(9.0));
+        // Limit triggered intrabar, and gapped through at the open
+        assert!(fill_price(&buy_limit, 10.0, 10.5, 9.0) == Some(9.5));
+        assert!(fill_price(&buy_limit, 9.2, 10.5, 9.0) == Some(9.2));
+        assert!(fill_price(&buy_limit, 10.0, 10.5, 9.6).is_none());
+        assert!(fill_price(&sell_stop, 10.0, 10.5, 8.5) == Some(9.0));
+        assert!(fill_price(&sell_stop, 8.0, 10.5, 7.5) == Some(8.0));
+    }
+
+    #[test]
+    fn test_run_backtest() {
+        let record = datasets::structs::YahooFinancePriceRecord::from_ohlc(&[
+            (10.0, 10.0, 10.0, 10.0),
+            (11.0, 12.0, 10.5, 12.0),
+            (12.0, 13.0, 11.0, 11.0),
+            (11.0, 11.0, 9.0, 9.5),
+        ]);
+        let mut strategy = ScriptedStrategy {
+            orders: vec![
+                (
+                    0,
+                    structs::Order::new(enums::OrderSide::Buy, 10.0, enums::OrderType::Market),
+                ),
+                (
+                    1,
+                    structs::Order::new(
+                        enums::OrderSide::Sell,
+                        10.0,
+                        enums::OrderType::Limit(12.5),
+                    ),
+                ),
+                (
+                    2,
+                    structs::Order::new(enums::OrderSide::Buy, f32::NAN, enums::OrderType::Market),
+                ),
+                (
+                    3,
+                    structs::Order::new(enums::OrderSide::Sell, 10.0, enums::OrderType::Market),
+                ),
+            ],
+            observed_lengths: Vec::new(),
+        };
+        let config = structs::BacktestConfig::new(1000.0).unwrap();
+        let result = run_backtest(&record, &mut strategy, &config).unwrap();
+
+        // The strategy never observes bars beyond the current bar
+        assert!(strategy.observed_lengths == vec![1, 2, 3, 4]);
+        // Bought at the open of bar 1, sold at the limit within bar 2, with the NaN quantity and final orders discarded
+        assert!(result.get_fills().len() == 2);
+        assert!(
+            result.get_fills()[0].get_price() == 11.0 && result.get_fills()[0].get_index() == 1
+        );
+        assert!(result.get_fills()[1].get_price() == 12.5);
+        assert!(result.get_positions() == [0.0, 10.0, 0.0, 0.0]);
+        assert!(result.get_equity_curve() == [1000.0, 1010.0, 1015.0, 1015.0]);
+        assert!((result.get_total_return() - 0.015).abs() < 1e-6);
+        assert!(structs::BacktestConfig::new(0.0).is_err());
+    }
+
+    #[test]
+    fn test_run_backtest_with_costs() {
+        let record = datasets::structs::YahooFinancePriceRecord::from_ohlc(&[
+            (10.0, 10.0, 10.0, 10.0),
+            (10.0, 10.5, 9.5, 10.0),
+            (10.0, 10.5, 9.5, 10.0),
+        ]);
+        let mut strategy = ScriptedStrategy {
+            orders: vec![
+                (
+                    0,
+                    structs::Order::new(enums::OrderSide::Buy, 100.0, enums::OrderType::Market),
+                ),
+                (
+                    1,
+                    structs::Order::new(
+                        enums::OrderSide::Sell,
+                        100.0,
+                        enums::OrderType::Limit(10.5),
+                    ),
+                ),
+            ],
+            observed_lengths: Vec::new(),
+        };
+        let config = structs::BacktestConfig::new(10000.0)
+            .unwrap()
+            .with_commission_model(costs::enums::CommissionModel::PerTrade(2.0))
+            .unwrap()
+            .with_slippage_model(costs::enums::SlippageModel::FixedBps(100.0))
+            .unwrap();
+        let result = run_backtest(&record, &mut strategy, &config).unwrap();
+
+        // The market order slips by 1%, while the limit order fills at the limit price
+        let fills = result.get_fills();
+        assert!(fills[0].get_reference_price() == 10.0 && fills[0].get_price() == 10.1);
+        assert!((fills[0].get_slippage() - 10.0).abs() < 1e-4);
+        assert!(fills[1].get_price() == 10.5 && fills[1].get_slippage() == 0.0);
+        assert!(result.get_total_commission() == 4.0);
+        // 10000 - 1010 - 2 + 1050 - 2
+        assert!((result.get_equity_curve()[2] - 10036.0).abs() < 1e-3);
+
+        // Participation of 5% of the volume of 1000 caps both orders at 50 shares
+        let config = structs::BacktestConfig::new(10000.0)
+            .unwrap()
+            .with_slippage_model(costs::enums::SlippageModel::VolumeParticipation {
+                max_participation: 0.05,
+                impact_coefficient: 0.1,
+            })
+            .unwrap();
+        let result = run_backtest(&record, &mut strategy, &config).unwrap();
+        let fills = result.get_fills();
+        assert!(fills[0].get_quantity() == 50.0 && fills[0].get_price() > 10.0);
+        assert!(fills[1].get_quantity() == 50.0);
+        assert!(fills[1].get_price() == 10.5 && fills[1].get_slippage() == 0.0);
+    }
+}
@@ -0,0 +1,734 @@
+//! Parameter search over strategies, and walk-forward analysis comparing in-sample with out-of-sample performance.
+
+use std::ops::Range;
+
+use itertools::Itertools;
+use rand::Rng;
+use rand::SeedableRng;
+use rayon::prelude::*;
+
+use super::run_backtest;
+use super::structs::{BacktestConfig, MarketView};
+use super::traits::Strategy;
+use crate::datasets::traits::{Prices, Timestamps, Volume};
+use crate::drawdowns;
+use crate::errors::CalculationError;
+use crate::performance;
+use crate::returns;
+use crate::statistics;
+
+pub mod enums {
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum Objective {
+        SharpeRatio, // Against a risk-free rate of 0
+        Cagr,
+        TotalReturn,
+        /// CAGR of the parameter sets whose maximum drawdown stays within the limit, as a positive fraction
+        /// (e.g. 0.2 for 20%). Parameter sets breaching the limit are ranked last.
+        DrawdownConstrainedCagr(f32),
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum SearchMethod {
+        Grid,
+        /// Parameter sets drawn uniformly from the values of each range.
+        Random {
+            num_of_samples: usize,
+            seed: u64,
+        },
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum WalkForwardWindow {
+        Anchored, // In-sample windows all start on the first bar, growing with every fold
+        Rolling,  // In-sample windows of fixed length, rolled forward with every fold
+    }
+}
+
+pub mod structs {
+    use super::*;
+
+    /// Candidate values of a strategy parameter.
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct ParameterRange {
+        pub(super) name: String,
+        pub(super) values: Vec<f32>,
+    }
+
+    impl ParameterRange {
+        pub fn get_name(&self) -> &str {
+            &self.name
+        }
+
+        pub fn get_values(&self) -> &[f32] {
+            &self.values
+        }
+    }
+
+    /// Values assigned to the named parameters, passed to the strategy factory.
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct ParameterSet {
+        pub(super) names: Vec<String>,
+        pub(super) values: Vec<f32>,
+    }
+
+    impl ParameterSet {
+        pub fn get(&self, name: &str) -> Option<f32> {
+            self.names
+                .iter()
+                .position(|x| x == name)
+                .map(|idx| self.values[idx])
+        }
+
+        pub fn get_names(&self) -> &[String] {
+            &self.names
+        }
+
+        pub fn get_values(&self) -> &[f32] {
+            &self.values
+        }
+    }
+
+    /// Performance of an equity curve, with the maximum drawdown as a negative fraction (e.g. -0.2 for 20%).
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct PerformanceMetrics {
+        pub(super) total_return: f32,
+        pub(super) cagr: f32,
+        pub(super) sharpe_ratio: f32,
+        pub(super) max_drawdown: f32,
+    }
+
+    impl PerformanceMetrics {
+        pub fn get_total_return(&self) -> f32 {
+            self.total_return
+        }
+
+        pub fn get_cagr(&self) -> f32 {
+            self.cagr
+        }
+
+        pub fn get_sharpe_ratio(&self) -> f32 {
+            self.sharpe_ratio
+        }
+
+        pub fn get_max_drawdown(&self) -> f32 {
+            self.max_drawdown
+        }
+
+        /// Score under the objective, where higher is better. Undefined scores (e.g. the Sharpe ratio of a strategy
+        /// that never trades) and breaches of the drawdown limit score negative infinity.
+        pub fn get_score(&self, objective: enums::Objective) -> f32 {
+            let score = match objective {
+                enums::Objective::SharpeRatio => self.sharpe_ratio,
+                enums::Objective::Cagr => self.cagr,
+                enums::Objective::TotalReturn => self.total_return,
+                enums::Objective::DrawdownConstrainedCagr(limit) => {
+                    if -self.max_drawdown <= limit {
+                        self.cagr
+                    } else {
+                        f32::NEG_INFINITY
+                    }
+                }
+            };
+            if score.is_nan() {
+                f32::NEG_INFINITY
+            } else {
+                score
+            }
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct Evaluation {
+        pub(super) parameters: ParameterSet,
+        pub(super) metrics: PerformanceMetrics,
+        pub(super) score: f32,
+    }
+
+    impl Evaluation {
+        pub fn get_parameters(&self) -> &ParameterSet {
+            &self.parameters
+        }
+
+        pub fn get_metrics(&self) -> PerformanceMetrics {
+            self.metrics
+        }
+
+        pub fn get_score(&self) -> f32 {
+            self.score
+        }
+    }
+
+    /// Number of bars of the in-sample and out-of-sample windows of each fold.
+    /// For anchored windows, the in-sample length is that of the first fold.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct WalkForwardConfig {
+        pub(super) window: enums::WalkForwardWindow,
+        pub(super) in_sample_length: usize,
+        pub(super) out_of_sample_length: usize,
+    }
+
+    impl WalkForwardConfig {
+        pub fn get_window(&self) -> enums::WalkForwardWindow {
+            self.window
+        }
+
+        pub fn get_in_sample_length(&self) -> usize {
+            self.in_sample_length
+        }
+
+        pub fn get_out_of_sample_length(&self) -> usize {
+            self.out_of_sample_length
+        }
+    }
+
+    /// Fold of a walk-forward analysis, with the bar index ranges of its windows within the record.
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct WalkForwardFold {
+        pub(super) in_sample_range: Range<usize>,
+        pub(super) out_of_sample_range: Range<usize>,
+        pub(super) best_parameters: ParameterSet,
+        pub(super) in_sample: PerformanceMetrics,
+        pub(super) out_of_sample: PerformanceMetrics,
+    }
+
+    impl WalkForwardFold {
+        pub fn get_in_sample_range(&self) -> Range<usize> {
+            self.in_sample_range.clone()
+        }
+
+        pub fn get_out_of_sample_range(&self) -> Range<usize> {
+            self.out_of_sample_range.clone()
+        }
+
+        pub fn get_best_parameters(&self) -> &ParameterSet {
+            &self.best_parameters
+        }
+
+        pub fn get_in_sample(&self) -> PerformanceMetrics {
+            self.in_sample
+        }
+
+        pub fn get_out_of_sample(&self) -> PerformanceMetrics {
+            self.out_of_sample
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct WalkForwardResult {
+        pub(super) objective: enums::Objective,
+        pub(super) folds: Vec<WalkForwardFold>,
+    }
+
+    impl WalkForwardResult {
+        pub fn get_objective(&self) -> enums::Objective {
+            self.objective
+        }
+
+        pub fn get_folds(&self) -> &[WalkForwardFold] {
+            &self.folds
+        }
+
+        /// Mean in-sample score over the folds with a finite score, NaN if there are none.
+        pub fn get_mean_in_sample_score(&self) -> f32 {
+            statistics::mean(&self.finite_scores(|fold| fold.in_sample))
+        }
+
+        /// Mean out-of-sample score over the folds with a finite score, NaN if there are none.
+        pub fn get_mean_out_of_sample_score(&self) -> f32 {
+            statistics::mean(&self.finite_scores(|fold| fold.out_of_sample))
+        }
+
+        /// Number of folds excluded from the mean in-sample score, as their score is undefined or breaches the
+        /// drawdown limit.
+        pub fn get_num_of_excluded_in_sample_folds(&self) -> usize {
+            self.folds.len() - self.finite_scores(|fold| fold.in_sample).len()
+        }
+
+        /// Number of folds excluded from the mean out-of-sample score, as their score is undefined or breaches the
+        /// drawdown limit.
+        pub fn get_num_of_excluded_out_of_sample_folds(&self) -> usize {
+            self.folds.len() - self.finite_scores(|fold| fold.out_of_sample).len()
+        }
+
+        /// Ratio of the mean out-of-sample score to the mean in-sample score. Ratios well below 1 suggest that the
+        /// in-sample optimum is overfitted. NaN if either mean is undefined.
+        pub fn get_walk_forward_efficiency(&self) -> f32 {
+            self.get_mean_out_of_sample_score() / self.get_mean_in_sample_score()
+        }
+
+        fn finite_scores<F>(&self, metrics: F) -> Vec<f32>
+        where
+            F: Fn(&WalkForwardFold) -> PerformanceMetrics,
+        {
+            self.folds
+                .iter()
+                .map(|fold| metrics(fold).get_score(self.objective))
+                .filter(|score| score.is_finite())
+                .collect()
+        }
+    }
+}
+
+impl structs::ParameterRange {
+    /// Values from the start to the end inclusive, in increments of the step.
+    pub fn new(name: &str, start: f32, end: f32, step: f32) -> Result<Self, CalculationError> {
+        if step.is_nan() || step <= 0.0 || start.is_nan() || end.is_nan() || end < start {
+            return Err(CalculationError::InvalidParameterError(format!(
+                "Parameter range requires a positive step and an end no less than the start. Range provided: {} to {} by {}",
+                start, end, step
+            )));
+        }
+        // Tolerating floating point error at the end of the range
+        let num_of_steps = ((end - start) / step + 1e-4).floor() as usize;
+        Ok(structs::ParameterRange {
+            name: name.to_owned(),
+            values: (0..=num_of_steps)
+                .map(|idx| start + idx as f32 * step)
+                .collect(),
+        })
+    }
+
+    pub fn from_values(name: &str, values: &[f32]) -> Result<Self, CalculationError> {
+        if values.is_empty() {
+            return Err(CalculationError::InvalidParameterError(format!(
+                "Parameter {} requires at least 1 value.",
+                name
+            )));
+        }
+        Ok(structs::ParameterRange {
+            name: name.to_owned(),
+            values: values.to_vec(),
+        })
+    }
+}
+
+impl structs::WalkForwardConfig {
+    pub fn new(
+        window: enums::WalkForwardWindow,
+        in_sample_length: usize,
+        out_of_sample_length: usize,
+    ) -> Result<Self, CalculationError> {
+        if in_sample_length < 2 || out_of_sample_length == 0 {
+            return Err(CalculationError::InvalidParameterError(format!(
+                "Walk-forward windows require at least 2 in-sample bars and 1 out-of-sample bar. In-sample length provided: {} \n Out-of-sample length provided: {}",
+                in_sample_length, out_of_sample_length
+            )));
+        }
+        Ok(structs::WalkForwardConfig {
+            window,
+            in_sample_length,
+            out_of_sample_length,
+        })
+    }
+}
+
+/// Parameter sets to be evaluated: the cartesian product of the ranges for a grid search, or sets drawn uniformly
+/// for a random search.
+pub fn parameter_sets(
+    ranges: &[structs::ParameterRange],
+    method: enums::SearchMethod,
+) -> Result<Vec<structs::ParameterSet>, CalculationError> {
+    if ranges.is_empty() {
+        return Err(CalculationError::InvalidParameterError(
+            "At least 1 parameter range is required.".to_string(),
+        ));
+    }
+    let names = ranges
+        .iter()
+        .map(|range| range.name.clone())
+        .collect::<Vec<String>>();
+
+    let sets = match method {
+        enums::SearchMethod::Grid => ranges
+            .iter()
+            .map(|range| range.values.iter().copied())
+            .multi_cartesian_product()
+            .map(|values| structs::ParameterSet {
+                names: names.clone(),
+                values,
+            })
+            .collect(),
+        enums::SearchMethod::Random {
+            num_of_samples,
+            seed,
+        } => {
+            if num_of_samples == 0 {
+                return Err(CalculationError::InvalidParameterError(
+                    "Random search requires at least 1 sample.".to_string(),
+                ));
+            }
+            let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+            (0..num_of_samples)
+                .map(|_| structs::ParameterSet {
+                    names: names.clone(),
+                    values: ranges
+                        .iter()
+                        .map(|range| range.values[rng.gen_range(0..range.values.len())])
+                        .collect(),
+                })
+                .collect()
+        }
+    };
+    Ok(sets)
+}
+
+/// Performance of the equity curve, annualized at the bar frequency detected from the timestamps.
+pub fn performance_metrics(
+    timestamps: &[chrono::DateTime<chrono::Utc>],
+    equity_curve: &[f32],
+) -> Result<structs::PerformanceMetrics, CalculationError> {
+    let frequency = returns::detect_frequency(timestamps)?;
+    let cagr = performance::cagr(timestamps, equity_curve)?;
+    let equity_returns = returns::simple_returns(equity_curve);
+    Ok(structs::PerformanceMetrics {
+        total_return: equity_curve[equity_curve.len() - 1] / equity_curve[0] - 1.0,
+        cagr,
+        sharpe_ratio: performance::sharpe_ratio(&equity_returns[1..], 0.0, frequency),
+        max_drawdown: drawdowns::drawdown_series(equity_curve)
+            .into_iter()
+            .fold(0.0, f32::min),
+    })
+}
+
+/// Bars of the record within the range, viewed without copying.
+fn window_view<T>(record: &T, range: Range<usize>) -> MarketView<'_>
+where
+    T: Prices + Timestamps + Volume,
+{
+    MarketView {
+        timestamps: &record.get_timestamps()[range.clone()],
+        open_prices: &record.get_open_prices()[range.clone()],
+        high_prices: &record.get_high_prices()[range.clone()],
+        low_prices: &record.get_low_prices()[range.clone()],
+        close_prices: &record.get_close_prices()[range.clone()],
+        adj_close_prices: &record.get_adj_close_prices()[range.clone()],
+        volume: &record.get_volume()[range],
+        currency: record.get_currency(),
+    }
+}
+
+/// Backtests a fresh strategy built from every parameter set in parallel across the available cores.
+/// Evaluations are returned in descending order of their score under the objective.
+pub fn optimize<T, S, F>(
+    record: &T,
+    ranges: &[structs::ParameterRange],
+    method: enums::SearchMethod,
+    objective: enums::Objective,
+    config: &BacktestConfig,
+    strategy_factory: F,
+) -> Result<Vec<structs::Evaluation>, CalculationError>
+where
+    T: Prices + Timestamps + Volume + Sync,
+    S: Strategy,
+    F: Fn(&structs::ParameterSet) -> S + Sync,
+{
+    let mut evaluations = parameter_sets(ranges, method)?
+        .into_par_iter()
+        .map(|parameters| {
+            let mut strategy = strategy_factory(&parameters);
+            let result = run_backtest(record, &mut strategy, config)?;
+            let metrics = performance_metrics(result.get_timestamps(), result.get_equity_curve())?;
+            Ok(structs::Evaluation {
+                score: metrics.get_score(objective),
+                parameters,
+                metrics,
+            })
+        })
+        .collect::<Result<Vec<structs::Evaluation>, CalculationError>>()?;
+    // Scores are never NaN, and the sort is stable such that ties keep the order of the parameter sets
+    evaluations.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+    Ok(evaluations)
+}
+
+/// In-sample and out-of-sample index ranges of the walk-forward folds, with the out-of-sample windows tiling the
+/// record after the first in-sample window. The trailing out-of-sample window may be shorter than configured.
+pub fn walk_forward_ranges(
+    length: usize,
+    walk_forward_config: &structs::WalkForwardConfig,
+) -> Vec<(Range<usize>, Range<usize>)> {
+    let mut ranges = Vec::new();
+    let mut split = walk_forward_config.in_sample_length;
+    while split < length {
+        let in_sample_start = match walk_forward_config.window {
+            enums::WalkForwardWindow::Anchored => 0,
+            enums::WalkForwardWindow::Rolling => split - walk_forward_config.in_sample_length,
+        };
+        let out_of_sample_end = (split + walk_forward_config.out_of_sample_length).min(length);
+        ranges.push((in_sample_start..split, split..out_of_sample_end));
+        split = out_of_sample_end;
+    }
+    ranges
+}
+
+/// Walk-forward analysis: for every fold, the parameters are optimized over the in-sample window, then traded over
+/// the out-of-sample window that follows.
+/// The out-of-sample backtest is run from the start of the in-sample window, such that indicators are warmed up and
+/// positions carry over, with the out-of-sample performance measured from the close of the last in-sample bar.
+pub fn walk_forward<T, S, F>(
+    record: &T,
+    ranges: &[structs::ParameterRange],
+    method: enums::SearchMethod,
+    objective: enums::Objective,
+    config: &BacktestConfig,
+    walk_forward_config: &structs::WalkForwardConfig,
+    strategy_factory: F,
+) -> Result<structs::WalkForwardResult, CalculationError>
+where
+    T: Prices + Timestamps + Volume + Sync,
+    S: Strategy,
+    F: Fn(&structs::ParameterSet) -> S + Sync,
+{
+    let length = record.get_close_prices().len();
+    let fold_ranges = walk_forward_ranges(length, walk_forward_config);
+    if fold_ranges.is_empty() {
+        return Err(CalculationError::InsufficientDataError(format!(
+            "Record of {} bars is too short for an in-sample window of {} bars.",
+            length, walk_forward_config.in_sample_length
+        )));
+    }
+
+    let mut folds = Vec::with_capacity(fold_ranges.len());
+    for (in_sample_range, out_of_sample_range) in fold_ranges {
+        let in_sample_view = window_view(record, in_sample_range.clone());
+        let best = optimize(
+            &in_sample_view,
+            ranges,
+            method,
+            objective,
+            config,
+            &strategy_factory,
+        )?
+        .swap_remove(0);
+
+        let full_view = window_view(record, in_sample_range.start..out_of_sample_range.end);
+        let mut strategy = strategy_factory(&best.parameters);
+        let result = run_backtest(&full_view, &mut strategy, config)?;
+        let out_of_sample_start = out_of_sample_range.start - in_sample_range.start - 1;
+        let out_of_sample = performance_metrics(
+            &result.get_timestamps()[out_of_sample_start..],
+            &result.get_equity_curve()[out_of_sample_start..],
+        )?;
+
+        folds.push(structs::WalkForwardFold {
+            in_sample_range,
+            out_of_sample_range,
+            best_parameters: best.parameters,
+            in_sample: best.metrics,
+            out_of_sample,
+        });
+    }
+
+    Ok(structs::WalkForwardResult { objective, folds })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backtest::enums::{OrderSide, OrderType};
+    use crate::backtest::structs::{AccountState, Order};
+    use crate::datasets::structs::YahooFinancePriceRecord;
+    use crate::indicators::moving_averages::sma_unchecked;
+
+    /// Long only moving average crossover, fully invested while the fast average is above the slow average.
+    struct MovingAverageCrossover {
+        fast_period: usize,
+        slow_period: usize,
+    }
+
+    impl Strategy for MovingAverageCrossover {
+        fn on_bar(&mut self, history: &MarketView, account: &AccountState) -> Vec<Order> {
+            let close = history.get_close_prices();
+            if close.len() < self.slow_period {
+                return Vec::new();
+            }
+            let fast = sma_unchecked(close, self.fast_period);
+            let slow = sma_unchecked(close, self.slow_period);
+            let is_bullish = fast[fast.len() - 1] > slow[slow.len() - 1];
+            if is_bullish && account.get_position() == 0.0 {
+                let quantity = (account.get_cash() / history.get_current_close()).floor();
+                vec![Order::new(OrderSide::Buy, quantity, OrderType::Market)]
+            } else if !is_bullish && account.get_position() > 0.0 {
+                vec![Order::new(
+                    OrderSide::Sell,
+                    account.get_position(),
+                    OrderType::Market,
+                )]
+            } else {
+                Vec::new()
+            }
+        }
+    }
+
+    fn crossover_factory(parameters: &structs::ParameterSet) -> MovingAverageCrossover {
+        MovingAverageCrossover {
+            fast_period: parameters.get("fast").unwrap() as usize,
+            slow_period: parameters.get("slow").unwrap() as usize,
+        }
+    }
+
+    /// Upward drifting sine wave, crossing its moving averages every few weeks.
+    fn sine_closes() -> Vec<f32> {
+        (0..120)
+            .map(|idx| 100.0 + 0.1 * idx as f32 + 5.0 * (idx as f32 / 8.0).sin())
+            .collect()
+    }
+
+    fn test_ranges() -> Vec<structs::ParameterRange> {
+        vec![
+            structs::ParameterRange::new("fast", 2.0, 6.0, 2.0).unwrap(),
+            structs::ParameterRange::from_values("slow", &[10.0, 20.0]).unwrap(),
+        ]
+    }
+
+    #[test]
+    fn test_parameter_sets() {
+        let ranges = test_ranges();
+        assert!(ranges[0].get_values() == [2.0, 4.0, 6.0]);
+        let grid = parameter_sets(&ranges, enums::SearchMethod::Grid).unwrap();
+        assert!(grid.len() == 6);
+        assert!(grid[1].get_values() == [2.0, 20.0] && grid[1].get("slow") == Some(20.0));
+
+        let method = enums::SearchMethod::Random {
+            num_of_samples: 5,
+            seed: 7,
+        };
+        let random = parameter_sets(&ranges, method).unwrap();
+        assert!(random.len() == 5 && random == parameter_sets(&ranges, method).unwrap());
+        assert!(random.iter().all(|set| grid.contains(set)));
+        assert!(structs::ParameterRange::new("fast", 6.0, 2.0, 2.0).is_err());
+    }
+
+    #[test]
+    fn test_optimize() {
+        let record = YahooFinancePriceRecord::from_closes(&sine_closes());
+        let config = BacktestConfig::new(10000.0).unwrap();
+        let evaluations = optimize(
+            &record,
+            &test_ranges(),
+            enums::SearchMethod::Grid,
+            enums::Objective::Cagr,
+            &config,
+            crossover_factory,
+        )
+        .unwrap();
+        assert!(evaluations.len() == 6);
+        assert!(evaluations
+            .windows(2)
+            .all(|pair| pair[0].get_score() >= pair[1].get_score()));
+
+        // The best evaluation matches a standalone backtest of its parameters
+        let best = &evaluations[0];
+        let result = run_backtest(
+            &record,
+            &mut crossover_factory(best.get_parameters()),
+            &config,
+        )
+        .unwrap();
+        let metrics =
+            performance_metrics(result.get_timestamps(), result.get_equity_curve()).unwrap();
+        assert!(metrics == best.get_metrics());
+        assert!(best.get_score() == metrics.get_cagr());
+
+        // A drawdown limit of 0 rejects every parameter set that ever loses money
+        let constrained = optimize(
+            &record,
+            &test_ranges(),
+            enums::SearchMethod::Grid,
+            enums::Objective::DrawdownConstrainedCagr(0.0),
+            &config,
+            crossover_factory,
+        )
+        .unwrap();
+        assert!(constrained.iter().all(
+            |x| x.get_score() == f32::NEG_INFINITY || x.get_metrics().get_max_drawdown() == 0.0
+        ));
+    }
+
+    #[test]
+    fn test_walk_forward_ranges() {
+        let anchored =
+            structs::WalkForwardConfig::new(enums::WalkForwardWindow::Anchored, 50, 30).unwrap();
+        assert!(
+            walk_forward_ranges(120, &anchored)
+                == [(0..50, 50..80), (0..80, 80..110), (0..110, 110..120)]
+        );
+        let rolling =
+            structs::WalkForwardConfig::new(enums::WalkForwardWindow::Rolling, 50, 30).unwrap();
+        assert!(
+            walk_forward_ranges(120, &rolling)
+                == [(0..50, 50..80), (30..80, 80..110), (60..110, 110..120)]
+        );
+        assert!(walk_forward_ranges(50, &rolling).is_empty());
+        assert!(structs::WalkForwardConfig::new(enums::WalkForwardWindow::Rolling, 50, 0).is_err());
+    }
+
+    #[test]
+    fn test_walk_forward() {
+        let record = YahooFinancePriceRecord::from_closes(&sine_closes());
+        let config = BacktestConfig::new(10000.0).unwrap();
+        let walk_forward_config =
+            structs::WalkForwardConfig::new(enums::WalkForwardWindow::Rolling, 60, 30).unwrap();
+        let result = walk_forward(
+            &record,
+            &test_ranges(),
+            enums::SearchMethod::Grid,
+            enums::Objective::TotalReturn,
+            &config,
+            &walk_forward_config,
+            crossover_factory,
+        )
+        .unwrap();
+        assert!(result.get_folds().len() == 2);
+
+        // The out-of-sample performance is measured from the close of the last in-sample bar
+        let fold = &result.get_folds()[1];
+        let full_view = window_view(&record, 30..120);
+        let backtest = run_backtest(
+            &full_view,
+            &mut crossover_factory(fold.get_best_parameters()),
+            &config,
+        )
+        .unwrap();
+        let equity = backtest.get_equity_curve();
+        assert!(
+            (fold.get_out_of_sample().get_total_return() - (equity[89] / equity[59] - 1.0)).abs()
+                < 1e-6
+        );
+        assert!(
+            (result.get_mean_in_sample_score()
+                - (result.get_folds()[0].get_in_sample().get_total_return()
+                    + fold.get_in_sample().get_total_return())
+                    / 2.0)
+                .abs()
+                < 1e-6
+        );
+        assert!(
+            result.get_num_of_excluded_in_sample_folds() == 0
+                && result.get_num_of_excluded_out_of_sample_folds() == 0
+        );
+
+        // Folds with an undefined score are excluded from the mean score, rather than turning it into NaN
+        let mut partially_scored = result.clone();
+        partially_scored.folds[0].out_of_sample.total_return = f32::NAN;
+        assert!(partially_scored.get_num_of_excluded_out_of_sample_folds() == 1);
+        assert!(
+            partially_scored.get_mean_out_of_sample_score()
+                == fold.get_out_of_sample().get_total_return()
+        );
+        assert!(partially_scored.get_walk_forward_efficiency().is_finite());
+        partially_scored.folds[1].out_of_sample.total_return = f32::NAN;
+        assert!(partially_scored.get_mean_out_of_sample_score().is_nan());
+        assert!(walk_forward(
+            &record,
+            &test_ranges(),
+            enums::SearchMethod::Grid,
+            enums::Objective::TotalReturn,
+            &config,
+            &structs::WalkForwardConfig::new(enums::WalkForwardWindow::Rolling, 120, 30).unwrap(),
+            crossover_factory,
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,581 @@
+//! Position sizing rules, usable standalone or by strategies within the backtester.
+
+use super::enums::{OrderSide, OrderType};
+use super::ledger::structs::RoundTripStatistics;
+use super::structs::{AccountState, MarketView, Order};
+use crate::datasets::traits::{Prices, Timestamps};
+use crate::enums::Currency;
+use crate::errors::CalculationError;
+use crate::indicators::bands::atr;
+use crate::returns;
+use crate::statistics;
+
+// Board lot of the Singapore Exchange
+const SGX_BOARD_LOT: usize = 100;
+
+pub mod enums {
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum SizingRule {
+        /// Position value as a fraction of the equity.
+        FixedFraction(f32),
+        /// Fraction of the equity lost if the stop, placed at the multiple of the ATR from the price, is hit.
+        AtrRisk {
+            risk_fraction: f32,
+            atr_period: usize,
+            atr_multiple: f32,
+        },
+        /// Position value scaled such that its annualized volatility matches the target, with the volatility
+        /// realized over the lookback period of returns.
+        VolatilityTarget {
+            target_volatility: f32,
+            lookback: usize,
+        },
+        /// Kelly fraction of the equity for the win rate and the ratio of the average win to the average loss,
+        /// capped at the maximum fraction (e.g. 0.25 for a quarter Kelly cap).
+        Kelly {
+            win_rate: f32,
+            payoff_ratio: f32,
+            max_fraction: f32,
+        },
+    }
+}
+
+pub mod structs {
+    use super::*;
+
+    /// Sizing rule together with the account constraints: the maximum gross exposure as a fraction of the equity,
+    /// the lot size, and the exchange rate converting the instrument's currency into the account currency.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct PositionSizer {
+        pub(super) rule: enums::SizingRule,
+        pub(super) max_exposure: f32,
+        pub(super) lot_size: Option<usize>, // Board lot of the instrument's currency if unset
+        pub(super) exchange_rate: f32,
+    }
+
+    impl PositionSizer {
+        pub fn get_rule(&self) -> enums::SizingRule {
+            self.rule
+        }
+
+        pub fn get_max_exposure(&self) -> f32 {
+            self.max_exposure
+        }
+
+        pub fn get_lot_size(&self) -> Option<usize> {
+            self.lot_size
+        }
+
+        pub fn get_exchange_rate(&self) -> f32 {
+            self.exchange_rate
+        }
+    }
+
+    /// Market data of the instrument being sized, together with the gross exposure the account already holds. The
+    /// ATR and annualized volatility are only required by the rules based on them.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct SizingInputs {
+        pub(super) price: f32,
+        pub(super) currency: Currency,
+        pub(super) atr: Option<f32>,
+        pub(super) annual_volatility: Option<f32>,
+        pub(super) current_exposure: f32, // Gross value of the holdings in the account currency
+    }
+
+    impl SizingInputs {
+        pub fn get_price(&self) -> f32 {
+            self.price
+        }
+
+        pub fn get_currency(&self) -> Currency {
+            self.currency
+        }
+
+        pub fn get_atr(&self) -> Option<f32> {
+            self.atr
+        }
+
+        pub fn get_annual_volatility(&self) -> Option<f32> {
+            self.annual_volatility
+        }
+
+        pub fn get_current_exposure(&self) -> f32 {
+            self.current_exposure
+        }
+    }
+
+    /// Long entry size in whole lots, added to the current holdings, with its value in the account currency.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct PositionSize {
+        pub(super) quantity: f32,
+        pub(super) value: f32,
+        pub(super) risk_amount: Option<f32>, // Loss at the stop, for the ATR risk rule only
+        pub(super) is_exposure_capped: bool,
+    }
+
+    impl PositionSize {
+        pub fn get_quantity(&self) -> f32 {
+            self.quantity
+        }
+
+        pub fn get_value(&self) -> f32 {
+            self.value
+        }
+
+        pub fn get_risk_amount(&self) -> Option<f32> {
+            self.risk_amount
+        }
+
+        pub fn is_exposure_capped(&self) -> bool {
+            self.is_exposure_capped
+        }
+    }
+}
+
+/// Minimum tradable quantity of the instruments quoted in the currency: SGX board lots of 100 shares for SGD, and
+/// single shares for USD.
+pub fn board_lot_size(currency: Currency) -> usize {
+    match currency {
+        Currency::Sgd => SGX_BOARD_LOT,
+        Currency::Usd => 1,
+    }
+}
+
+fn validate_fraction(name: &str, value: f32, max_value: f32) -> Result<(), CalculationError> {
+    if value.is_nan() || value <= 0.0 || value > max_value {
+        return Err(CalculationError::InvalidParameterError(format!(
+            "{} must be within (0, {}]. Value provided: {}",
+            name, max_value, value
+        )));
+    }
+    Ok(())
+}
+
+impl enums::SizingRule {
+    /// Kelly rule from the win rate and payoff ratio of past round trips.
+    pub fn kelly(
+        statistics: &RoundTripStatistics,
+        max_fraction: f32,
+    ) -> Result<Self, CalculationError> {
+        match (statistics.get_average_win(), statistics.get_average_loss()) {
+            (Some(average_win), Some(average_loss)) => Ok(enums::SizingRule::Kelly {
+                win_rate: statistics.get_win_rate(),
+                payoff_ratio: average_win / -average_loss,
+                max_fraction,
+            }),
+            _ => Err(CalculationError::InsufficientDataError(
+                "Kelly fraction requires both winning and losing round trips.".to_string(),
+            )),
+        }
+    }
+
+    fn validate(&self) -> Result<(), CalculationError> {
+        match *self {
+            enums::SizingRule::FixedFraction(fraction) => {
+                validate_fraction("Fixed fraction", fraction, f32::MAX)
+            }
+            enums::SizingRule::AtrRisk {
+                risk_fraction,
+                atr_period,
+                atr_multiple,
+            } => {
+                validate_fraction("Risk fraction", risk_fraction, 1.0)?;
+                validate_fraction("ATR multiple", atr_multiple, f32::MAX)?;
+                crate::indicators::validate_period(atr_period)
+            }
+            enums::SizingRule::VolatilityTarget {
+                target_volatility,
+                lookback,
+            } => {
+                validate_fraction("Target volatility", target_volatility, f32::MAX)?;
+                if lookback < 2 {
+                    return Err(CalculationError::InvalidParameterError(format!(
+                        "Volatility lookback must be at least 2 returns. Lookback provided: {}",
+                        lookback
+                    )));
+                }
+                Ok(())
+            }
+            enums::SizingRule::Kelly {
+                win_rate,
+                payoff_ratio,
+                max_fraction,
+            } => {
+                if win_rate.is_nan() || !(0.0..=1.0).contains(&win_rate) {
+                    return Err(CalculationError::InvalidParameterError(format!(
+                        "Win rate must be within [0, 1]. Value provided: {}",
+                        win_rate
+                    )));
+                }
+                validate_fraction("Payoff ratio", payoff_ratio, f32::MAX)?;
+                validate_fraction("Maximum Kelly fraction", max_fraction, 1.0)
+            }
+        }
+    }
+}
+
+impl structs::PositionSizer {
+    /// Sizer with the maximum exposure of the full equity, the board lot of the instrument's currency, and an
+    /// account held in the instrument's currency.
+    pub fn new(rule: enums::SizingRule) -> Result<Self, CalculationError> {
+        rule.validate()?;
+        Ok(structs::PositionSizer {
+            rule,
+            max_exposure: 1.0,
+            lot_size: None,
+            exchange_rate: 1.0,
+        })
+    }
+
+    pub fn with_max_exposure(mut self, max_exposure: f32) -> Result<Self, CalculationError> {
+        validate_fraction("Maximum exposure", max_exposure, f32::MAX)?;
+        self.max_exposure = max_exposure;
+        Ok(self)
+    }
+
+    pub fn with_lot_size(mut self, lot_size: usize) -> Result<Self, CalculationError> {
+        if lot_size == 0 {
+            return Err(CalculationError::InvalidParameterError(
+                "Lot size must be at least 1.".to_string(),
+            ));
+        }
+        self.lot_size = Some(lot_size);
+        Ok(self)
+    }
+
+    /// Units of the account currency per unit of the instrument's currency (e.g. 1.35 for a SGD account trading
+    /// USD listings at 1.35 SGD per USD).
+    pub fn with_exchange_rate(mut self, exchange_rate: f32) -> Result<Self, CalculationError> {
+        validate_fraction("Exchange rate", exchange_rate, f32::MAX)?;
+        self.exchange_rate = exchange_rate;
+        Ok(self)
+    }
+
+    /// Sizes a long entry for the equity, in the account currency, rounded down to whole lots. The entry is capped
+    /// such that the gross exposure after it, including the current holdings, stays within the maximum exposure.
+    pub fn size(
+        &self,
+        equity: f32,
+        inputs: &structs::SizingInputs,
+    ) -> Result<structs::PositionSize, CalculationError> {
+        if equity.is_nan()
+            || inputs.price.is_nan()
+            || inputs.price <= 0.0
+            || inputs.current_exposure.is_nan()
+            || inputs.current_exposure < 0.0
+        {
+            return Err(CalculationError::InvalidParameterError(format!(
+                "Sizing requires a valid equity, a positive price and a non-negative current exposure. Equity provided: {} \n Price provided: {} \n Current exposure provided: {}",
+                equity, inputs.price, inputs.current_exposure
+            )));
+        }
+        let price = inputs.price * self.exchange_rate; // In the account currency
+        let equity = equity.max(0.0);
+
+        let (target_value, stop_distance) = match self.rule {
+            enums::SizingRule::FixedFraction(fraction) => (equity * fraction, None),
+            enums::SizingRule::AtrRisk {
+                risk_fraction,
+                atr_multiple,
+                ..
+            } => {
+                let stop_distance = match inputs.atr {
+                    Some(x) if x > 0.0 => atr_multiple * x * self.exchange_rate,
+                    _ => {
+                        return Err(CalculationError::InsufficientDataError(
+                            "ATR risk sizing requires a positive ATR.".to_string(),
+                        ))
+                    }
+                };
+                (
+                    equity * risk_fraction / stop_distance * price,
+                    Some(stop_distance),
+                )
+            }
+            enums::SizingRule::VolatilityTarget {
+                target_volatility, ..
+            } => match inputs.annual_volatility {
+                Some(x) if x > 0.0 => (equity * target_volatility / x, None),
+                _ => {
+                    return Err(CalculationError::InsufficientDataError(
+                        "Volatility targeting requires a positive realized volatility.".to_string(),
+                    ))
+                }
+            },
+            enums::SizingRule::Kelly {
+                win_rate,
+                payoff_ratio,
+                max_fraction,
+            } => {
+                let kelly_fraction = win_rate - (1.0 - win_rate) / payoff_ratio;
+                (equity * kelly_fraction.max(0.0).min(max_fraction), None)
+            }
+        };
+
+        let max_value = (equity * self.max_exposure - inputs.current_exposure).max(0.0);
+        let lot_size = self
+            .lot_size
+            .unwrap_or_else(|| board_lot_size(inputs.currency)) as f32;
+        let quantity = (target_value.min(max_value) / price / lot_size).floor() * lot_size;
+        Ok(structs::PositionSize {
+            quantity,
+            value: quantity * price,
+            risk_amount: stop_distance.map(|x| x * quantity),
+            is_exposure_capped: target_value > max_value,
+        })
+    }
+
+    /// Sizes a long entry from the history observed by a strategy, with the ATR and realized volatility computed as
+    /// of the current bar, and the current exposure from the position held by the account.
+    pub fn size_from_history(
+        &self,
+        history: &MarketView,
+        account: &AccountState,
+    ) -> Result<structs::PositionSize, CalculationError> {
+        let mut inputs =
+            structs::SizingInputs::new(history.get_current_close(), history.get_currency())
+                .with_current_exposure(
+                    account.get_position().abs() * history.get_current_close() * self.exchange_rate,
+                );
+        match self.rule {
+            enums::SizingRule::AtrRisk { atr_period, .. } => {
+                let average_true_range = atr(history, atr_period)?;
+                inputs.atr = average_true_range.last().copied().filter(|x| !x.is_nan());
+            }
+            enums::SizingRule::VolatilityTarget { lookback, .. } => {
+                let close = history.get_close_prices();
+                if close.len() > lookback {
+                    let recent_returns =
+                        returns::simple_returns(&close[close.len() - lookback - 1..]);
+                    let periods_per_year =
+                        returns::detect_frequency(history.get_timestamps())?.periods_per_year();
+                    inputs.annual_volatility = Some(
+                        statistics::sample_std(&recent_returns[1..]) * periods_per_year.sqrt(),
+                    );
+                }
+            }
+            _ => (),
+        }
+        self.size(account.get_equity(), &inputs)
+    }
+}
+
+impl structs::SizingInputs {
+    pub fn new(price: f32, currency: Currency) -> Self {
+        structs::SizingInputs {
+            price,
+            currency,
+            atr: None,
+            annual_volatility: None,
+            current_exposure: 0.0,
+        }
+    }
+
+    pub fn with_atr(mut self, atr: f32) -> Self {
+        self.atr = Some(atr);
+        self
+    }
+
+    pub fn with_annual_volatility(mut self, annual_volatility: f32) -> Self {
+        self.annual_volatility = Some(annual_volatility);
+        self
+    }
+
+    /// Gross value of the holdings in the account currency, counted towards the maximum exposure.
+    pub fn with_current_exposure(mut self, current_exposure: f32) -> Self {
+        self.current_exposure = current_exposure;
+        self
+    }
+}
+
+/// Market order moving the position to the target quantity, None if already there.
+pub fn order_to_target(target_quantity: f32, position: f32) -> Option<Order> {
+    let delta = target_quantity - position;
+    if delta > 0.0 {
+        Some(Order::new(OrderSide::Buy, delta, OrderType::Market))
+    } else if delta < 0.0 {
+        Some(Order::new(OrderSide::Sell, -delta, OrderType::Market))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backtest::structs::BacktestConfig;
+    use crate::backtest::traits::Strategy;
+    use crate::backtest::{ledger, run_backtest};
+    use crate::datasets::structs::YahooFinancePriceRecord;
+    use crate::datasets::traits::Volume;
+
+    #[test]
+    fn test_fixed_fraction_and_lots() {
+        // 10% of 100000 at 2.35 is 4255 shares, rounded down to 42 board lots
+        let sizer = structs::PositionSizer::new(enums::SizingRule::FixedFraction(0.1)).unwrap();
+        let result = sizer
+            .size(100000.0, &structs::SizingInputs::new(2.35, Currency::Sgd))
+            .unwrap();
+        assert!(result.get_quantity() == 4200.0);
+        assert!(!result.is_exposure_capped());
+        let result = sizer
+            .size(100000.0, &structs::SizingInputs::new(2.35, Currency::Usd))
+            .unwrap();
+        assert!(result.get_quantity() == 4255.0);
+
+        // SGD account trading a USD listing at 1.35 SGD per USD: 10000 / (50 * 1.35) = 148.1 shares
+        let converted = sizer.with_exchange_rate(1.35).unwrap();
+        let result = converted
+            .size(100000.0, &structs::SizingInputs::new(50.0, Currency::Usd))
+            .unwrap();
+        assert!(result.get_quantity() == 148.0);
+        assert!((result.get_value() - 148.0 * 67.5).abs() < 1e-2);
+
+        // Leveraged fractions are capped at the maximum exposure
+        let leveraged = structs::PositionSizer::new(enums::SizingRule::FixedFraction(2.0))
+            .unwrap()
+            .with_max_exposure(1.5)
+            .unwrap();
+        let result = leveraged
+            .size(10000.0, &structs::SizingInputs::new(10.0, Currency::Usd))
+            .unwrap();
+        assert!(result.get_quantity() == 1500.0 && result.is_exposure_capped());
+
+        // Repeated entries are capped by the exposure already held
+        let inputs = structs::SizingInputs::new(10.0, Currency::Usd).with_current_exposure(12000.0);
+        let result = leveraged.size(10000.0, &inputs).unwrap();
+        assert!(result.get_quantity() == 300.0 && result.is_exposure_capped());
+        let inputs = structs::SizingInputs::new(10.0, Currency::Usd).with_current_exposure(15000.0);
+        assert!(leveraged.size(10000.0, &inputs).unwrap().get_quantity() == 0.0);
+        let inputs = structs::SizingInputs::new(10.0, Currency::Usd).with_current_exposure(-1.0);
+        assert!(leveraged.size(10000.0, &inputs).is_err());
+        assert!(structs::PositionSizer::new(enums::SizingRule::FixedFraction(0.0)).is_err());
+    }
+
+    #[test]
+    fn test_risk_based_rules() {
+        // Risking 1% of 100000 with a stop at 2 ATRs of 0.5: 1000 / 1 = 1000 shares
+        let sizer = structs::PositionSizer::new(enums::SizingRule::AtrRisk {
+            risk_fraction: 0.01,
+            atr_period: 14,
+            atr_multiple: 2.0,
+        })
+        .unwrap();
+        let inputs = structs::SizingInputs::new(20.0, Currency::Usd).with_atr(0.5);
+        let result = sizer.size(100000.0, &inputs).unwrap();
+        assert!(result.get_quantity() == 1000.0 && result.get_risk_amount() == Some(1000.0));
+        assert!(sizer
+            .size(100000.0, &structs::SizingInputs::new(20.0, Currency::Usd))
+            .is_err());
+
+        // Targeting 10% volatility on an instrument of 40% volatility: 25% of the equity
+        let sizer = structs::PositionSizer::new(enums::SizingRule::VolatilityTarget {
+            target_volatility: 0.1,
+            lookback: 20,
+        })
+        .unwrap();
+        let inputs = structs::SizingInputs::new(10.0, Currency::Usd).with_annual_volatility(0.4);
+        assert!(sizer.size(100000.0, &inputs).unwrap().get_quantity() == 2500.0);
+
+        // Kelly fraction of 0.6 - 0.4 / 2 = 0.4, capped at 0.25
+        let sizer = structs::PositionSizer::new(enums::SizingRule::Kelly {
+            win_rate: 0.6,
+            payoff_ratio: 2.0,
+            max_fraction: 0.25,
+        })
+        .unwrap();
+        let inputs = structs::SizingInputs::new(10.0, Currency::Usd);
+        assert!(sizer.size(100000.0, &inputs).unwrap().get_quantity() == 2500.0);
+    }
+
+    #[test]
+    fn test_kelly_from_round_trips() {
+        let record =
+            YahooFinancePriceRecord::from_closes(&[10.0, 10.0, 12.0, 10.0, 11.0, 11.0, 10.0]);
+
+        /// Alternates between buying 100 shares and selling them.
+        struct Churn;
+
+        impl Strategy for Churn {
+            fn on_bar(&mut self, _history: &MarketView, account: &AccountState) -> Vec<Order> {
+                order_to_target(100.0 - account.get_position(), account.get_position())
+                    .into_iter()
+                    .collect()
+            }
+        }
+
+        // Round trips of +200, +100 and -100
+        let config = BacktestConfig::new(10000.0).unwrap();
+        let result = run_backtest(&record, &mut Churn, &config).unwrap();
+        let round_trips = ledger::trade_ledger(&record, result.get_fills()).unwrap();
+        let statistics = ledger::round_trip_statistics(round_trips.get_round_trips()).unwrap();
+        let rule = enums::SizingRule::kelly(&statistics, 0.5).unwrap();
+        assert!(
+            rule == enums::SizingRule::Kelly {
+                win_rate: 2.0 / 3.0,
+                payoff_ratio: 1.5,
+                max_fraction: 0.5
+            }
+        );
+    }
+
+    #[test]
+    fn test_size_from_history() {
+        let record = YahooFinancePriceRecord::from_bars(&[(10.0, 10.25, 9.75, 10.0, 100000); 30])
+            .with_currency(Currency::Sgd);
+        let history = MarketView {
+            timestamps: record.get_timestamps(),
+            open_prices: record.get_open_prices(),
+            high_prices: record.get_high_prices(),
+            low_prices: record.get_low_prices(),
+            close_prices: record.get_close_prices(),
+            adj_close_prices: record.get_adj_close_prices(),
+            volume: record.get_volume(),
+            currency: record.get_currency(),
+        };
+        let account = AccountState {
+            cash: 100000.0,
+            position: 0.0,
+            equity: 100000.0,
+        };
+
+        // ATR of 0.5 and a stop at 2 ATRs: 1562.5 / 1 = 1562.5 shares, rounded down to 15 board lots
+        let sizer = structs::PositionSizer::new(enums::SizingRule::AtrRisk {
+            risk_fraction: 0.015625,
+            atr_period: 14,
+            atr_multiple: 2.0,
+        })
+        .unwrap();
+        let result = sizer.size_from_history(&history, &account).unwrap();
+        assert!(result.get_quantity() == 1500.0);
+
+        // Holding 500 shares leaves the risk-based entry unchanged, on top of the current holdings
+        let account = AccountState {
+            cash: 95000.0,
+            position: 500.0,
+            equity: 100000.0,
+        };
+        let result = sizer.size_from_history(&history, &account).unwrap();
+        assert!(result.get_quantity() == 1500.0 && !result.is_exposure_capped());
+        let order = order_to_target(account.get_position() + result.get_quantity(), 500.0).unwrap();
+        assert!(order.get_side() == OrderSide::Buy && order.get_quantity() == 1500.0);
+
+        // Holding 9500 shares worth 95000 leaves 5000 of the full equity exposure for the entry
+        let account = AccountState {
+            cash: 5000.0,
+            position: 9500.0,
+            equity: 100000.0,
+        };
+        let result = sizer.size_from_history(&history, &account).unwrap();
+        assert!(result.get_quantity() == 500.0 && result.is_exposure_capped());
+
+        // Constant prices have no realized volatility to target
+        let sizer = structs::PositionSizer::new(enums::SizingRule::VolatilityTarget {
+            target_volatility: 0.1,
+            lookback: 20,
+        })
+        .unwrap();
+        assert!(sizer.size_from_history(&history, &account).is_err());
+    }
+}
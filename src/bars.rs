@@ -0,0 +1,425 @@
+//! Objective: Construct alternative bar series from the pricing data of a record.
+//! Each transformation returns a new YahooFinancePriceRecord, such that the existing traits, groupings and aggregations apply.
+
+use crate::datasets::structs::YahooFinancePriceRecord;
+use crate::datasets::traits::{Description, Prices, Timestamps, Volume};
+use crate::errors::CalculationError;
+use crate::indicators::bands::atr;
+use crate::volatility::validate_ohlc_lengths;
+
+pub mod enums {
+
+    /// Box size of the Renko and point-and-figure charts.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum BoxSize {
+        Fixed(f32),
+        Atr(usize), // Latest value of the ATR over the given period
+    }
+}
+
+/// Validates the lengths of the arrays of the record, returning the length of the arrays.
+fn validate_record<T>(record: &T) -> Result<usize, CalculationError>
+where
+    T: Prices + Timestamps + Volume,
+{
+    let length = validate_ohlc_lengths(
+        record.get_open_prices(),
+        record.get_high_prices(),
+        record.get_low_prices(),
+        record.get_close_prices(),
+    )?;
+    if record.get_timestamps().len() != length || record.get_volume().len() != length {
+        return Err(CalculationError::InconsistentLengthError(format!(
+            "Length of the timestamps array: {} \n Length of the volume array: {} \n Length of the prices array: {}",
+            record.get_timestamps().len(),
+            record.get_volume().len(),
+            length
+        )));
+    }
+    Ok(length)
+}
+
+/// Resolves the box size to a fixed price increment.
+fn resolve_box_size<T>(record: &T, box_size: enums::BoxSize) -> Result<f32, CalculationError>
+where
+    T: Prices,
+{
+    let resolved = match box_size {
+        enums::BoxSize::Fixed(size) => size,
+        enums::BoxSize::Atr(period) => {
+            let average_true_range = atr(record, period)?;
+            match average_true_range.last() {
+                Some(value) if !value.is_nan() => *value,
+                _ => {
+                    return Err(CalculationError::InsufficientDataError(format!(
+                        "ATR box size requires at least {} bars. Number of bars provided: {}",
+                        period,
+                        average_true_range.len()
+                    )))
+                }
+            }
+        }
+    };
+    if resolved.is_nan() || resolved <= 0.0 {
+        return Err(CalculationError::InvalidParameterError(format!(
+            "Box size must be positive. Box size resolved: {}",
+            resolved
+        )));
+    }
+    validate_increment(resolved, &[record.get_close_prices()])?;
+    Ok(resolved)
+}
+
+/// Validates that the prices are finite and that the price increment of the bars exceeds the f32 spacing at the
+/// largest price, as smaller increments would leave the prices unchanged when added.
+fn validate_increment(increment: f32, prices: &[&[f32]]) -> Result<(), CalculationError> {
+    let mut max_price = 0.0_f32;
+    for price in prices.iter().flat_map(|x| x.iter()) {
+        if !price.is_finite() {
+            return Err(CalculationError::InvalidParameterError(format!(
+                "Prices must be finite to construct the bars. Price provided: {}",
+                price
+            )));
+        }
+        max_price = max_price.max(price.abs());
+    }
+    if increment <= max_price * f32::EPSILON {
+        return Err(CalculationError::InvalidParameterError(format!(
+            "Price increment of the bars is below the precision of the prices. Increment provided: {} \n Largest price: {}",
+            increment, max_price
+        )));
+    }
+    Ok(())
+}
+
+/// Heikin-Ashi candles, aligned with the timestamps of the record.
+pub fn heikin_ashi<T>(record: &T) -> Result<YahooFinancePriceRecord, CalculationError>
+where
+    T: Prices + Timestamps + Volume + Description,
+{
+    let length = validate_record(record)?;
+    let open = record.get_open_prices();
+    let high = record.get_high_prices();
+    let low = record.get_low_prices();
+    let close = record.get_close_prices();
+
+    let mut result =
+        YahooFinancePriceRecord::new(record.get_ticker_symbol(), length, record.get_currency());
+    let mut previous: Option<(f32, f32)> = None; // Previous Heikin-Ashi open and close
+    for idx in 0..length {
+        let ha_close = (open[idx] + high[idx] + low[idx] + close[idx]) / 4.0;
+        let ha_open = match previous {
+            Some((previous_open, previous_close)) => (previous_open + previous_close) / 2.0,
+            None => (open[idx] + close[idx]) / 2.0,
+        };
+        result.push_record(
+            record.get_timestamps()[idx],
+            ha_open,
+            high[idx].max(ha_open).max(ha_close),
+            low[idx].min(ha_open).min(ha_close),
+            ha_close,
+            ha_close,
+            record.get_volume()[idx],
+        );
+        previous = Some((ha_open, ha_close));
+    }
+    Ok(result)
+}
+
+/// Renko bricks over the close prices. A brick in the direction of the previous brick requires a move of one box
+/// beyond it, while a reversal brick requires a move of two boxes. Each brick is dated on the bar which completed it,
+/// with the volume traded since the previous brick attributed to the first brick completed on the bar.
+pub fn renko<T>(
+    record: &T,
+    box_size: enums::BoxSize,
+) -> Result<YahooFinancePriceRecord, CalculationError>
+where
+    T: Prices + Timestamps + Volume + Description,
+{
+    let length = validate_record(record)?;
+    let size = resolve_box_size(record, box_size)?;
+    let close = record.get_close_prices();
+    let volume = record.get_volume();
+
+    let mut result =
+        YahooFinancePriceRecord::new(record.get_ticker_symbol(), 0, record.get_currency());
+    if length == 0 {
+        return Ok(result);
+    }
+
+    // Top and bottom of the latest brick, initially collapsed onto the first close
+    let mut top = close[0];
+    let mut bottom = close[0];
+    let mut pending_volume = volume[0] as i64;
+    for idx in 1..length {
+        pending_volume += volume[idx] as i64;
+        loop {
+            let (open, brick_close) = if close[idx] >= top + size {
+                (top, top + size)
+            } else if close[idx] <= bottom - size {
+                (bottom, bottom - size)
+            } else {
+                break;
+            };
+            if brick_close == open {
+                break; // The brick would not move the top or bottom, hence would be repeated indefinitely
+            }
+            result.push_record(
+                record.get_timestamps()[idx],
+                open,
+                open.max(brick_close),
+                open.min(brick_close),
+                brick_close,
+                brick_close,
+                pending_volume.min(i32::MAX as i64) as i32,
+            );
+            pending_volume = 0;
+            top = open.max(brick_close);
+            bottom = open.min(brick_close);
+        }
+    }
+    Ok(result)
+}
+
+/// Approximate intrabar price path of a bar: open, low, high, close for up bars and open, high, low, close otherwise.
+fn price_path(open: f32, high: f32, low: f32, close: f32) -> [f32; 4] {
+    if close >= open {
+        [open, low, high, close]
+    } else {
+        [open, high, low, close]
+    }
+}
+
+/// Range bars, each completed once its high - low range reaches the given range, built over the approximate intrabar
+/// price path of each bar. Each range bar is dated on the bar which completed it, with the volume of each source bar
+/// attributed to the range bar in progress at its open. The final incomplete range bar is excluded.
+pub fn range_bars<T>(record: &T, range: f32) -> Result<YahooFinancePriceRecord, CalculationError>
+where
+    T: Prices + Timestamps + Volume + Description,
+{
+    if range.is_nan() || range <= 0.0 {
+        return Err(CalculationError::InvalidParameterError(format!(
+            "Range of the bars must be positive. Range provided: {}",
+            range
+        )));
+    }
+    let length = validate_record(record)?;
+    let open = record.get_open_prices();
+    let high = record.get_high_prices();
+    let low = record.get_low_prices();
+    let close = record.get_close_prices();
+    let volume = record.get_volume();
+    validate_increment(range, &[open, high, low, close])?;
+
+    let mut result =
+        YahooFinancePriceRecord::new(record.get_ticker_symbol(), 0, record.get_currency());
+    if length == 0 {
+        return Ok(result);
+    }
+
+    // Open, high and low of the range bar in progress, together with its volume
+    let (mut bar_open, mut bar_high, mut bar_low) = (open[0], open[0], open[0]);
+    let mut bar_volume = 0_i64;
+    for idx in 0..length {
+        bar_volume += volume[idx] as i64;
+        for price in price_path(open[idx], high[idx], low[idx], close[idx]) {
+            loop {
+                let bar_close = if price >= bar_low + range {
+                    bar_low + range
+                } else if price <= bar_high - range {
+                    bar_high - range
+                } else {
+                    bar_high = bar_high.max(price);
+                    bar_low = bar_low.min(price);
+                    break;
+                };
+                if bar_close >= bar_low && bar_close <= bar_high {
+                    break; // The bar would not move the high or low, hence would be repeated indefinitely
+                }
+                result.push_record(
+                    record.get_timestamps()[idx],
+                    bar_open,
+                    bar_high.max(bar_close),
+                    bar_low.min(bar_close),
+                    bar_close,
+                    bar_close,
+                    bar_volume.min(i32::MAX as i64) as i32,
+                );
+                bar_volume = 0;
+                bar_open = bar_close;
+                bar_high = bar_close;
+                bar_low = bar_close;
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Point-and-figure columns over the close prices, with prices quantized to multiples of the box size.
+/// A column of X's (up) is extended by every new box above its top, and reverses into a column of O's (down) once the
+/// close falls by the reversal number of boxes, and vice versa. Each column is returned as a bar from its first to its
+/// last box, dated on the bar which last extended it, with the volume traded over the column.
+pub fn point_and_figure<T>(
+    record: &T,
+    box_size: enums::BoxSize,
+    reversal_boxes: usize,
+) -> Result<YahooFinancePriceRecord, CalculationError>
+where
+    T: Prices + Timestamps + Volume + Description,
+{
+    if reversal_boxes == 0 {
+        return Err(CalculationError::InvalidParameterError(
+            "Number of reversal boxes must be greater than 0.".to_string(),
+        ));
+    }
+    let length = validate_record(record)?;
+    let size = resolve_box_size(record, box_size)?;
+    let close = record.get_close_prices();
+    let volume = record.get_volume();
+    let timestamps = record.get_timestamps();
+
+    let mut result =
+        YahooFinancePriceRecord::new(record.get_ticker_symbol(), 0, record.get_currency());
+    if length == 0 {
+        return Ok(result);
+    }
+
+    let reversal = size * reversal_boxes as f32;
+    let to_box = |price: f32| (price / size).floor() * size;
+    let mut is_up: Option<bool> = None; // Column direction, undetermined until the first box move
+    let mut column_start = to_box(close[0]);
+    let mut column_end = column_start;
+    let mut column_timestamp = timestamps[0];
+    let mut column_volume = volume[0] as i64;
+    for idx in 1..length {
+        let level = to_box(close[idx]);
+        let (extends, reverses) = match is_up {
+            None => (level != column_end, false),
+            Some(true) => (level > column_end, level <= column_end - reversal),
+            Some(false) => (level < column_end, level >= column_end + reversal),
+        };
+
+        if reverses {
+            result.push_record(
+                column_timestamp,
+                column_start,
+                column_start.max(column_end),
+                column_start.min(column_end),
+                column_end,
+                column_end,
+                column_volume.min(i32::MAX as i64) as i32,
+            );
+            // The new column starts one box away from the end of the previous column
+            let is_new_column_up = level > column_end;
+            column_start = if is_new_column_up {
+                column_end + size
+            } else {
+                column_end - size
+            };
+            column_end = level;
+            column_timestamp = timestamps[idx];
+            column_volume = volume[idx] as i64;
+            is_up = Some(is_new_column_up);
+            continue;
+        }
+
+        column_volume += volume[idx] as i64;
+        if extends {
+            if is_up.is_none() {
+                is_up = Some(level > column_end);
+            }
+            column_end = level;
+            column_timestamp = timestamps[idx];
+        }
+    }
+
+    // The latest column remains open, but is included as it reflects the prevailing trend
+    if is_up.is_some() {
+        result.push_record(
+            column_timestamp,
+            column_start,
+            column_start.max(column_end),
+            column_start.min(column_end),
+            column_end,
+            column_end,
+            column_volume.min(i32::MAX as i64) as i32,
+        );
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_heikin_ashi() {
+        let record = YahooFinancePriceRecord::from_ohlc(&[
+            (10.0, 12.0, 9.0, 11.0),
+            (11.0, 13.0, 10.0, 12.0),
+        ]);
+        let result = heikin_ashi(&record).unwrap();
+        assert!(result.get_open_prices() == [10.5, 10.5]);
+        assert!(result.get_close_prices() == [10.5, 11.5]);
+        assert!(result.get_high_prices() == [12.0, 13.0]);
+        assert!(result.get_timestamps() == record.get_timestamps());
+        assert!(result.get_ticker_symbol() == "TEST");
+    }
+
+    #[test]
+    fn test_renko() {
+        let record = YahooFinancePriceRecord::from_closes(&[10.0, 10.5, 12.2, 11.5, 10.9, 7.9]);
+        let result = renko(&record, enums::BoxSize::Fixed(1.0)).unwrap();
+        // Two up bricks on the third bar, while the reversal requires a close at or below 10.0
+        assert!(result.get_open_prices() == [10.0, 11.0, 11.0, 10.0, 9.0]);
+        assert!(result.get_close_prices() == [11.0, 12.0, 10.0, 9.0, 8.0]);
+        assert!(result.get_volume() == [3000, 0, 3000, 0, 0]);
+        assert!(result.get_timestamps()[2] == chrono::Utc.ymd(2022, 1, 8).and_hms(0, 0, 0));
+        assert!(renko(&record, enums::BoxSize::Fixed(0.0)).is_err());
+        assert!(renko(&record, enums::BoxSize::Atr(10)).is_err());
+
+        // Box sizes below the f32 spacing of the prices, and non-finite prices, are rejected
+        let record = YahooFinancePriceRecord::from_closes(&[100.0, 101.0, 99.0]);
+        assert!(renko(&record, enums::BoxSize::Fixed(1e-6)).is_err());
+        assert!(point_and_figure(&record, enums::BoxSize::Fixed(1e-6), 3).is_err());
+        let record = YahooFinancePriceRecord::from_closes(&[100.0, f32::INFINITY]);
+        assert!(renko(&record, enums::BoxSize::Fixed(1.0)).is_err());
+    }
+
+    #[test]
+    fn test_range_bars() {
+        let record = YahooFinancePriceRecord::from_ohlc(&[
+            (10.0, 10.5, 9.5, 10.5),
+            (10.5, 12.5, 10.5, 12.5),
+        ]);
+        let result = range_bars(&record, 1.0).unwrap();
+        // Path: 10, 9.5, 10.5 completes the first bar, 10.5, 10.5, 12.5 completes two more
+        assert!(result.get_open_prices() == [10.0, 10.5, 11.5]);
+        assert!(result.get_close_prices() == [10.5, 11.5, 12.5]);
+        assert!(result.get_low_prices() == [9.5, 10.5, 11.5]);
+        assert!(result.get_volume() == [1000, 1000, 0]);
+        assert!(range_bars(&record, 1e-6).is_err());
+        let record = YahooFinancePriceRecord::from_ohlc(&[(10.0, f32::INFINITY, 9.5, 10.5)]);
+        assert!(range_bars(&record, 1.0).is_err());
+    }
+
+    #[test]
+    fn test_point_and_figure() {
+        let record =
+            YahooFinancePriceRecord::from_closes(&[10.0, 11.2, 13.5, 12.1, 10.4, 11.0, 9.0]);
+        let result = point_and_figure(&record, enums::BoxSize::Fixed(1.0), 3).unwrap();
+        // X column from 10 to 13, followed by an O column from 12 down to 9 after the reversal at 10.4
+        assert!(result.get_open_prices() == [10.0, 12.0]);
+        assert!(result.get_close_prices() == [13.0, 9.0]);
+        assert!(
+            result.get_timestamps()
+                == [
+                    chrono::Utc.ymd(2022, 1, 5).and_hms(0, 0, 0),
+                    chrono::Utc.ymd(2022, 1, 9).and_hms(0, 0, 0)
+                ]
+        );
+        assert!(result.get_volume() == [4000, 3000]);
+        assert!(point_and_figure(&record, enums::BoxSize::Fixed(1.0), 0).is_err());
+    }
+}
@@ -0,0 +1,540 @@
+//! Objective: Compare a ticker against a benchmark (e.g. SPY or the STI ETF) over the TickerInfo date range, on the
+//! trading days common to both records.
+
+use itertools::Itertools;
+
+use crate::datasets::structs::TickerInfo;
+use crate::datasets::traits::{Prices, Timestamps};
+use crate::enums::BarFrequency;
+use crate::errors::CalculationError;
+use crate::functions::Grouping;
+use crate::inputs::enums::AggregationPeriod;
+use crate::performance;
+use crate::returns;
+use crate::statistics;
+
+pub mod structs {
+
+    /// Prices of the record and benchmark on their common timestamps within the TickerInfo date range.
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct AlignedPrices {
+        pub(super) timestamps: Vec<chrono::DateTime<chrono::Utc>>,
+        pub(super) prices: Vec<f32>,
+        pub(super) benchmark_prices: Vec<f32>,
+    }
+
+    impl AlignedPrices {
+        pub fn get_timestamps(&self) -> &[chrono::DateTime<chrono::Utc>] {
+            &self.timestamps
+        }
+
+        pub fn get_prices(&self) -> &[f32] {
+            &self.prices
+        }
+
+        pub fn get_benchmark_prices(&self) -> &[f32] {
+            &self.benchmark_prices
+        }
+    }
+
+    /// Regression statistics of the record's returns on the benchmark's returns over a trailing window. Arrays are
+    /// aligned with the timestamps, with NaN warm-up values.
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct RollingRegression {
+        pub(super) timestamps: Vec<chrono::DateTime<chrono::Utc>>,
+        pub(super) betas: Vec<f32>,
+        pub(super) alphas: Vec<f32>, // Annualized
+        pub(super) correlations: Vec<f32>,
+    }
+
+    impl RollingRegression {
+        pub fn get_timestamps(&self) -> &[chrono::DateTime<chrono::Utc>] {
+            &self.timestamps
+        }
+
+        pub fn get_betas(&self) -> &[f32] {
+            &self.betas
+        }
+
+        pub fn get_alphas(&self) -> &[f32] {
+            &self.alphas
+        }
+
+        pub fn get_correlations(&self) -> &[f32] {
+            &self.correlations
+        }
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct CaptureRatios {
+        pub(super) up_capture: f32,
+        pub(super) down_capture: f32,
+        pub(super) num_of_up_periods: usize,
+        pub(super) num_of_down_periods: usize,
+    }
+
+    impl CaptureRatios {
+        /// Record's geometric mean return relative to the benchmark's, over the periods the benchmark rose. NaN
+        /// without such periods.
+        pub fn get_up_capture(&self) -> f32 {
+            self.up_capture
+        }
+
+        /// Record's geometric mean return relative to the benchmark's, over the periods the benchmark fell. NaN
+        /// without such periods.
+        pub fn get_down_capture(&self) -> f32 {
+            self.down_capture
+        }
+
+        pub fn get_num_of_up_periods(&self) -> usize {
+            self.num_of_up_periods
+        }
+
+        pub fn get_num_of_down_periods(&self) -> usize {
+            self.num_of_down_periods
+        }
+
+        /// Up capture over down capture. Above 1 when the record gains more in rising markets than it loses in
+        /// falling ones, relative to the benchmark.
+        pub fn get_capture_ratio(&self) -> f32 {
+            self.up_capture / self.down_capture
+        }
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct PeriodExcessReturn {
+        pub(super) period_start: chrono::DateTime<chrono::Utc>,
+        pub(super) period_return: f32,
+        pub(super) benchmark_return: f32,
+    }
+
+    impl PeriodExcessReturn {
+        pub fn get_period_start(&self) -> chrono::DateTime<chrono::Utc> {
+            self.period_start
+        }
+
+        pub fn get_period_return(&self) -> f32 {
+            self.period_return
+        }
+
+        pub fn get_benchmark_return(&self) -> f32 {
+            self.benchmark_return
+        }
+
+        pub fn get_excess_return(&self) -> f32 {
+            self.period_return - self.benchmark_return
+        }
+    }
+}
+
+/// Aligns the prices of the record and benchmark on their common timestamps within the TickerInfo date range. Days
+/// on which only one of the two traded are dropped, such that returns span the same interval for both.
+pub fn align_prices<T, U>(
+    record: &T,
+    benchmark: &U,
+    ticker_info: &TickerInfo,
+    basis: returns::enums::ReturnBasis,
+) -> Result<structs::AlignedPrices, CalculationError>
+where
+    T: Prices + Timestamps,
+    U: Prices + Timestamps,
+{
+    let (timestamps, prices, benchmark_prices) = performance::align_series(
+        record.get_timestamps(),
+        returns::get_basis_prices(record, basis),
+        benchmark.get_timestamps(),
+        returns::get_basis_prices(benchmark, basis),
+    );
+    let range = performance::range_indexes(
+        &timestamps,
+        ticker_info.get_start_datetime(),
+        ticker_info.get_end_datetime(),
+    );
+    if range.len() < 2 {
+        return Err(CalculationError::InsufficientDataError(format!(
+            "At least 2 common timestamps are required within the date range, got {}.",
+            range.len()
+        )));
+    }
+
+    Ok(structs::AlignedPrices {
+        timestamps: timestamps[range.clone()].to_vec(),
+        prices: prices[range.clone()].to_vec(),
+        benchmark_prices: benchmark_prices[range].to_vec(),
+    })
+}
+
+/// Relative strength of the record against the benchmark: the ratio of their prices, rebased to 1 at the first
+/// common timestamp. A rising ratio indicates outperformance.
+pub fn relative_strength(aligned: &structs::AlignedPrices) -> Vec<f32> {
+    let base = aligned.prices[0] / aligned.benchmark_prices[0];
+    aligned
+        .prices
+        .iter()
+        .zip(&aligned.benchmark_prices)
+        .map(|(price, benchmark_price)| price / benchmark_price / base)
+        .collect()
+}
+
+/// Beta, annualized Jensen's alpha and correlation of the record's returns against the benchmark's returns over a
+/// trailing window of returns. The risk-free rate is provided as an annualized rate (e.g. 0.03 for 3%).
+pub fn rolling_regression(
+    aligned: &structs::AlignedPrices,
+    window: usize,
+    risk_free_rate: f32,
+) -> Result<structs::RollingRegression, CalculationError> {
+    if window < 2 {
+        return Err(CalculationError::InvalidParameterError(format!(
+            "Rolling window must be at least 2 returns, got {}.",
+            window
+        )));
+    }
+    let frequency = returns::detect_frequency(&aligned.timestamps)?;
+    let excess_returns = period_excess_returns(&aligned.prices, risk_free_rate, frequency);
+    let benchmark_excess_returns =
+        period_excess_returns(&aligned.benchmark_prices, risk_free_rate, frequency);
+
+    let length = aligned.timestamps.len();
+    let mut betas = vec![f32::NAN; length];
+    let mut alphas = vec![f32::NAN; length];
+    let mut correlations = vec![f32::NAN; length];
+    // Return idx corresponds to the price idx + 1, as the leading return is undefined
+    for end in window..=excess_returns.len() {
+        let returns = &excess_returns[end - window..end];
+        let benchmark_returns = &benchmark_excess_returns[end - window..end];
+        let beta = statistics::sample_covariance(returns, benchmark_returns)
+            / statistics::sample_variance(benchmark_returns);
+        betas[end] = beta;
+        alphas[end] = (statistics::mean(returns) - beta * statistics::mean(benchmark_returns))
+            * frequency.periods_per_year();
+        correlations[end] = statistics::correlation(returns, benchmark_returns);
+    }
+
+    Ok(structs::RollingRegression {
+        timestamps: aligned.timestamps.clone(),
+        betas,
+        alphas,
+        correlations,
+    })
+}
+
+/// Per-period returns of the prices in excess of the risk-free rate, without the leading NaN.
+fn period_excess_returns(prices: &[f32], risk_free_rate: f32, frequency: BarFrequency) -> Vec<f32> {
+    let period_returns = &returns::simple_returns(prices)[1..];
+    let rates = vec![risk_free_rate; period_returns.len()];
+    returns::excess_returns(period_returns, &rates, frequency).unwrap() // Arrays are equal in length by construction
+}
+
+/// Up and down capture ratios of the record against the benchmark, over the periods in which the benchmark rose and
+/// fell respectively. Periods in which the benchmark was unchanged are excluded.
+pub fn capture_ratios(aligned: &structs::AlignedPrices) -> structs::CaptureRatios {
+    let period_returns = &returns::simple_returns(&aligned.prices)[1..];
+    let benchmark_returns = &returns::simple_returns(&aligned.benchmark_prices)[1..];
+
+    let capture = |is_selected: fn(f32) -> bool| {
+        let (selected, benchmark_selected): (Vec<f32>, Vec<f32>) = period_returns
+            .iter()
+            .zip(benchmark_returns)
+            .filter(|(_, benchmark_return)| is_selected(**benchmark_return))
+            .map(|(a, b)| (*a, *b))
+            .unzip();
+        (
+            geometric_mean_return(&selected) / geometric_mean_return(&benchmark_selected),
+            selected.len(),
+        )
+    };
+    let (up_capture, num_of_up_periods) = capture(|x| x > 0.0);
+    let (down_capture, num_of_down_periods) = capture(|x| x < 0.0);
+
+    structs::CaptureRatios {
+        up_capture,
+        down_capture,
+        num_of_up_periods,
+        num_of_down_periods,
+    }
+}
+
+/// Geometric mean of the returns, NaN for an empty array.
+fn geometric_mean_return(returns: &[f32]) -> f32 {
+    if returns.is_empty() {
+        return f32::NAN;
+    }
+    let log_growth = returns.iter().map(|x| (1.0 + *x as f64).ln()).sum::<f64>();
+    ((log_growth / returns.len() as f64).exp() - 1.0) as f32
+}
+
+/// Returns of the record and benchmark over each grouping period, measured from the last common price of the
+/// previous period. The first period is measured from its first price.
+pub fn excess_returns_by_period(
+    aligned: &structs::AlignedPrices,
+    period: AggregationPeriod,
+) -> Result<Vec<structs::PeriodExcessReturn>, CalculationError> {
+    let price_pairs = aligned
+        .prices
+        .iter()
+        .zip(&aligned.benchmark_prices)
+        .map(|(a, b)| (*a, *b))
+        .collect::<Vec<(f32, f32)>>();
+    let grouped = Grouping::groupby_period(&aligned.timestamps, &price_pairs, period)
+        .map_err(|e| CalculationError::InconsistentLengthError(format!("{:?}", e)))?;
+
+    let mut previous_prices = price_pairs[0];
+    let mut result = Vec::new();
+    for (period_start, group) in grouped.into_iter() {
+        let last_prices = match group.last() {
+            Some((_timestamp, prices)) => *prices,
+            None => continue, // Groups are never empty
+        };
+        result.push(structs::PeriodExcessReturn {
+            period_start,
+            period_return: last_prices.0 / previous_prices.0 - 1.0,
+            benchmark_return: last_prices.1 / previous_prices.1 - 1.0,
+        });
+        previous_prices = last_prices;
+    }
+    Ok(result)
+}
+
+/// Fraction of the grouping periods in which the record outperformed the benchmark.
+pub fn outperformance_rate(
+    period_returns: &[structs::PeriodExcessReturn],
+) -> Result<f32, CalculationError> {
+    if period_returns.is_empty() {
+        return Err(CalculationError::InsufficientDataError(
+            "Outperformance rate requires at least 1 period.".to_string(),
+        ));
+    }
+    let num_of_outperforming = period_returns
+        .iter()
+        .filter(|x| x.get_excess_return() > 0.0)
+        .count();
+    Ok(num_of_outperforming as f32 / period_returns.len() as f32)
+}
+
+/// Excess returns over the grouping periods, sorted in ascending order for percentile lookups.
+pub fn sorted_excess_returns(period_returns: &[structs::PeriodExcessReturn]) -> Vec<f32> {
+    period_returns
+        .iter()
+        .map(|x| x.get_excess_return())
+        .filter(|x| !x.is_nan())
+        .sorted_by(|a, b| a.partial_cmp(b).unwrap()) // NaN values are filtered out
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datasets::structs::YahooFinancePriceRecord;
+    use crate::enums::Currency;
+    use crate::synthetic;
+
+    fn benchmark(missing_rate: f32) -> YahooFinancePriceRecord {
+        let config = synthetic::structs::SyntheticConfig::new(
+            "BENCH",
+            Currency::Usd,
+            chrono::NaiveDate::from_ymd(2022, 1, 3),
+            260,
+            17,
+        )
+        .unwrap()
+        .with_missing_rate(missing_rate)
+        .unwrap();
+        synthetic::generate_record(&config).unwrap()
+    }
+
+    /// Record whose daily returns are the given multiple of the benchmark's daily returns.
+    fn leveraged(benchmark: &YahooFinancePriceRecord, leverage: f32) -> YahooFinancePriceRecord {
+        let prices = benchmark.get_close_prices();
+        let mut record = YahooFinancePriceRecord::new("TEST", prices.len(), Currency::Usd);
+        let mut price = 50.0;
+        for idx in 0..prices.len() {
+            if idx > 0 {
+                price *= 1.0 + leverage * (prices[idx] / prices[idx - 1] - 1.0);
+            }
+            record.push_record(
+                benchmark.get_timestamps()[idx],
+                price,
+                price,
+                price,
+                price,
+                price,
+                1000,
+            );
+        }
+        record
+    }
+
+    fn ticker_info() -> TickerInfo<'static> {
+        TickerInfo::new("TEST", "2022-01-01", "2022-12-31", Currency::Usd).unwrap()
+    }
+
+    #[test]
+    fn test_align_prices() {
+        let benchmark_with_gaps = benchmark(0.1);
+        let record = leveraged(&benchmark(0.0), 1.0);
+        let full_range =
+            TickerInfo::new("TEST", "2022-01-01", "2023-12-31", Currency::Usd).unwrap();
+        let aligned = align_prices(
+            &record,
+            &benchmark_with_gaps,
+            &full_range,
+            returns::enums::ReturnBasis::Close,
+        )
+        .unwrap();
+        assert!(aligned.get_timestamps().len() == benchmark_with_gaps.get_timestamps().len());
+        assert!(aligned.get_benchmark_prices() == benchmark_with_gaps.get_close_prices());
+        // Record prices are a constant multiple of the benchmark prices on the common days
+        assert!(relative_strength(&aligned)
+            .iter()
+            .all(|x| (x - 1.0).abs() < 1e-3));
+
+        let ticker_info =
+            TickerInfo::new("TEST", "2023-01-01", "2023-12-31", Currency::Usd).unwrap();
+        assert!(align_prices(
+            &record,
+            &benchmark_with_gaps,
+            &ticker_info,
+            returns::enums::ReturnBasis::Close
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_rolling_regression() {
+        let benchmark = benchmark(0.0);
+        let record = leveraged(&benchmark, 2.0);
+        let aligned = align_prices(
+            &record,
+            &benchmark,
+            &ticker_info(),
+            returns::enums::ReturnBasis::Close,
+        )
+        .unwrap();
+        let regression = rolling_regression(&aligned, 20, 0.0).unwrap();
+        assert!(regression.get_betas().len() == aligned.get_timestamps().len());
+        assert!(regression.get_betas()[..20].iter().all(|x| x.is_nan()));
+        for idx in 20..regression.get_betas().len() {
+            assert!((regression.get_betas()[idx] - 2.0).abs() < 1e-3);
+            assert!(regression.get_alphas()[idx].abs() < 1e-3);
+            assert!((regression.get_correlations()[idx] - 1.0).abs() < 1e-3);
+        }
+        assert!(rolling_regression(&aligned, 1, 0.0).is_err());
+    }
+
+    #[test]
+    fn test_capture_ratios() {
+        let benchmark = benchmark(0.0);
+        let record = leveraged(&benchmark, 2.0);
+        let aligned = align_prices(
+            &record,
+            &benchmark,
+            &ticker_info(),
+            returns::enums::ReturnBasis::Close,
+        )
+        .unwrap();
+        let capture = capture_ratios(&aligned);
+        assert!(
+            capture.get_num_of_up_periods() + capture.get_num_of_down_periods()
+                == aligned.get_timestamps().len() - 1
+        );
+        assert!((capture.get_up_capture() - 2.0).abs() < 0.05);
+        assert!((capture.get_down_capture() - 2.0).abs() < 0.05);
+
+        let aligned = structs::AlignedPrices {
+            timestamps: aligned.get_timestamps()[..3].to_vec(),
+            prices: vec![100.0, 101.0, 102.01],
+            benchmark_prices: vec![100.0, 102.0, 104.04],
+        };
+        let capture = capture_ratios(&aligned);
+        assert!((capture.get_up_capture() - 0.5).abs() < 1e-4);
+        assert!(capture.get_down_capture().is_nan());
+    }
+
+    #[test]
+    fn test_excess_returns_by_period() {
+        let benchmark = benchmark(0.05);
+        let record = leveraged(&benchmark, 1.5);
+        let aligned = align_prices(
+            &record,
+            &benchmark,
+            &ticker_info(),
+            returns::enums::ReturnBasis::Close,
+        )
+        .unwrap();
+        let monthly = excess_returns_by_period(&aligned, AggregationPeriod::Monthly).unwrap();
+        assert!(monthly.len() == 12);
+        assert!(monthly
+            .iter()
+            .all(|x| x.get_period_start().format("%d").to_string() == "01"));
+
+        // Compounding the period returns recovers the total return over the range
+        let compound = |values: Vec<f32>| values.iter().fold(1.0, |acc, x| acc * (1.0 + x));
+        let prices = aligned.get_prices();
+        let total = prices[prices.len() - 1] / prices[0];
+        assert!(
+            (compound(monthly.iter().map(|x| x.get_period_return()).collect()) - total).abs()
+                < 1e-3
+        );
+        let benchmark_prices = aligned.get_benchmark_prices();
+        let benchmark_total = benchmark_prices[benchmark_prices.len() - 1] / benchmark_prices[0];
+        assert!(
+            (compound(monthly.iter().map(|x| x.get_benchmark_return()).collect())
+                - benchmark_total)
+                .abs()
+                < 1e-3
+        );
+
+        let sorted = sorted_excess_returns(&monthly);
+        assert!(sorted.windows(2).all(|x| x[0] <= x[1]));
+        let rate = outperformance_rate(&monthly).unwrap();
+        assert!((0.0..=1.0).contains(&rate));
+        assert!(outperformance_rate(&[]).is_err());
+        let quarterly = excess_returns_by_period(&aligned, AggregationPeriod::Quarterly).unwrap();
+        assert!(quarterly.len() == 4);
+
+        // Weeks across the end of 2024, when Monday 30 Dec starts ISO week 1 of 2025
+        let config = synthetic::structs::SyntheticConfig::new(
+            "BENCH",
+            Currency::Usd,
+            chrono::NaiveDate::from_ymd(2024, 12, 2),
+            30,
+            17,
+        )
+        .unwrap();
+        let benchmark = synthetic::generate_record(&config).unwrap();
+        let record = leveraged(&benchmark, 1.5);
+        let ticker_info =
+            TickerInfo::new("TEST", "2024-12-01", "2025-01-31", Currency::Usd).unwrap();
+        let aligned = align_prices(
+            &record,
+            &benchmark,
+            &ticker_info,
+            returns::enums::ReturnBasis::Close,
+        )
+        .unwrap();
+        let weekly = excess_returns_by_period(&aligned, AggregationPeriod::Weekly).unwrap();
+        let period_starts = weekly
+            .iter()
+            .map(|x| x.get_period_start().format("%Y-%m-%d").to_string())
+            .collect::<Vec<String>>();
+        assert!(
+            period_starts
+                == [
+                    "2024-12-02",
+                    "2024-12-09",
+                    "2024-12-16",
+                    "2024-12-23",
+                    "2024-12-30",
+                    "2025-01-06",
+                    "2025-01-13"
+                ]
+        );
+        let prices = aligned.get_prices();
+        assert!(
+            (compound(weekly.iter().map(|x| x.get_period_return()).collect())
+                - prices[prices.len() - 1] / prices[0])
+                .abs()
+                < 1e-3
+        );
+    }
+}
@@ -40,8 +40,114 @@ pub mod structs {
             }
         }
 
-        pub fn get_adj_close_prices(&self) -> &[f32] {
-            &self.adj_close
+        /// Appends a single bar to the record, keeping all the internal arrays aligned.
+        #[allow(clippy::too_many_arguments)]
+        pub fn push_record(
+            &mut self,
+            timestamp: chrono::DateTime<chrono::Utc>,
+            open_price: f32,
+            high_price: f32,
+            low_price: f32,
+            close_price: f32,
+            adj_close_price: f32,
+            volume: i32,
+        ) {
+            self.timestamps.push(timestamp);
+            self.open_prices.push(open_price);
+            self.high_prices.push(high_price);
+            self.low_prices.push(low_price);
+            self.close_prices.push(close_price);
+            self.adj_close.push(adj_close_price);
+            self.volume.push(volume);
+        }
+    }
+
+    /// Test fixtures of the "TEST" ticker quoted in USD, with daily bars from Monday 3 Jan 2022 (weekends included).
+    #[cfg(test)]
+    impl YahooFinancePriceRecord {
+        /// Record of (open, high, low, close, volume) bars, with the adjusted close equal to the close.
+        pub(crate) fn from_bars(bars: &[(f32, f32, f32, f32, i32)]) -> Self {
+            let start = chrono::Utc.ymd(2022, 1, 3).and_hms(0, 0, 0);
+            let mut record = YahooFinancePriceRecord::new("TEST", bars.len(), enums::Currency::Usd);
+            for (idx, (open, high, low, close, volume)) in bars.iter().enumerate() {
+                record.push_record(
+                    start + chrono::Duration::days(idx as i64),
+                    *open,
+                    *high,
+                    *low,
+                    *close,
+                    *close,
+                    *volume,
+                );
+            }
+            record
+        }
+
+        /// Record of flat bars at the close prices, with a volume of 1000.
+        pub(crate) fn from_closes(closes: &[f32]) -> Self {
+            YahooFinancePriceRecord::from_bars(
+                &closes
+                    .iter()
+                    .map(|&close| (close, close, close, close, 1000))
+                    .collect::<Vec<_>>(),
+            )
+        }
+
+        /// Record of (open, high, low, close) bars, with a volume of 1000.
+        pub(crate) fn from_ohlc(bars: &[(f32, f32, f32, f32)]) -> Self {
+            YahooFinancePriceRecord::from_bars(
+                &bars
+                    .iter()
+                    .map(|&(open, high, low, close)| (open, high, low, close, 1000))
+                    .collect::<Vec<_>>(),
+            )
+        }
+
+        /// Record of (high, low, close, volume) bars, opening at the close.
+        pub(crate) fn from_hlcv(bars: &[(f32, f32, f32, i32)]) -> Self {
+            YahooFinancePriceRecord::from_bars(
+                &bars
+                    .iter()
+                    .map(|&(high, low, close, volume)| (close, high, low, close, volume))
+                    .collect::<Vec<_>>(),
+            )
+        }
+
+        /// Record of (high, low, close) bars, opening at the close, with a volume of 1000.
+        pub(crate) fn from_hlc(bars: &[(f32, f32, f32)]) -> Self {
+            YahooFinancePriceRecord::from_hlcv(
+                &bars
+                    .iter()
+                    .map(|&(high, low, close)| (high, low, close, 1000))
+                    .collect::<Vec<_>>(),
+            )
+        }
+
+        /// Record of flat bars compounding the period returns from a close of 100.
+        pub(crate) fn from_returns(returns: &[f32]) -> Self {
+            let mut closes = vec![100.0];
+            for period_return in returns {
+                closes.push(closes[closes.len() - 1] * (1.0 + period_return));
+            }
+            YahooFinancePriceRecord::from_closes(&closes)
+        }
+
+        pub(crate) fn with_ticker_symbol(mut self, ticker_symbol: &str) -> Self {
+            self.ticker = ticker_symbol.to_owned();
+            self
+        }
+
+        pub(crate) fn with_currency(mut self, currency: enums::Currency) -> Self {
+            self.currency = currency;
+            self
+        }
+
+        /// Re-dates the bars to consecutive days from the start.
+        pub(crate) fn with_start_datetime(mut self, start: chrono::DateTime<chrono::Utc>) -> Self {
+            for (idx, timestamp) in self.timestamps.iter_mut().enumerate() {
+                *timestamp = start + chrono::Duration::days(idx as i64);
+            }
+            self
         }
     }
 
@@ -62,6 +168,10 @@ pub mod structs {
             &self.close_prices
         }
 
+        fn get_adj_close_prices(&self) -> &[f32] {
+            &self.adj_close
+        }
+
         fn get_currency(&self) -> enums::Currency {
             self.currency
         }
@@ -137,6 +247,22 @@ pub mod structs {
                 currency,
             })
         }
+
+        pub fn get_ticker_symbol(&self) -> &str {
+            self.ticker_symbol
+        }
+
+        pub fn get_start_datetime(&self) -> chrono::DateTime<chrono::Utc> {
+            self.start_datetime
+        }
+
+        pub fn get_end_datetime(&self) -> chrono::DateTime<chrono::Utc> {
+            self.end_datetime
+        }
+
+        pub fn get_currency(&self) -> enums::Currency {
+            self.currency
+        }
     }
 }
 
@@ -158,6 +284,11 @@ pub mod traits {
 
         fn get_close_prices(&self) -> &[f32];
 
+        /// Sources without corporate action adjustments fall back to the raw close prices.
+        fn get_adj_close_prices(&self) -> &[f32] {
+            self.get_close_prices()
+        }
+
         fn get_currency(&self) -> enums::Currency;
     }
 
@@ -0,0 +1,307 @@
+//! Objective: Provide drawdown analysis over a price or equity series.
+
+use crate::datasets::traits::{Prices, Timestamps};
+use crate::errors::CalculationError;
+use crate::returns;
+use crate::statistics;
+
+pub mod structs {
+    /// A single drawdown episode, spanning from the peak until the series recovers to the peak value.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct DrawdownEpisode {
+        pub(super) peak_datetime: chrono::DateTime<chrono::Utc>,
+        pub(super) trough_datetime: chrono::DateTime<chrono::Utc>,
+        pub(super) recovery_datetime: Option<chrono::DateTime<chrono::Utc>>, // None if the series has not recovered
+        pub(super) peak_value: f32,
+        pub(super) trough_value: f32,
+        pub(super) depth: f32,
+        pub(super) duration: usize,
+    }
+
+    impl DrawdownEpisode {
+        pub fn get_peak_datetime(&self) -> chrono::DateTime<chrono::Utc> {
+            self.peak_datetime
+        }
+
+        pub fn get_trough_datetime(&self) -> chrono::DateTime<chrono::Utc> {
+            self.trough_datetime
+        }
+
+        pub fn get_recovery_datetime(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+            self.recovery_datetime
+        }
+
+        pub fn get_peak_value(&self) -> f32 {
+            self.peak_value
+        }
+
+        pub fn get_trough_value(&self) -> f32 {
+            self.trough_value
+        }
+
+        /// Depth of the drawdown as a negative fraction of the peak value (e.g. -0.2 for a 20% drawdown).
+        pub fn get_depth(&self) -> f32 {
+            self.depth
+        }
+
+        /// Number of bars between the peak and the recovery (or the end of the series if unrecovered).
+        pub fn get_duration(&self) -> usize {
+            self.duration
+        }
+    }
+
+    #[derive(Debug)]
+    pub struct DrawdownAnalysis {
+        pub(super) running_peaks: Vec<f32>,
+        pub(super) drawdowns: Vec<f32>,
+        pub(super) durations: Vec<usize>,
+        pub(super) episodes: Vec<DrawdownEpisode>,
+        pub(super) ulcer_index: f32,
+    }
+
+    impl DrawdownAnalysis {
+        pub fn get_running_peaks(&self) -> &[f32] {
+            &self.running_peaks
+        }
+
+        pub fn get_drawdowns(&self) -> &[f32] {
+            &self.drawdowns
+        }
+
+        pub fn get_durations(&self) -> &[usize] {
+            &self.durations
+        }
+
+        /// Drawdown episodes in chronological order.
+        pub fn get_episodes(&self) -> &[DrawdownEpisode] {
+            &self.episodes
+        }
+
+        pub fn get_ulcer_index(&self) -> f32 {
+            self.ulcer_index
+        }
+
+        /// Episode with the deepest drawdown, None if the series never declined from its peak.
+        pub fn get_max_drawdown(&self) -> Option<&DrawdownEpisode> {
+            self.episodes
+                .iter()
+                .min_by(|a, b| a.depth.partial_cmp(&b.depth).unwrap()) // Depths are finite for positive series
+        }
+
+        /// Top N drawdown episodes, sorted from the deepest to the shallowest.
+        pub fn get_top_episodes(&self, n: usize) -> Vec<&DrawdownEpisode> {
+            let mut episodes = self.episodes.iter().collect::<Vec<_>>();
+            episodes.sort_by(|a, b| a.depth.partial_cmp(&b.depth).unwrap());
+            episodes.truncate(n);
+            episodes
+        }
+    }
+}
+
+/// Highest value observed up to and including each timestamp.
+pub fn running_peaks(values: &[f32]) -> Vec<f32> {
+    let mut peak = f32::NEG_INFINITY;
+    values
+        .iter()
+        .map(|&value| {
+            peak = peak.max(value); // f32::max ignores NaN values
+            peak
+        })
+        .collect()
+}
+
+/// Drawdown of each timestamp as a fraction of the running peak. Values are 0 at new peaks and negative otherwise.
+pub fn drawdown_series(values: &[f32]) -> Vec<f32> {
+    running_peaks(values)
+        .into_iter()
+        .zip(values)
+        .map(|(peak, value)| value / peak - 1.0)
+        .collect()
+}
+
+/// Number of bars elapsed since the last running peak, for each timestamp.
+pub fn drawdown_durations(values: &[f32]) -> Vec<usize> {
+    let mut duration = 0;
+    drawdown_series(values)
+        .into_iter()
+        .map(|drawdown| {
+            if drawdown < 0.0 {
+                duration += 1;
+            } else {
+                duration = 0;
+            }
+            duration
+        })
+        .collect()
+}
+
+/// Ulcer index of the series: the root mean square of the percentage drawdowns. NaN values are skipped.
+pub fn ulcer_index(values: &[f32]) -> Result<f32, CalculationError> {
+    let squared_drawdowns = drawdown_series(values)
+        .into_iter()
+        .filter(|x| x.is_finite())
+        .map(|drawdown| (drawdown * 100.0).powi(2))
+        .collect::<Vec<f32>>();
+    if squared_drawdowns.is_empty() {
+        return Err(CalculationError::InsufficientDataError(
+            "Ulcer index requires at least 1 valid value.".to_string(),
+        ));
+    }
+
+    Ok(statistics::mean(&squared_drawdowns).sqrt())
+}
+
+/// Identifies each drawdown episode within the series, in chronological order.
+pub fn drawdown_episodes(
+    timestamps: &[chrono::DateTime<chrono::Utc>],
+    values: &[f32],
+) -> Result<Vec<structs::DrawdownEpisode>, CalculationError> {
+    if timestamps.len() != values.len() {
+        return Err(CalculationError::InconsistentLengthError(format!(
+            "Length of the timestamps array: {} \n Length of the values array: {}",
+            timestamps.len(),
+            values.len()
+        )));
+    }
+
+    let mut episodes = Vec::new();
+    let mut current_episode: Option<structs::DrawdownEpisode> = None;
+    let mut peak_idx = 0;
+
+    for (idx, &value) in values.iter().enumerate() {
+        if value.is_nan() {
+            continue;
+        }
+        let peak_value = values[peak_idx];
+
+        if value >= peak_value || peak_value.is_nan() {
+            // New peak reached - closing off the ongoing episode if present
+            if let Some(mut episode) = current_episode.take() {
+                episode.recovery_datetime = Some(timestamps[idx]);
+                episode.duration = idx - peak_idx;
+                episodes.push(episode);
+            }
+            peak_idx = idx;
+            continue;
+        }
+
+        let depth = value / peak_value - 1.0;
+        match current_episode.as_mut() {
+            Some(episode) => {
+                if depth < episode.depth {
+                    episode.trough_datetime = timestamps[idx];
+                    episode.trough_value = value;
+                    episode.depth = depth;
+                }
+            }
+            None => {
+                current_episode = Some(structs::DrawdownEpisode {
+                    peak_datetime: timestamps[peak_idx],
+                    trough_datetime: timestamps[idx],
+                    recovery_datetime: None,
+                    peak_value,
+                    trough_value: value,
+                    depth,
+                    duration: 0,
+                })
+            }
+        }
+    }
+
+    // Handling the unrecovered episode at the end of the series
+    if let Some(mut episode) = current_episode {
+        episode.duration = values.len() - 1 - peak_idx;
+        episodes.push(episode);
+    }
+
+    Ok(episodes)
+}
+
+/// Performs the full drawdown analysis over the selected price series of the record.
+pub fn analyze_drawdowns<T>(
+    record: &T,
+    basis: returns::enums::ReturnBasis,
+) -> Result<structs::DrawdownAnalysis, CalculationError>
+where
+    T: Prices + Timestamps,
+{
+    let values = returns::get_basis_prices(record, basis);
+    analyze_series_drawdowns(record.get_timestamps(), values)
+}
+
+/// Performs the full drawdown analysis over an arbitrary series, such as a backtest equity curve.
+pub fn analyze_series_drawdowns(
+    timestamps: &[chrono::DateTime<chrono::Utc>],
+    values: &[f32],
+) -> Result<structs::DrawdownAnalysis, CalculationError> {
+    let episodes = drawdown_episodes(timestamps, values)?;
+
+    Ok(structs::DrawdownAnalysis {
+        running_peaks: running_peaks(values),
+        drawdowns: drawdown_series(values),
+        durations: drawdown_durations(values),
+        episodes,
+        ulcer_index: ulcer_index(values)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn test_timestamps(n: usize) -> Vec<chrono::DateTime<chrono::Utc>> {
+        (0..n)
+            .map(|x| {
+                chrono::Utc.ymd(2022, 1, 1).and_hms(0, 0, 0) + chrono::Duration::days(x as i64)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_drawdown_series() {
+        let values = [100.0, 120.0, 90.0, 108.0, 130.0];
+        assert!(running_peaks(&values) == vec![100.0, 120.0, 120.0, 120.0, 130.0]);
+        let drawdowns = drawdown_series(&values);
+        assert!((drawdowns[2] + 0.25).abs() < 1e-6);
+        assert!((drawdowns[3] + 0.1).abs() < 1e-6);
+        assert!(drawdown_durations(&values) == vec![0, 0, 1, 2, 0]);
+    }
+
+    #[test]
+    fn test_drawdown_episodes() {
+        let values = [100.0, 120.0, 90.0, 108.0, 130.0, 117.0, 104.0];
+        let timestamps = test_timestamps(values.len());
+        let analysis = analyze_series_drawdowns(&timestamps, &values).unwrap();
+        let episodes = analysis.get_episodes();
+        assert!(episodes.len() == 2);
+
+        let first = &episodes[0];
+        assert!(first.get_peak_datetime() == timestamps[1]);
+        assert!(first.get_trough_datetime() == timestamps[2]);
+        assert!(first.get_recovery_datetime() == Some(timestamps[4]));
+        assert!(first.get_duration() == 3);
+
+        let second = &episodes[1];
+        assert!(second.get_recovery_datetime().is_none());
+        assert!((second.get_depth() + 0.2).abs() < 1e-6);
+        assert!(second.get_duration() == 2);
+
+        let max_drawdown = analysis.get_max_drawdown().unwrap();
+        assert!((max_drawdown.get_depth() + 0.25).abs() < 1e-6);
+        assert!(analysis.get_top_episodes(1)[0] == max_drawdown);
+    }
+
+    #[test]
+    fn test_ulcer_index() {
+        let values = [100.0, 90.0, 100.0, 80.0];
+        let expected = ((100.0_f32 + 400.0) / 4.0).sqrt();
+        assert!((ulcer_index(&values).unwrap() - expected).abs() < 1e-4);
+        assert!(ulcer_index(&[]).is_err());
+
+        // NaN values are excluded from the mean
+        let values = [100.0, f32::NAN, 90.0, 100.0, 80.0];
+        assert!((ulcer_index(&values).unwrap() - expected).abs() < 1e-4);
+        assert!(ulcer_index(&[f32::NAN]).is_err());
+    }
+}
@@ -3,3 +3,26 @@ pub enum Currency {
     Sgd,
     Usd,
 }
+
+/// Sampling frequency of the bars within a record, used for annualizing the calculated metrics.
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum BarFrequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Quarterly,
+    Yearly,
+}
+
+impl BarFrequency {
+    /// Number of bars expected within a single year.
+    pub fn periods_per_year(&self) -> f32 {
+        match self {
+            BarFrequency::Daily => 252.0, // Trading days within a year
+            BarFrequency::Weekly => 52.0,
+            BarFrequency::Monthly => 12.0,
+            BarFrequency::Quarterly => 4.0,
+            BarFrequency::Yearly => 1.0,
+        }
+    }
+}
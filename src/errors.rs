@@ -68,6 +68,31 @@ impl std::fmt::Display for AggregationError {
     }
 }
 
+#[derive(Debug)]
+pub enum CalculationError {
+    InconsistentLengthError(String),
+    InsufficientDataError(String),
+    InvalidParameterError(String),
+}
+
+impl std::error::Error for CalculationError {}
+
+impl std::fmt::Display for CalculationError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CalculationError::InconsistentLengthError(err) => {
+                std::fmt::write(formatter, format_args!("Error occured in line {} due to inconsistencies observed between array lengths. See the error raised: {}", line!(), err))
+            }
+            CalculationError::InsufficientDataError(err) => {
+                std::fmt::write(formatter, format_args!("Error occured in line {} due to insufficient data points for the calculation. See the error raised: {}", line!(), err))
+            }
+            CalculationError::InvalidParameterError(err) => {
+                std::fmt::write(formatter, format_args!("Error occured in line {} due to an invalid parameter passed to the calculation. See the error raised: {}", line!(), err))
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum InputError {
     ExcessiveArgsError(String),
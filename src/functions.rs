@@ -7,6 +7,7 @@ use num_traits::{self, FromPrimitive, Num};
 
 pub mod Grouping {
     use super::*;
+    use crate::inputs::enums::AggregationPeriod;
     use chrono::Datelike;
     use std::{iter::Zip, slice::Iter};
 
@@ -65,6 +66,87 @@ pub mod Grouping {
 
         Ok(result)
     }
+
+    /// Start of the period containing the timestamp: the Monday of its ISO week, or the first day of its month or
+    /// quarter.
+    pub fn period_start<T>(
+        timestamp: &T,
+        period: AggregationPeriod,
+    ) -> chrono::DateTime<chrono::Utc>
+    where
+        T: chrono::Datelike,
+    {
+        let naive_date = match period {
+            AggregationPeriod::Weekly => chrono::NaiveDate::from_isoywd(
+                timestamp.iso_week().year(),
+                timestamp.iso_week().week(),
+                chrono::Weekday::Mon,
+            ),
+            AggregationPeriod::Monthly => {
+                chrono::NaiveDate::from_ymd(timestamp.year(), timestamp.month(), 1)
+            }
+            AggregationPeriod::Quarterly => {
+                chrono::NaiveDate::from_ymd(timestamp.year(), timestamp.month0() / 3 * 3 + 1, 1)
+            }
+        };
+
+        chrono::Date::from_utc(naive_date, chrono::Utc).and_hms(0, 0, 0)
+    }
+
+    /// Function will aggregate the dataset over the given period.
+    pub fn groupby_period<'a, T, U>(
+        timestamps: &'a [T],
+        values: &'a [U],
+        period: AggregationPeriod,
+    ) -> Result<GroupedBy<'a, T, U>, AggregationError>
+    where
+        T: chrono::Datelike,
+    {
+        // Grouping functions are function items for the same reason as the weekly grouping function above
+        fn _weekly_grouping_function<X, Y>(x: &(&X, &Y)) -> chrono::DateTime<chrono::Utc>
+        where
+            X: chrono::Datelike,
+        {
+            period_start(x.0, AggregationPeriod::Weekly)
+        }
+
+        fn _monthly_grouping_function<X, Y>(x: &(&X, &Y)) -> chrono::DateTime<chrono::Utc>
+        where
+            X: chrono::Datelike,
+        {
+            period_start(x.0, AggregationPeriod::Monthly)
+        }
+
+        fn _quarterly_grouping_function<X, Y>(x: &(&X, &Y)) -> chrono::DateTime<chrono::Utc>
+        where
+            X: chrono::Datelike,
+        {
+            period_start(x.0, AggregationPeriod::Quarterly)
+        }
+
+        let grouping_function = match period {
+            AggregationPeriod::Weekly => {
+                _weekly_grouping_function as fn(&(&T, &U)) -> chrono::DateTime<chrono::Utc>
+            }
+            AggregationPeriod::Monthly => {
+                _monthly_grouping_function as fn(&(&T, &U)) -> chrono::DateTime<chrono::Utc>
+            }
+            AggregationPeriod::Quarterly => {
+                _quarterly_grouping_function as fn(&(&T, &U)) -> chrono::DateTime<chrono::Utc>
+            }
+        };
+
+        // Validating that the lengths of the arrays are equal.
+        if timestamps.len() != values.len() {
+            return Err(AggregationError::InconsistentLengthError(format!(
+                "Length of the timestamps array: {} \n Length of the values array: {}",
+                timestamps.len(),
+                values.len()
+            )));
+        }
+
+        Ok(timestamps.iter().zip(values).group_by(grouping_function))
+    }
 }
 
 pub mod AggregationFunctions {
@@ -197,20 +279,21 @@ where
 mod tests {
 
     use super::*;
-    use crate::datasets;
     use crate::datasets::traits::*;
     use crate::enums;
+    use crate::synthetic;
 
     #[test]
     fn visualize_groupby_weekly() {
-        let foo = datasets::structs::TickerInfo::new(
+        let foo = synthetic::structs::SyntheticConfig::new(
             "AAPL",
-            "2022-01-01 00:00:00",
-            "2022-04-01 00:00:00",
             enums::Currency::Usd,
+            chrono::NaiveDate::from_ymd(2022, 1, 3),
+            63,
+            0,
         )
         .unwrap();
-        let bar = datasets::source_yahoo_finance(&foo).unwrap();
+        let bar = synthetic::generate_record(&foo).unwrap();
         let baz = Grouping::groupby_weekly(bar.get_timestamps(), bar.get_high_prices()).unwrap();
         for qux in baz.into_iter() {
             dbg!(&qux.0);
@@ -220,6 +303,71 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_groupby_period() {
+        use crate::inputs::enums::AggregationPeriod;
+        use chrono::TimeZone;
+
+        // Saturday 1 Jan 2022 falls within ISO week 52 of 2021
+        let timestamp = chrono::Utc.ymd(2022, 1, 1).and_hms(0, 0, 0);
+        assert!(
+            Grouping::period_start(&timestamp, AggregationPeriod::Weekly)
+                == chrono::Utc.ymd(2021, 12, 27).and_hms(0, 0, 0)
+        );
+        let timestamp = chrono::Utc.ymd(2022, 8, 17).and_hms(9, 30, 0);
+        assert!(
+            Grouping::period_start(&timestamp, AggregationPeriod::Monthly)
+                == chrono::Utc.ymd(2022, 8, 1).and_hms(0, 0, 0)
+        );
+        assert!(
+            Grouping::period_start(&timestamp, AggregationPeriod::Quarterly)
+                == chrono::Utc.ymd(2022, 7, 1).and_hms(0, 0, 0)
+        );
+
+        let timestamps = [
+            chrono::Utc.ymd(2022, 3, 30).and_hms(0, 0, 0),
+            chrono::Utc.ymd(2022, 3, 31).and_hms(0, 0, 0),
+            chrono::Utc.ymd(2022, 4, 1).and_hms(0, 0, 0),
+            chrono::Utc.ymd(2022, 6, 30).and_hms(0, 0, 0),
+            chrono::Utc.ymd(2022, 7, 1).and_hms(0, 0, 0),
+        ];
+        let values = [1.0, 2.0, 3.0, 4.0, 5.0];
+        let grouped =
+            Grouping::groupby_period(&timestamps, &values, AggregationPeriod::Quarterly).unwrap();
+        let sizes = grouped
+            .into_iter()
+            .map(|(_key, group)| group.count())
+            .collect::<Vec<usize>>();
+        assert!(sizes == [2, 2, 1]);
+
+        // Monday 30 Dec 2024 starts ISO week 1 of 2025, which runs into January
+        let timestamps = [
+            chrono::Utc.ymd(2024, 12, 27).and_hms(0, 0, 0),
+            chrono::Utc.ymd(2024, 12, 30).and_hms(0, 0, 0),
+            chrono::Utc.ymd(2024, 12, 31).and_hms(0, 0, 0),
+            chrono::Utc.ymd(2025, 1, 2).and_hms(0, 0, 0),
+            chrono::Utc.ymd(2025, 1, 6).and_hms(0, 0, 0),
+        ];
+        let grouped =
+            Grouping::groupby_period(&timestamps, &values, AggregationPeriod::Weekly).unwrap();
+        let groups = grouped
+            .into_iter()
+            .map(|(key, group)| (key, group.count()))
+            .collect::<Vec<(chrono::DateTime<chrono::Utc>, usize)>>();
+        assert!(
+            groups
+                == [
+                    (chrono::Utc.ymd(2024, 12, 23).and_hms(0, 0, 0), 1),
+                    (chrono::Utc.ymd(2024, 12, 30).and_hms(0, 0, 0), 3),
+                    (chrono::Utc.ymd(2025, 1, 6).and_hms(0, 0, 0), 1)
+                ]
+        );
+        assert!(
+            Grouping::groupby_period(&timestamps, &values[1..], AggregationPeriod::Monthly)
+                .is_err()
+        );
+    }
+
     // #[test]
     // fn visualize_aggregationfunctions_max() {
     //     let foo = datasets::structs::TickerInfo::new(
@@ -238,14 +386,15 @@ mod tests {
 
     #[test]
     fn visualize_openclose_delta() {
-        let foo = datasets::structs::TickerInfo::new(
+        let foo = synthetic::structs::SyntheticConfig::new(
             "EEM",
-            "2022-03-29 00:00:00",
-            "2022-04-29 00:00:00",
             enums::Currency::Usd,
+            chrono::NaiveDate::from_ymd(2022, 3, 29),
+            23,
+            0,
         )
         .unwrap();
-        let bar = datasets::source_yahoo_finance(&foo).unwrap();
+        let bar = synthetic::generate_record(&foo).unwrap();
         let baz = bar
             .get_open_prices()
             .into_iter()
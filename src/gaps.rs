@@ -0,0 +1,314 @@
+//! Objective: Detect opening gaps versus the previous close, track when each gap is filled, and summarize the fill statistics.
+
+use std::collections::BTreeMap;
+
+use crate::datasets::traits::{Prices, Timestamps};
+use crate::errors::CalculationError;
+use crate::functions;
+use crate::volatility::validate_ohlc_lengths;
+
+pub mod enums {
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize)]
+    pub enum GapDirection {
+        Up,
+        Down,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize)]
+    pub enum GapType {
+        Full,    // Opens beyond the previous bar's high (gap up) or low (gap down)
+        Partial, // Opens beyond the previous close, but within the previous bar's range
+    }
+}
+
+pub mod structs {
+    use super::enums::{GapDirection, GapType};
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct Gap {
+        pub(super) index: usize,
+        pub(super) timestamp: chrono::DateTime<chrono::Utc>,
+        pub(super) direction: GapDirection,
+        pub(super) gap_type: GapType,
+        pub(super) previous_close: f32,
+        pub(super) open: f32,
+        pub(super) gap_size: f32, // Open relative to the previous close, as a fraction
+        pub(super) fill_timestamp: Option<chrono::DateTime<chrono::Utc>>, // None if the gap remains unfilled
+        pub(super) bars_to_fill: Option<usize>, // 0 if the gap is filled within the gap bar itself
+    }
+
+    impl Gap {
+        pub fn get_index(&self) -> usize {
+            self.index
+        }
+
+        pub fn get_timestamp(&self) -> chrono::DateTime<chrono::Utc> {
+            self.timestamp
+        }
+
+        pub fn get_direction(&self) -> GapDirection {
+            self.direction
+        }
+
+        pub fn get_gap_type(&self) -> GapType {
+            self.gap_type
+        }
+
+        pub fn get_previous_close(&self) -> f32 {
+            self.previous_close
+        }
+
+        pub fn get_open(&self) -> f32 {
+            self.open
+        }
+
+        pub fn get_gap_size(&self) -> f32 {
+            self.gap_size
+        }
+
+        pub fn get_fill_timestamp(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+            self.fill_timestamp
+        }
+
+        pub fn get_bars_to_fill(&self) -> Option<usize> {
+            self.bars_to_fill
+        }
+
+        pub fn is_filled(&self) -> bool {
+            self.bars_to_fill.is_some()
+        }
+    }
+
+    /// Fill statistics over the gaps of a given direction and type.
+    #[derive(Clone, Debug, PartialEq, serde::Serialize)]
+    pub struct GapSummary {
+        pub(super) direction: GapDirection,
+        pub(super) gap_type: GapType,
+        pub(super) num_of_gaps: usize,
+        pub(super) num_of_filled: usize,
+        pub(super) fill_probability: f32,
+        pub(super) mean_gap_size: f32,
+        pub(super) median_bars_to_fill: Option<f32>, // Over the filled gaps only
+        pub(super) p90_bars_to_fill: Option<f32>,
+        pub(super) cumulative_fill_probability: Vec<f32>, // Element k: fraction of the gaps filled within k bars
+    }
+
+    impl GapSummary {
+        pub fn get_direction(&self) -> GapDirection {
+            self.direction
+        }
+
+        pub fn get_gap_type(&self) -> GapType {
+            self.gap_type
+        }
+
+        pub fn get_num_of_gaps(&self) -> usize {
+            self.num_of_gaps
+        }
+
+        pub fn get_num_of_filled(&self) -> usize {
+            self.num_of_filled
+        }
+
+        pub fn get_fill_probability(&self) -> f32 {
+            self.fill_probability
+        }
+
+        pub fn get_mean_gap_size(&self) -> f32 {
+            self.mean_gap_size
+        }
+
+        pub fn get_median_bars_to_fill(&self) -> Option<f32> {
+            self.median_bars_to_fill
+        }
+
+        pub fn get_p90_bars_to_fill(&self) -> Option<f32> {
+            self.p90_bars_to_fill
+        }
+
+        pub fn get_cumulative_fill_probability(&self) -> &[f32] {
+            &self.cumulative_fill_probability
+        }
+    }
+}
+
+/// Detects the gaps where the open moves beyond the previous close by at least the threshold fraction
+/// (e.g. 0.01 for 1%). A gap up is filled once a subsequent low trades back down to the previous close,
+/// and a gap down once a subsequent high trades back up to it, including the gap bar itself.
+pub fn detect_gaps<T>(record: &T, threshold: f32) -> Result<Vec<structs::Gap>, CalculationError>
+where
+    T: Prices + Timestamps,
+{
+    if threshold.is_nan() || threshold < 0.0 {
+        return Err(CalculationError::InvalidParameterError(format!(
+            "Gap threshold must be non-negative. Threshold provided: {}",
+            threshold
+        )));
+    }
+    let open = record.get_open_prices();
+    let high = record.get_high_prices();
+    let low = record.get_low_prices();
+    let close = record.get_close_prices();
+    let length = validate_ohlc_lengths(open, high, low, close)?;
+    let timestamps = record.get_timestamps();
+    if timestamps.len() != length {
+        return Err(CalculationError::InconsistentLengthError(format!(
+            "Length of the timestamps array: {} \n Length of the prices array: {}",
+            timestamps.len(),
+            length
+        )));
+    }
+
+    Ok((1..length)
+        .filter_map(|idx| {
+            let previous_close = close[idx - 1];
+            let gap_size = open[idx] / previous_close - 1.0;
+            let (direction, gap_type) = if open[idx] > previous_close && gap_size >= threshold {
+                let gap_type = if open[idx] > high[idx - 1] {
+                    enums::GapType::Full
+                } else {
+                    enums::GapType::Partial
+                };
+                (enums::GapDirection::Up, gap_type)
+            } else if open[idx] < previous_close && -gap_size >= threshold {
+                let gap_type = if open[idx] < low[idx - 1] {
+                    enums::GapType::Full
+                } else {
+                    enums::GapType::Partial
+                };
+                (enums::GapDirection::Down, gap_type)
+            } else {
+                return None;
+            };
+
+            let fill_index = (idx..length).find(|&x| match direction {
+                enums::GapDirection::Up => low[x] <= previous_close,
+                enums::GapDirection::Down => high[x] >= previous_close,
+            });
+            Some(structs::Gap {
+                index: idx,
+                timestamp: timestamps[idx],
+                direction,
+                gap_type,
+                previous_close,
+                open: open[idx],
+                gap_size,
+                fill_timestamp: fill_index.map(|x| timestamps[x]),
+                bars_to_fill: fill_index.map(|x| x - idx),
+            })
+        })
+        .collect())
+}
+
+/// Summarizes the fill probability and time to fill distribution for each direction and type of gap.
+/// Gaps which are unfilled by the end of the record count as unfilled.
+pub fn summarize_gaps(gaps: &[structs::Gap]) -> Vec<structs::GapSummary> {
+    let mut grouped_gaps: BTreeMap<(enums::GapDirection, enums::GapType), Vec<&structs::Gap>> =
+        BTreeMap::new();
+    gaps.iter().for_each(|gap| {
+        grouped_gaps
+            .entry((gap.direction, gap.gap_type))
+            .or_default()
+            .push(gap)
+    });
+
+    grouped_gaps
+        .into_iter()
+        .map(|((direction, gap_type), group)| {
+            let num_of_gaps = group.len();
+            let mut bars_to_fill = group
+                .iter()
+                .filter_map(|gap| gap.bars_to_fill.map(|x| x as f32))
+                .collect::<Vec<f32>>();
+            bars_to_fill.sort_by(|a, b| a.total_cmp(b));
+            let num_of_filled = bars_to_fill.len();
+
+            let max_bars_to_fill = bars_to_fill.last().map(|&x| x as usize);
+            let cumulative_fill_probability = match max_bars_to_fill {
+                Some(max_bars) => (0..=max_bars)
+                    .map(|k| {
+                        bars_to_fill.iter().filter(|&&x| x as usize <= k).count() as f32
+                            / num_of_gaps as f32
+                    })
+                    .collect(),
+                None => Vec::new(),
+            };
+            let percentile = |p: usize| -> Option<f32> {
+                if bars_to_fill.is_empty() {
+                    return None;
+                }
+                functions::percentile_from_sorted_array(p, &bars_to_fill).ok()
+            };
+
+            structs::GapSummary {
+                direction,
+                gap_type,
+                num_of_gaps,
+                num_of_filled,
+                fill_probability: num_of_filled as f32 / num_of_gaps as f32,
+                mean_gap_size: group.iter().map(|gap| gap.gap_size).sum::<f32>()
+                    / num_of_gaps as f32,
+                median_bars_to_fill: percentile(50),
+                p90_bars_to_fill: percentile(90),
+                cumulative_fill_probability,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datasets;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_detect_gaps() {
+        let record = datasets::structs::YahooFinancePriceRecord::from_ohlc(&[
+            (10.0, 10.5, 9.5, 10.0),
+            (11.0, 11.5, 10.8, 11.2), // Full gap up from 10.0, unfilled on the day
+            (10.9, 11.3, 10.5, 10.6), // Partial gap down from 11.2, filled on the day
+            (10.6, 10.8, 9.9, 10.2),  // No gap, fills the first gap 2 bars after it
+            (10.7, 10.9, 10.3, 10.5), // Partial gap up from 10.2, never filled
+        ]);
+        let gaps = detect_gaps(&record, 0.02).unwrap();
+        assert!(gaps.len() == 3);
+
+        assert!(gaps[0].get_direction() == enums::GapDirection::Up);
+        assert!(gaps[0].get_gap_type() == enums::GapType::Full);
+        assert!((gaps[0].get_gap_size() - 0.1).abs() < 1e-6);
+        assert!(gaps[0].get_bars_to_fill() == Some(2));
+        assert!(gaps[0].get_fill_timestamp() == Some(chrono::Utc.ymd(2022, 1, 6).and_hms(0, 0, 0)));
+
+        assert!(gaps[1].get_direction() == enums::GapDirection::Down);
+        assert!(gaps[1].get_gap_type() == enums::GapType::Partial);
+        assert!(gaps[1].get_bars_to_fill() == Some(0));
+
+        assert!(gaps[2].get_gap_type() == enums::GapType::Partial && !gaps[2].is_filled());
+        assert!(detect_gaps(&record, -0.01).is_err());
+    }
+
+    #[test]
+    fn test_summarize_gaps() {
+        let record = datasets::structs::YahooFinancePriceRecord::from_ohlc(&[
+            (10.0, 10.5, 9.5, 10.0),
+            (11.0, 11.5, 10.8, 11.2),
+            (10.9, 11.3, 10.5, 10.6),
+            (10.6, 10.8, 9.9, 10.2),
+            (10.7, 10.9, 10.3, 10.5),
+        ]);
+        let summary = summarize_gaps(&detect_gaps(&record, 0.02).unwrap());
+        // Ordered by direction, then gap type
+        assert!(summary.len() == 3);
+        let partial_up = &summary[1];
+        assert!(partial_up.get_direction() == enums::GapDirection::Up);
+        assert!(partial_up.get_num_of_gaps() == 1 && partial_up.get_fill_probability() == 0.0);
+        assert!(partial_up.get_median_bars_to_fill().is_none());
+
+        let full_up = &summary[0];
+        assert!(full_up.get_fill_probability() == 1.0);
+        assert!(full_up.get_median_bars_to_fill() == Some(2.0));
+        assert!(full_up.get_cumulative_fill_probability() == [0.0, 0.0, 1.0]);
+    }
+}
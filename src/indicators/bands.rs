@@ -0,0 +1,368 @@
+//! Volatility band and channel indicators, together with the average true range.
+
+use super::moving_averages::{ema_unchecked, sma_unchecked};
+use super::{get_price_series, validate_period};
+use crate::datasets::traits::Prices;
+use crate::errors::CalculationError;
+use crate::inputs::enums::BasePriceType;
+use crate::volatility::validate_ohlc_lengths;
+
+pub mod structs {
+
+    /// Upper, middle and lower lines of a band or channel indicator.
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct BandSeries {
+        pub(super) upper: Vec<f32>,
+        pub(super) middle: Vec<f32>,
+        pub(super) lower: Vec<f32>,
+    }
+
+    impl BandSeries {
+        pub fn get_upper(&self) -> &[f32] {
+            &self.upper
+        }
+
+        pub fn get_middle(&self) -> &[f32] {
+            &self.middle
+        }
+
+        pub fn get_lower(&self) -> &[f32] {
+            &self.lower
+        }
+    }
+
+    /// Chandelier exit levels for long and short positions.
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct ChandelierExits {
+        pub(super) long_exit: Vec<f32>,
+        pub(super) short_exit: Vec<f32>,
+    }
+
+    impl ChandelierExits {
+        pub fn get_long_exit(&self) -> &[f32] {
+            &self.long_exit
+        }
+
+        pub fn get_short_exit(&self) -> &[f32] {
+            &self.short_exit
+        }
+    }
+}
+
+/// True range of each bar. The first bar has no previous close, hence uses the high - low range.
+pub fn true_range<T>(record: &T) -> Result<Vec<f32>, CalculationError>
+where
+    T: Prices,
+{
+    let high = record.get_high_prices();
+    let low = record.get_low_prices();
+    let close = record.get_close_prices();
+    validate_ohlc_lengths(record.get_open_prices(), high, low, close)?;
+
+    Ok((0..close.len())
+        .map(|idx| {
+            let range = high[idx] - low[idx];
+            if idx == 0 {
+                return range;
+            }
+            range
+                .max((high[idx] - close[idx - 1]).abs())
+                .max((low[idx] - close[idx - 1]).abs())
+        })
+        .collect())
+}
+
+/// Wilder's average true range, seeded with the simple average of the first period.
+pub fn atr<T>(record: &T, period: usize) -> Result<Vec<f32>, CalculationError>
+where
+    T: Prices,
+{
+    validate_period(period)?;
+    let true_ranges = true_range(record)?;
+    let mut result = vec![f32::NAN; true_ranges.len()];
+    if true_ranges.len() < period {
+        return Ok(result);
+    }
+
+    result[period - 1] = true_ranges[..period].iter().sum::<f32>() / period as f32;
+    for idx in period..true_ranges.len() {
+        result[idx] = (result[idx - 1] * (period - 1) as f32 + true_ranges[idx]) / period as f32;
+    }
+    Ok(result)
+}
+
+/// Average true range as a percentage of the close price.
+pub fn normalized_atr<T>(record: &T, period: usize) -> Result<Vec<f32>, CalculationError>
+where
+    T: Prices,
+{
+    Ok(atr(record, period)?
+        .into_iter()
+        .zip(record.get_close_prices())
+        .map(|(atr, close)| atr / close * 100.0)
+        .collect())
+}
+
+/// Bollinger bands: SMA of the close prices +/- the multiplier times the population standard deviation.
+pub fn bollinger_bands<T>(
+    record: &T,
+    period: usize,
+    multiplier: f32,
+) -> Result<structs::BandSeries, CalculationError>
+where
+    T: Prices,
+{
+    validate_period(period)?;
+    let close = record.get_close_prices();
+    let middle = sma_unchecked(close, period);
+    let deviations = (0..close.len())
+        .map(|idx| {
+            if idx + 1 < period {
+                return f32::NAN;
+            }
+            let window = &close[idx + 1 - period..=idx];
+            (window
+                .iter()
+                .map(|x| (x - middle[idx]).powi(2))
+                .sum::<f32>()
+                / period as f32)
+                .sqrt()
+        })
+        .collect::<Vec<f32>>();
+
+    Ok(structs::BandSeries {
+        upper: middle
+            .iter()
+            .zip(&deviations)
+            .map(|(m, d)| m + multiplier * d)
+            .collect(),
+        lower: middle
+            .iter()
+            .zip(&deviations)
+            .map(|(m, d)| m - multiplier * d)
+            .collect(),
+        middle,
+    })
+}
+
+/// Position of the close price relative to the bands: 0 at the lower band and 1 at the upper band.
+pub fn percent_b<T>(record: &T, bands: &structs::BandSeries) -> Vec<f32>
+where
+    T: Prices,
+{
+    record
+        .get_close_prices()
+        .iter()
+        .zip(bands.upper.iter().zip(&bands.lower))
+        .map(|(close, (upper, lower))| (close - lower) / (upper - lower))
+        .collect()
+}
+
+/// Width of the bands relative to the middle line.
+pub fn bandwidth(bands: &structs::BandSeries) -> Vec<f32> {
+    (0..bands.middle.len())
+        .map(|idx| (bands.upper[idx] - bands.lower[idx]) / bands.middle[idx])
+        .collect()
+}
+
+/// Keltner channels: EMA of the close prices +/- the multiplier times the ATR.
+pub fn keltner_channels<T>(
+    record: &T,
+    ema_period: usize,
+    atr_period: usize,
+    multiplier: f32,
+) -> Result<structs::BandSeries, CalculationError>
+where
+    T: Prices,
+{
+    validate_period(ema_period)?;
+    let middle = ema_unchecked(record.get_close_prices(), ema_period);
+    let average_true_range = atr(record, atr_period)?;
+
+    Ok(structs::BandSeries {
+        upper: middle
+            .iter()
+            .zip(&average_true_range)
+            .map(|(m, a)| m + multiplier * a)
+            .collect(),
+        lower: middle
+            .iter()
+            .zip(&average_true_range)
+            .map(|(m, a)| m - multiplier * a)
+            .collect(),
+        middle,
+    })
+}
+
+/// Highest value over the trailing period, for each timestamp.
+pub(crate) fn rolling_max(values: &[f32], period: usize) -> Vec<f32> {
+    (0..values.len())
+        .map(|idx| {
+            if idx + 1 < period {
+                return f32::NAN;
+            }
+            values[idx + 1 - period..=idx]
+                .iter()
+                .copied()
+                .fold(f32::NEG_INFINITY, f32::max)
+        })
+        .collect()
+}
+
+/// Lowest value over the trailing period, for each timestamp.
+pub(crate) fn rolling_min(values: &[f32], period: usize) -> Vec<f32> {
+    (0..values.len())
+        .map(|idx| {
+            if idx + 1 < period {
+                return f32::NAN;
+            }
+            values[idx + 1 - period..=idx]
+                .iter()
+                .copied()
+                .fold(f32::INFINITY, f32::min)
+        })
+        .collect()
+}
+
+/// Donchian channels: highest high and lowest low over the period, with the midpoint as the middle line.
+pub fn donchian_channels<T>(
+    record: &T,
+    period: usize,
+) -> Result<structs::BandSeries, CalculationError>
+where
+    T: Prices,
+{
+    validate_period(period)?;
+    let upper = rolling_max(record.get_high_prices(), period);
+    let lower = rolling_min(record.get_low_prices(), period);
+
+    Ok(structs::BandSeries {
+        middle: upper
+            .iter()
+            .zip(&lower)
+            .map(|(u, l)| (u + l) / 2.0)
+            .collect(),
+        upper,
+        lower,
+    })
+}
+
+/// Chandelier exits: highest high less the multiplier times the ATR for longs, lowest low plus the same for shorts.
+pub fn chandelier_exits<T>(
+    record: &T,
+    period: usize,
+    multiplier: f32,
+) -> Result<structs::ChandelierExits, CalculationError>
+where
+    T: Prices,
+{
+    let average_true_range = atr(record, period)?;
+    let highest = rolling_max(record.get_high_prices(), period);
+    let lowest = rolling_min(record.get_low_prices(), period);
+
+    Ok(structs::ChandelierExits {
+        long_exit: highest
+            .iter()
+            .zip(&average_true_range)
+            .map(|(h, a)| h - multiplier * a)
+            .collect(),
+        short_exit: lowest
+            .iter()
+            .zip(&average_true_range)
+            .map(|(l, a)| l + multiplier * a)
+            .collect(),
+    })
+}
+
+/// Trailing price delta scaled by the previous bar's ATR: (first price - previous second price) / previous ATR.
+/// E.g. High | Close gives the high vs previous close move in units of ATR, instead of raw percent.
+pub fn atr_scaled_trailing_delta<T>(
+    record: &T,
+    first_price_type: BasePriceType,
+    second_price_type: BasePriceType,
+    period: usize,
+) -> Result<Vec<f32>, CalculationError>
+where
+    T: Prices,
+{
+    let first = get_price_series(record, first_price_type);
+    let second = get_price_series(record, second_price_type);
+    let average_true_range = atr(record, period)?;
+
+    let mut result = Vec::with_capacity(first.len());
+    if first.is_empty() {
+        return Ok(result);
+    }
+    result.push(f32::NAN);
+    (1..first.len())
+        .for_each(|idx| result.push((first[idx] - second[idx - 1]) / average_true_range[idx - 1]));
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datasets;
+
+    #[test]
+    fn test_atr() {
+        let record = datasets::structs::YahooFinancePriceRecord::from_hlc(&[
+            (11.0, 9.0, 10.0),
+            (12.0, 10.5, 11.0),
+            (11.5, 8.0, 9.0),
+            (10.0, 9.0, 9.5),
+        ]);
+        // True ranges: 2, max(1.5, 2, 0.5) = 2, max(3.5, 0.5, 3) = 3.5, max(1, 1, 0) = 1
+        assert!(true_range(&record).unwrap() == vec![2.0, 2.0, 3.5, 1.0]);
+        let result = atr(&record, 2).unwrap();
+        assert!(result[0].is_nan());
+        assert!((result[1] - 2.0).abs() < 1e-6);
+        assert!((result[2] - 2.75).abs() < 1e-6);
+        assert!((result[3] - 1.875).abs() < 1e-6);
+        assert!((normalized_atr(&record, 2).unwrap()[3] - 1.875 / 9.5 * 100.0).abs() < 1e-4);
+
+        let delta =
+            atr_scaled_trailing_delta(&record, BasePriceType::High, BasePriceType::Close, 2)
+                .unwrap();
+        assert!(delta[1].is_nan()); // ATR is unavailable for the first bar
+        assert!((delta[2] - 0.5 / 2.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_bollinger_bands() {
+        let record = datasets::structs::YahooFinancePriceRecord::from_hlc(&[
+            (1.0, 1.0, 1.0),
+            (3.0, 3.0, 3.0),
+            (5.0, 5.0, 5.0),
+        ]);
+        let bands = bollinger_bands(&record, 3, 2.0).unwrap();
+        let deviation = (8.0_f32 / 3.0).sqrt();
+        assert!(bands.get_middle()[1].is_nan());
+        assert!((bands.get_upper()[2] - (3.0 + 2.0 * deviation)).abs() < 1e-5);
+        assert!((bands.get_lower()[2] - (3.0 - 2.0 * deviation)).abs() < 1e-5);
+        let expected_b = (5.0 - (3.0 - 2.0 * deviation)) / (4.0 * deviation);
+        assert!((percent_b(&record, &bands)[2] - expected_b).abs() < 1e-5);
+        assert!((bandwidth(&bands)[2] - 4.0 * deviation / 3.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_channels() {
+        let record = datasets::structs::YahooFinancePriceRecord::from_hlc(&[
+            (11.0, 9.0, 10.0),
+            (12.0, 10.5, 11.0),
+            (11.5, 8.0, 9.0),
+        ]);
+        let donchian = donchian_channels(&record, 2).unwrap();
+        assert!(donchian.get_upper()[2] == 12.0 && donchian.get_lower()[2] == 8.0);
+        assert!(donchian.get_middle()[2] == 10.0);
+
+        let chandelier = chandelier_exits(&record, 2, 3.0).unwrap();
+        assert!((chandelier.get_long_exit()[2] - (12.0 - 3.0 * 2.75)).abs() < 1e-5);
+        assert!((chandelier.get_short_exit()[2] - (8.0 + 3.0 * 2.75)).abs() < 1e-5);
+
+        let keltner = keltner_channels(&record, 2, 2, 1.0).unwrap();
+        // EMA(2) seeded at 10.5, then 2/3 * 9 + 1/3 * 10.5 = 9.5
+        assert!((keltner.get_middle()[2] - 9.5).abs() < 1e-5);
+        assert!((keltner.get_upper()[2] - 12.25).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,102 @@
+//! Objective: Provide technical indicators computed over the pricing data of a record.
+//! All indicator series are aligned with the timestamps of the record, with NaN values during the warm-up periods.
+
+use crate::datasets::traits::Prices;
+use crate::errors::CalculationError;
+use crate::inputs::enums::BasePriceType;
+
+pub mod bands;
+pub mod momentum;
+pub mod moving_averages;
+pub mod trend;
+pub mod volume;
+
+pub mod structs {
+
+    /// Single bar of pricing data, used for the bar-by-bar update of the incremental indicators.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct PriceBar {
+        pub high: f32,
+        pub low: f32,
+        pub close: f32,
+    }
+}
+
+pub mod traits {
+    use super::structs::PriceBar;
+
+    /// Indicators which can be updated bar by bar, for use within live pipelines.
+    pub trait IncrementalIndicator {
+        type Output;
+
+        /// Consumes the next bar and returns the latest indicator value.
+        fn update(&mut self, bar: &PriceBar) -> Self::Output;
+    }
+}
+
+/// Replays the bars of the record through the incremental indicator, returning the output aligned with the timestamps.
+pub fn replay<T, I>(record: &T, indicator: &mut I) -> Result<Vec<I::Output>, CalculationError>
+where
+    T: Prices,
+    I: traits::IncrementalIndicator,
+{
+    let high = record.get_high_prices();
+    let low = record.get_low_prices();
+    let close = record.get_close_prices();
+    if high.len() != close.len() || low.len() != close.len() {
+        return Err(CalculationError::InconsistentLengthError(format!(
+            "Length of the high array: {} \n Length of the low array: {} \n Length of the close array: {}",
+            high.len(),
+            low.len(),
+            close.len()
+        )));
+    }
+
+    Ok((0..close.len())
+        .map(|idx| {
+            indicator.update(&structs::PriceBar {
+                high: high[idx],
+                low: low[idx],
+                close: close[idx],
+            })
+        })
+        .collect())
+}
+
+/// Returns the price series of the record corresponding to the provided price type.
+pub fn get_price_series<T>(record: &T, price_type: BasePriceType) -> &[f32]
+where
+    T: Prices,
+{
+    match price_type {
+        BasePriceType::Open => record.get_open_prices(),
+        BasePriceType::Close => record.get_close_prices(),
+        BasePriceType::High => record.get_high_prices(),
+        BasePriceType::Low => record.get_low_prices(),
+    }
+}
+
+/// Validates that the indicator period is non-zero.
+pub(crate) fn validate_period(period: usize) -> Result<(), CalculationError> {
+    if period == 0 {
+        return Err(CalculationError::InvalidParameterError(
+            "Indicator period must be greater than 0.".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Applies the indicator function over the values after skipping the leading NaN values, re-aligning the output.
+/// Allows indicators to be chained over the output of other indicators.
+pub(crate) fn skip_leading_nan<F>(values: &[f32], indicator: F) -> Vec<f32>
+where
+    F: Fn(&[f32]) -> Vec<f32>,
+{
+    let first_valid = values
+        .iter()
+        .position(|x| !x.is_nan())
+        .unwrap_or(values.len());
+    let mut result = vec![f32::NAN; first_valid];
+    result.extend(indicator(&values[first_valid..]));
+    result
+}
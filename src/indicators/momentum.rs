@@ -0,0 +1,537 @@
+//! Momentum oscillators. Each oscillator is implemented as an incremental indicator, with the batch
+//! functions replaying the bars of a record through a freshly initialized state.
+
+use std::collections::VecDeque;
+
+use super::moving_averages::structs::{EmaState, SmaState};
+use super::structs::PriceBar;
+use super::traits::IncrementalIndicator;
+use super::{replay, validate_period};
+use crate::datasets::traits::Prices;
+use crate::errors::CalculationError;
+
+pub mod structs {
+    use super::*;
+
+    /// Wilder's relative strength index.
+    #[derive(Clone, Debug)]
+    pub struct RsiState {
+        pub(super) period: usize,
+        pub(super) previous_close: Option<f32>,
+        pub(super) num_of_changes: usize,
+        pub(super) average_gain: f32,
+        pub(super) average_loss: f32,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct MacdValue {
+        pub(super) macd: f32,
+        pub(super) signal: f32,
+        pub(super) histogram: f32,
+    }
+
+    impl MacdValue {
+        pub fn get_macd(&self) -> f32 {
+            self.macd
+        }
+
+        pub fn get_signal(&self) -> f32 {
+            self.signal
+        }
+
+        pub fn get_histogram(&self) -> f32 {
+            self.histogram
+        }
+    }
+
+    /// Moving average convergence divergence, with the signal line and histogram.
+    #[derive(Clone, Debug)]
+    pub struct MacdState {
+        pub(super) fast_ema: EmaState,
+        pub(super) slow_ema: EmaState,
+        pub(super) signal_ema: EmaState,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct StochasticValue {
+        pub(super) fast_k: f32,
+        pub(super) fast_d: f32,
+        pub(super) slow_d: f32,
+    }
+
+    impl StochasticValue {
+        pub fn get_fast_k(&self) -> f32 {
+            self.fast_k
+        }
+
+        pub fn get_fast_d(&self) -> f32 {
+            self.fast_d
+        }
+
+        /// Slow %K is identical to the fast %D.
+        pub fn get_slow_k(&self) -> f32 {
+            self.fast_d
+        }
+
+        pub fn get_slow_d(&self) -> f32 {
+            self.slow_d
+        }
+    }
+
+    /// Fast and slow stochastic oscillators.
+    #[derive(Clone, Debug)]
+    pub struct StochasticState {
+        pub(super) highs: VecDeque<f32>,
+        pub(super) lows: VecDeque<f32>,
+        pub(super) k_period: usize,
+        pub(super) fast_d: SmaState,
+        pub(super) slow_d: SmaState,
+    }
+
+    /// Williams %R, ranging between -100 and 0.
+    #[derive(Clone, Debug)]
+    pub struct WilliamsRState {
+        pub(super) highs: VecDeque<f32>,
+        pub(super) lows: VecDeque<f32>,
+        pub(super) period: usize,
+    }
+
+    /// Commodity channel index, using the typical price and the 0.015 scaling constant.
+    #[derive(Clone, Debug)]
+    pub struct CciState {
+        pub(super) typical_prices: VecDeque<f32>,
+        pub(super) period: usize,
+    }
+
+    /// Rate of change in percent, or the absolute momentum, of the close price against the close price `period` bars ago.
+    #[derive(Clone, Debug)]
+    pub struct RateOfChangeState {
+        pub(super) closes: VecDeque<f32>,
+        pub(super) period: usize,
+        pub(super) as_percentage: bool,
+    }
+}
+
+/// Pushes the value into the window, dropping the oldest value when the window exceeds the period.
+fn push_window(window: &mut VecDeque<f32>, value: f32, period: usize) {
+    window.push_back(value);
+    if window.len() > period {
+        window.pop_front();
+    }
+}
+
+fn window_max(window: &VecDeque<f32>) -> f32 {
+    window.iter().copied().fold(f32::NEG_INFINITY, f32::max)
+}
+
+fn window_min(window: &VecDeque<f32>) -> f32 {
+    window.iter().copied().fold(f32::INFINITY, f32::min)
+}
+
+impl structs::RsiState {
+    pub fn new(period: usize) -> Result<Self, CalculationError> {
+        validate_period(period)?;
+        Ok(structs::RsiState {
+            period,
+            previous_close: None,
+            num_of_changes: 0,
+            average_gain: 0.0,
+            average_loss: 0.0,
+        })
+    }
+}
+
+impl IncrementalIndicator for structs::RsiState {
+    type Output = f32;
+
+    fn update(&mut self, bar: &PriceBar) -> f32 {
+        let previous_close = match self.previous_close.replace(bar.close) {
+            Some(i) => i,
+            None => return f32::NAN, // No change available for the first bar
+        };
+        let change = bar.close - previous_close;
+        let period = self.period as f32;
+        self.num_of_changes += 1;
+
+        if self.num_of_changes <= self.period {
+            // Simple average over the first period
+            self.average_gain += change.max(0.0) / period;
+            self.average_loss += (-change).max(0.0) / period;
+            if self.num_of_changes < self.period {
+                return f32::NAN;
+            }
+        } else {
+            // Wilder's smoothing thereafter
+            self.average_gain = (self.average_gain * (period - 1.0) + change.max(0.0)) / period;
+            self.average_loss = (self.average_loss * (period - 1.0) + (-change).max(0.0)) / period;
+        }
+
+        if self.average_loss == 0.0 {
+            return 100.0;
+        }
+        100.0 - 100.0 / (1.0 + self.average_gain / self.average_loss)
+    }
+}
+
+impl structs::MacdState {
+    pub fn new(
+        fast_period: usize,
+        slow_period: usize,
+        signal_period: usize,
+    ) -> Result<Self, CalculationError> {
+        validate_period(fast_period)?;
+        validate_period(signal_period)?;
+        if slow_period <= fast_period {
+            return Err(CalculationError::InvalidParameterError(format!(
+                "Slow period ({}) must be greater than the fast period ({}).",
+                slow_period, fast_period
+            )));
+        }
+        Ok(structs::MacdState {
+            fast_ema: EmaState::new(fast_period),
+            slow_ema: EmaState::new(slow_period),
+            signal_ema: EmaState::new(signal_period),
+        })
+    }
+}
+
+impl IncrementalIndicator for structs::MacdState {
+    type Output = structs::MacdValue;
+
+    fn update(&mut self, bar: &PriceBar) -> structs::MacdValue {
+        let macd = self.fast_ema.update_value(bar.close) - self.slow_ema.update_value(bar.close);
+        let signal = if macd.is_nan() {
+            f32::NAN
+        } else {
+            self.signal_ema.update_value(macd)
+        };
+        structs::MacdValue {
+            macd,
+            signal,
+            histogram: macd - signal,
+        }
+    }
+}
+
+impl structs::StochasticState {
+    pub fn new(
+        k_period: usize,
+        d_period: usize,
+        slow_d_period: usize,
+    ) -> Result<Self, CalculationError> {
+        validate_period(k_period)?;
+        validate_period(d_period)?;
+        validate_period(slow_d_period)?;
+        Ok(structs::StochasticState {
+            highs: VecDeque::with_capacity(k_period + 1),
+            lows: VecDeque::with_capacity(k_period + 1),
+            k_period,
+            fast_d: SmaState::new(d_period),
+            slow_d: SmaState::new(slow_d_period),
+        })
+    }
+}
+
+impl IncrementalIndicator for structs::StochasticState {
+    type Output = structs::StochasticValue;
+
+    fn update(&mut self, bar: &PriceBar) -> structs::StochasticValue {
+        push_window(&mut self.highs, bar.high, self.k_period);
+        push_window(&mut self.lows, bar.low, self.k_period);
+        if self.highs.len() < self.k_period {
+            return structs::StochasticValue {
+                fast_k: f32::NAN,
+                fast_d: f32::NAN,
+                slow_d: f32::NAN,
+            };
+        }
+
+        let highest = window_max(&self.highs);
+        let lowest = window_min(&self.lows);
+        let fast_k = if highest == lowest {
+            50.0 // Flat range, the close sits in the middle by convention
+        } else {
+            (bar.close - lowest) / (highest - lowest) * 100.0
+        };
+        let fast_d = self.fast_d.update_value(fast_k);
+        let slow_d = if fast_d.is_nan() {
+            f32::NAN
+        } else {
+            self.slow_d.update_value(fast_d)
+        };
+
+        structs::StochasticValue {
+            fast_k,
+            fast_d,
+            slow_d,
+        }
+    }
+}
+
+impl structs::WilliamsRState {
+    pub fn new(period: usize) -> Result<Self, CalculationError> {
+        validate_period(period)?;
+        Ok(structs::WilliamsRState {
+            highs: VecDeque::with_capacity(period + 1),
+            lows: VecDeque::with_capacity(period + 1),
+            period,
+        })
+    }
+}
+
+impl IncrementalIndicator for structs::WilliamsRState {
+    type Output = f32;
+
+    fn update(&mut self, bar: &PriceBar) -> f32 {
+        push_window(&mut self.highs, bar.high, self.period);
+        push_window(&mut self.lows, bar.low, self.period);
+        if self.highs.len() < self.period {
+            return f32::NAN;
+        }
+
+        let highest = window_max(&self.highs);
+        let lowest = window_min(&self.lows);
+        if highest == lowest {
+            return -50.0;
+        }
+        (highest - bar.close) / (highest - lowest) * -100.0
+    }
+}
+
+impl structs::CciState {
+    pub fn new(period: usize) -> Result<Self, CalculationError> {
+        validate_period(period)?;
+        Ok(structs::CciState {
+            typical_prices: VecDeque::with_capacity(period + 1),
+            period,
+        })
+    }
+}
+
+impl IncrementalIndicator for structs::CciState {
+    type Output = f32;
+
+    fn update(&mut self, bar: &PriceBar) -> f32 {
+        let typical_price = (bar.high + bar.low + bar.close) / 3.0;
+        push_window(&mut self.typical_prices, typical_price, self.period);
+        if self.typical_prices.len() < self.period {
+            return f32::NAN;
+        }
+
+        let period = self.period as f32;
+        let mean = self.typical_prices.iter().sum::<f32>() / period;
+        let mean_deviation = self
+            .typical_prices
+            .iter()
+            .map(|x| (x - mean).abs())
+            .sum::<f32>()
+            / period;
+        if mean_deviation == 0.0 {
+            return 0.0;
+        }
+        (typical_price - mean) / (0.015 * mean_deviation)
+    }
+}
+
+impl structs::RateOfChangeState {
+    /// Rate of change in percent: (close / close `period` bars ago - 1) * 100.
+    pub fn new_rate_of_change(period: usize) -> Result<Self, CalculationError> {
+        validate_period(period)?;
+        Ok(structs::RateOfChangeState {
+            closes: VecDeque::with_capacity(period + 2),
+            period,
+            as_percentage: true,
+        })
+    }
+
+    /// Absolute momentum: close - close `period` bars ago.
+    pub fn new_momentum(period: usize) -> Result<Self, CalculationError> {
+        let mut state = Self::new_rate_of_change(period)?;
+        state.as_percentage = false;
+        Ok(state)
+    }
+}
+
+impl IncrementalIndicator for structs::RateOfChangeState {
+    type Output = f32;
+
+    fn update(&mut self, bar: &PriceBar) -> f32 {
+        push_window(&mut self.closes, bar.close, self.period + 1);
+        if self.closes.len() <= self.period {
+            return f32::NAN;
+        }
+
+        let reference = self.closes[0];
+        if self.as_percentage {
+            (bar.close / reference - 1.0) * 100.0
+        } else {
+            bar.close - reference
+        }
+    }
+}
+
+/// Wilder's RSI over the close prices of the record.
+pub fn rsi<T>(record: &T, period: usize) -> Result<Vec<f32>, CalculationError>
+where
+    T: Prices,
+{
+    replay(record, &mut structs::RsiState::new(period)?)
+}
+
+/// MACD line, signal line and histogram over the close prices of the record.
+pub fn macd<T>(
+    record: &T,
+    fast_period: usize,
+    slow_period: usize,
+    signal_period: usize,
+) -> Result<Vec<structs::MacdValue>, CalculationError>
+where
+    T: Prices,
+{
+    replay(
+        record,
+        &mut structs::MacdState::new(fast_period, slow_period, signal_period)?,
+    )
+}
+
+/// Fast and slow stochastic oscillators of the record.
+pub fn stochastic<T>(
+    record: &T,
+    k_period: usize,
+    d_period: usize,
+    slow_d_period: usize,
+) -> Result<Vec<structs::StochasticValue>, CalculationError>
+where
+    T: Prices,
+{
+    replay(
+        record,
+        &mut structs::StochasticState::new(k_period, d_period, slow_d_period)?,
+    )
+}
+
+/// Williams %R of the record.
+pub fn williams_r<T>(record: &T, period: usize) -> Result<Vec<f32>, CalculationError>
+where
+    T: Prices,
+{
+    replay(record, &mut structs::WilliamsRState::new(period)?)
+}
+
+/// Commodity channel index of the record.
+pub fn cci<T>(record: &T, period: usize) -> Result<Vec<f32>, CalculationError>
+where
+    T: Prices,
+{
+    replay(record, &mut structs::CciState::new(period)?)
+}
+
+/// Rate of change in percent over the close prices of the record.
+pub fn rate_of_change<T>(record: &T, period: usize) -> Result<Vec<f32>, CalculationError>
+where
+    T: Prices,
+{
+    replay(
+        record,
+        &mut structs::RateOfChangeState::new_rate_of_change(period)?,
+    )
+}
+
+/// Absolute momentum over the close prices of the record.
+pub fn momentum<T>(record: &T, period: usize) -> Result<Vec<f32>, CalculationError>
+where
+    T: Prices,
+{
+    replay(
+        record,
+        &mut structs::RateOfChangeState::new_momentum(period)?,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datasets;
+
+    #[test]
+    fn test_rsi() {
+        let record = datasets::structs::YahooFinancePriceRecord::from_closes(&[
+            10.0, 11.0, 10.5, 11.5, 11.0,
+        ]);
+        let result = rsi(&record, 2).unwrap();
+        assert!(result[0].is_nan() && result[1].is_nan());
+        // Changes: +1, -0.5 -> average gain 0.5, average loss 0.25
+        assert!((result[2] - (100.0 - 100.0 / 3.0)).abs() < 1e-4);
+        // Change +1 -> gain (0.5 + 1) / 2 = 0.75, loss 0.25 / 2 = 0.125
+        assert!((result[3] - (100.0 - 100.0 / 7.0)).abs() < 1e-4);
+        assert!(rsi(&record, 0).is_err());
+    }
+
+    #[test]
+    fn test_macd() {
+        let record = datasets::structs::YahooFinancePriceRecord::from_closes(&[
+            1.0, 2.0, 3.0, 4.0, 5.0, 6.0,
+        ]);
+        let result = macd(&record, 2, 3, 2).unwrap();
+        assert!(result[1].get_macd().is_nan());
+        // Linear series: EMA(2) lags by 0.5 and EMA(3) lags by 1, hence the MACD line is constant at 0.5
+        assert!((result[2].get_macd() - 0.5).abs() < 1e-5);
+        assert!(result[2].get_signal().is_nan());
+        assert!((result[3].get_signal() - 0.5).abs() < 1e-5);
+        assert!(result[5].get_histogram().abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_stochastic_and_williams_r() {
+        let record = datasets::structs::YahooFinancePriceRecord::from_hlc(&[
+            (10.0, 8.0, 9.0),
+            (12.0, 9.0, 11.0),
+            (11.0, 7.0, 8.0),
+        ]);
+        let result = stochastic(&record, 2, 2, 1).unwrap();
+        // Bar 1: range 8 - 12, close 11 -> 75. Bar 2: range 7 - 12, close 8 -> 20
+        assert!((result[1].get_fast_k() - 75.0).abs() < 1e-4);
+        assert!((result[2].get_fast_k() - 20.0).abs() < 1e-4);
+        assert!((result[2].get_fast_d() - 47.5).abs() < 1e-4);
+        assert!((result[2].get_slow_d() - 47.5).abs() < 1e-4);
+
+        let result = williams_r(&record, 2).unwrap();
+        assert!(result[0].is_nan());
+        assert!((result[2] + 80.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_cci_and_rate_of_change() {
+        let record = datasets::structs::YahooFinancePriceRecord::from_closes(&[10.0, 12.0, 14.0]);
+        let result = cci(&record, 3).unwrap();
+        // Mean 12, mean deviation 4 / 3 -> (14 - 12) / (0.015 * 4 / 3) = 100
+        assert!((result[2] - 100.0).abs() < 1e-3);
+
+        let result = rate_of_change(&record, 2).unwrap();
+        assert!(result[1].is_nan());
+        assert!((result[2] - 40.0).abs() < 1e-4);
+        assert!((momentum(&record, 1).unwrap()[2] - 2.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_rsi_state() {
+        let mut state = structs::RsiState::new(3).unwrap();
+        let values = [10.0, 10.5, 10.2, 10.8, 11.3, 11.0]
+            .iter()
+            .map(|&close| {
+                state.update(&PriceBar {
+                    high: close,
+                    low: close,
+                    close,
+                })
+            })
+            .collect::<Vec<f32>>();
+        assert!(values[..3].iter().all(|x| x.is_nan()));
+        // Changes: +0.5, -0.3, +0.6 -> average gain 1.1 / 3, average loss 0.3 / 3
+        assert!((values[3] - (100.0 - 300.0 / 14.0)).abs() < 1e-3);
+        // Change +0.5 -> gain 3.7 / 9, loss 0.6 / 9
+        assert!((values[4] - (100.0 - 600.0 / 43.0)).abs() < 1e-3);
+        // Change -0.3 -> gain 7.4 / 27, loss 3.9 / 27
+        assert!((values[5] - (100.0 - 3900.0 / 113.0)).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,378 @@
+//! Moving average indicators.
+
+use super::{get_price_series, skip_leading_nan, validate_period};
+use crate::datasets::traits::{Prices, Volume};
+use crate::errors::CalculationError;
+use crate::inputs::enums::BasePriceType;
+
+pub mod enums {
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum MovingAverageType {
+        Simple,
+        Exponential,
+        Weighted,
+        DoubleExponential,
+        TripleExponential,
+        Hull,
+        /// Kaufman adaptive moving average, with the fast and slow smoothing periods.
+        KaufmanAdaptive {
+            fast_period: usize,
+            slow_period: usize,
+        },
+    }
+}
+
+pub mod structs {
+    use std::collections::VecDeque;
+
+    /// Incremental simple moving average over a fixed period.
+    #[derive(Clone, Debug)]
+    pub struct SmaState {
+        pub(super) period: usize,
+        pub(super) window: VecDeque<f32>,
+        pub(super) window_sum: f64,
+    }
+
+    impl SmaState {
+        pub fn new(period: usize) -> Self {
+            SmaState {
+                period,
+                window: VecDeque::with_capacity(period + 1),
+                window_sum: 0.0,
+            }
+        }
+
+        /// Consumes the next value, returning NaN until the period is filled.
+        pub fn update_value(&mut self, value: f32) -> f32 {
+            self.window.push_back(value);
+            self.window_sum += value as f64;
+            if self.window.len() > self.period {
+                self.window_sum -= self.window.pop_front().unwrap() as f64; // Window is non-empty
+            }
+            if self.window.len() < self.period {
+                f32::NAN
+            } else {
+                (self.window_sum / self.period as f64) as f32
+            }
+        }
+    }
+
+    /// Incremental exponential moving average, seeded with the SMA of the first period.
+    #[derive(Clone, Debug)]
+    pub struct EmaState {
+        pub(super) alpha: f32,
+        pub(super) seed: SmaState,
+        pub(super) value: Option<f32>,
+    }
+
+    impl EmaState {
+        pub fn new(period: usize) -> Self {
+            EmaState {
+                alpha: 2.0 / (period as f32 + 1.0),
+                seed: SmaState::new(period),
+                value: None,
+            }
+        }
+
+        /// Consumes the next value, returning NaN until the period is filled.
+        pub fn update_value(&mut self, value: f32) -> f32 {
+            let updated = match self.value {
+                Some(previous) => self.alpha * value + (1.0 - self.alpha) * previous,
+                None => self.seed.update_value(value),
+            };
+            if !updated.is_nan() {
+                self.value = Some(updated);
+            }
+            updated
+        }
+    }
+}
+
+/// Computes the moving average over the selected price series of the record.
+pub fn moving_average<T>(
+    record: &T,
+    price_type: BasePriceType,
+    average_type: enums::MovingAverageType,
+    period: usize,
+) -> Result<Vec<f32>, CalculationError>
+where
+    T: Prices,
+{
+    let values = get_price_series(record, price_type);
+    match average_type {
+        enums::MovingAverageType::Simple => sma(values, period),
+        enums::MovingAverageType::Exponential => ema(values, period),
+        enums::MovingAverageType::Weighted => wma(values, period),
+        enums::MovingAverageType::DoubleExponential => dema(values, period),
+        enums::MovingAverageType::TripleExponential => tema(values, period),
+        enums::MovingAverageType::Hull => hull_ma(values, period),
+        enums::MovingAverageType::KaufmanAdaptive {
+            fast_period,
+            slow_period,
+        } => kama(values, period, fast_period, slow_period),
+    }
+}
+
+/// Simple moving average.
+pub fn sma(values: &[f32], period: usize) -> Result<Vec<f32>, CalculationError> {
+    validate_period(period)?;
+    Ok(sma_unchecked(values, period))
+}
+
+/// Exponential moving average with a smoothing factor of 2 / (period + 1), seeded with the SMA of the first period.
+pub fn ema(values: &[f32], period: usize) -> Result<Vec<f32>, CalculationError> {
+    validate_period(period)?;
+    Ok(ema_unchecked(values, period))
+}
+
+/// Linearly weighted moving average, with the latest value carrying the largest weight.
+pub fn wma(values: &[f32], period: usize) -> Result<Vec<f32>, CalculationError> {
+    validate_period(period)?;
+    Ok(wma_unchecked(values, period))
+}
+
+/// Double exponential moving average: 2 * EMA - EMA(EMA).
+pub fn dema(values: &[f32], period: usize) -> Result<Vec<f32>, CalculationError> {
+    validate_period(period)?;
+    let first = ema_unchecked(values, period);
+    let second = skip_leading_nan(&first, |x| ema_unchecked(x, period));
+    Ok(first.iter().zip(second).map(|(a, b)| 2.0 * a - b).collect())
+}
+
+/// Triple exponential moving average: 3 * EMA - 3 * EMA(EMA) + EMA(EMA(EMA)).
+pub fn tema(values: &[f32], period: usize) -> Result<Vec<f32>, CalculationError> {
+    validate_period(period)?;
+    let first = ema_unchecked(values, period);
+    let second = skip_leading_nan(&first, |x| ema_unchecked(x, period));
+    let third = skip_leading_nan(&second, |x| ema_unchecked(x, period));
+    Ok((0..values.len())
+        .map(|idx| 3.0 * first[idx] - 3.0 * second[idx] + third[idx])
+        .collect())
+}
+
+/// Hull moving average: WMA(2 * WMA(period / 2) - WMA(period), sqrt(period)).
+pub fn hull_ma(values: &[f32], period: usize) -> Result<Vec<f32>, CalculationError> {
+    if period < 2 {
+        return Err(CalculationError::InvalidParameterError(format!(
+            "Hull moving average period must be at least 2, got {}.",
+            period
+        )));
+    }
+    let half = wma_unchecked(values, period / 2);
+    let full = wma_unchecked(values, period);
+    let raw = half
+        .iter()
+        .zip(full)
+        .map(|(a, b)| 2.0 * a - b)
+        .collect::<Vec<f32>>();
+    let smoothing_period = (period as f32).sqrt().round() as usize;
+    Ok(skip_leading_nan(&raw, |x| {
+        wma_unchecked(x, smoothing_period)
+    }))
+}
+
+/// Kaufman adaptive moving average, using the efficiency ratio over the period to scale between the fast and slow smoothing constants.
+pub fn kama(
+    values: &[f32],
+    period: usize,
+    fast_period: usize,
+    slow_period: usize,
+) -> Result<Vec<f32>, CalculationError> {
+    validate_period(period)?;
+    validate_period(fast_period)?;
+    if slow_period <= fast_period {
+        return Err(CalculationError::InvalidParameterError(format!(
+            "Slow period ({}) must be greater than the fast period ({}).",
+            slow_period, fast_period
+        )));
+    }
+
+    let fast_constant = 2.0 / (fast_period as f32 + 1.0);
+    let slow_constant = 2.0 / (slow_period as f32 + 1.0);
+    let mut result = vec![f32::NAN; values.len()];
+    if values.len() <= period {
+        return Ok(result);
+    }
+
+    result[period - 1] = values[period - 1]; // Seeding with the price at the end of the first period
+    for idx in period..values.len() {
+        let change = (values[idx] - values[idx - period]).abs();
+        let volatility: f32 = (idx + 1 - period..=idx)
+            .map(|x| (values[x] - values[x - 1]).abs())
+            .sum();
+        let efficiency_ratio = if volatility == 0.0 {
+            0.0
+        } else {
+            change / volatility
+        };
+        let smoothing_constant =
+            (efficiency_ratio * (fast_constant - slow_constant) + slow_constant).powi(2);
+        result[idx] = result[idx - 1] + smoothing_constant * (values[idx] - result[idx - 1]);
+    }
+    Ok(result)
+}
+
+/// Volume weighted moving average over the selected price series of the record.
+pub fn vwma<T>(
+    record: &T,
+    price_type: BasePriceType,
+    period: usize,
+) -> Result<Vec<f32>, CalculationError>
+where
+    T: Prices + Volume,
+{
+    validate_period(period)?;
+    let values = get_price_series(record, price_type);
+    let volume = record.get_volume();
+    if values.len() != volume.len() {
+        return Err(CalculationError::InconsistentLengthError(format!(
+            "Length of the prices array: {} \n Length of the volume array: {}",
+            values.len(),
+            volume.len()
+        )));
+    }
+
+    Ok((0..values.len())
+        .map(|idx| {
+            if idx + 1 < period {
+                return f32::NAN;
+            }
+            let window = idx + 1 - period..=idx;
+            let weighted_sum: f64 = window
+                .clone()
+                .map(|x| values[x] as f64 * volume[x] as f64)
+                .sum();
+            let total_volume: f64 = window.map(|x| volume[x] as f64).sum();
+            (weighted_sum / total_volume) as f32
+        })
+        .collect())
+}
+
+pub(crate) fn sma_unchecked(values: &[f32], period: usize) -> Vec<f32> {
+    let mut result = Vec::with_capacity(values.len());
+    let mut window_sum = 0.0_f64;
+    for (idx, value) in values.iter().enumerate() {
+        window_sum += *value as f64;
+        if idx >= period {
+            window_sum -= values[idx - period] as f64;
+        }
+        if idx + 1 < period {
+            result.push(f32::NAN);
+        } else {
+            result.push((window_sum / period as f64) as f32);
+        }
+    }
+    result
+}
+
+pub(crate) fn ema_unchecked(values: &[f32], period: usize) -> Vec<f32> {
+    let alpha = 2.0 / (period as f32 + 1.0);
+    let mut result = vec![f32::NAN; values.len()];
+    if values.len() < period {
+        return result;
+    }
+
+    result[period - 1] = values[..period].iter().sum::<f32>() / period as f32;
+    for idx in period..values.len() {
+        result[idx] = alpha * values[idx] + (1.0 - alpha) * result[idx - 1];
+    }
+    result
+}
+
+pub(crate) fn wma_unchecked(values: &[f32], period: usize) -> Vec<f32> {
+    let denominator = (period * (period + 1)) as f32 / 2.0;
+    (0..values.len())
+        .map(|idx| {
+            if idx + 1 < period {
+                return f32::NAN;
+            }
+            let start = idx + 1 - period;
+            values[start..=idx]
+                .iter()
+                .enumerate()
+                .map(|(weight, value)| (weight + 1) as f32 * value)
+                .sum::<f32>()
+                / denominator
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALUES: [f32; 6] = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+
+    fn assert_series(result: &[f32], expected: &[f32]) {
+        assert!(result.len() == expected.len());
+        result.iter().zip(expected).for_each(|(a, b)| {
+            assert!(
+                (a.is_nan() && b.is_nan()) || (a - b).abs() < 1e-4,
+                "{:?} != {:?}",
+                result,
+                expected
+            )
+        });
+    }
+
+    #[test]
+    fn test_sma_and_wma() {
+        let nan = f32::NAN;
+        assert_series(&sma(&VALUES, 3).unwrap(), &[nan, nan, 2.0, 3.0, 4.0, 5.0]);
+        // (1 * 1 + 2 * 2 + 3 * 3) / 6 = 14 / 6
+        assert_series(
+            &wma(&VALUES, 3).unwrap(),
+            &[nan, nan, 14.0 / 6.0, 20.0 / 6.0, 26.0 / 6.0, 32.0 / 6.0],
+        );
+        assert!(sma(&VALUES, 0).is_err());
+    }
+
+    #[test]
+    fn test_ema_family() {
+        let nan = f32::NAN;
+        let values = [2.0, 4.0, 6.0, 8.0, 12.0];
+        // Alpha = 0.5, seeded with the SMA of the first 3 values
+        assert_series(&ema(&values, 3).unwrap(), &[nan, nan, 4.0, 6.0, 9.0]);
+        // EMA(EMA) is seeded at index 4 with the SMA of (4, 6, 9)
+        assert_series(
+            &dema(&values, 3).unwrap(),
+            &[nan, nan, nan, nan, 2.0 * 9.0 - 19.0 / 3.0],
+        );
+        // For a linear series, DEMA and TEMA remove the lag of the EMA entirely
+        let linear = (0..12).map(|x| x as f32).collect::<Vec<f32>>();
+        let result = tema(&linear, 3).unwrap();
+        assert!(
+            result[5].is_nan()
+                && (result[6] - 6.0).abs() < 1e-4
+                && (result[11] - 11.0).abs() < 1e-4
+        );
+    }
+
+    #[test]
+    fn test_hull_ma() {
+        let linear = (0..10).map(|x| x as f32).collect::<Vec<f32>>();
+        let result = hull_ma(&linear, 4).unwrap();
+        // Warm-up of 4 - 1 bars for the full WMA and 2 - 1 bars for the smoothing WMA
+        assert!(result[3].is_nan());
+        // WMA(2) of x: x - 1/3, WMA(4) of x: x - 1, raw = x + 1/3, smoothed by WMA(2) = x
+        assert_series(&result[4..], &linear[4..]);
+    }
+
+    #[test]
+    fn test_kama() {
+        let values = [10.0, 11.0, 12.0, 11.0, 13.0];
+        let result = kama(&values, 2, 2, 30).unwrap();
+        let fast = 2.0_f32 / 3.0;
+        let slow = 2.0_f32 / 31.0;
+        // Index 2: ER = 2 / 2 = 1
+        let kama_2 = 11.0 + fast.powi(2) * (12.0 - 11.0);
+        // Index 3: ER = |11 - 11| / 2 = 0
+        let kama_3 = kama_2 + slow.powi(2) * (11.0 - kama_2);
+        assert!(result[0].is_nan());
+        assert!((result[1] - 11.0).abs() < 1e-6);
+        assert!((result[2] - kama_2).abs() < 1e-5);
+        assert!((result[3] - kama_3).abs() < 1e-5);
+        assert!(kama(&values, 2, 30, 2).is_err());
+    }
+}
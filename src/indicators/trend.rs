@@ -0,0 +1,630 @@
+//! Trend strength and trend following indicators over the high, low and close prices.
+//! Besides the aligned series, discrete signal events can be extracted from crossovers of two lines and trend flips.
+
+use super::bands::{atr, rolling_max, rolling_min};
+use super::validate_period;
+use crate::datasets::traits::Prices;
+use crate::errors::CalculationError;
+use crate::volatility::validate_ohlc_lengths;
+
+pub mod enums {
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    pub enum SignalDirection {
+        Bullish,
+        Bearish,
+    }
+}
+
+pub mod structs {
+    use super::enums::SignalDirection;
+
+    /// Directional movement index: +DI, -DI and the ADX.
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct DirectionalMovement {
+        pub(super) plus_di: Vec<f32>,
+        pub(super) minus_di: Vec<f32>,
+        pub(super) adx: Vec<f32>,
+    }
+
+    impl DirectionalMovement {
+        pub fn get_plus_di(&self) -> &[f32] {
+            &self.plus_di
+        }
+
+        pub fn get_minus_di(&self) -> &[f32] {
+            &self.minus_di
+        }
+
+        pub fn get_adx(&self) -> &[f32] {
+            &self.adx
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct AroonSeries {
+        pub(super) up: Vec<f32>,
+        pub(super) down: Vec<f32>,
+        pub(super) oscillator: Vec<f32>,
+    }
+
+    impl AroonSeries {
+        pub fn get_up(&self) -> &[f32] {
+            &self.up
+        }
+
+        pub fn get_down(&self) -> &[f32] {
+            &self.down
+        }
+
+        pub fn get_oscillator(&self) -> &[f32] {
+            &self.oscillator
+        }
+    }
+
+    /// Stop level of a trend following indicator, together with the prevailing trend direction.
+    /// The direction is None during the warm-up periods.
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct TrailingStopSeries {
+        pub(super) stop: Vec<f32>,
+        pub(super) direction: Vec<Option<SignalDirection>>,
+    }
+
+    impl TrailingStopSeries {
+        pub fn get_stop(&self) -> &[f32] {
+            &self.stop
+        }
+
+        pub fn get_direction(&self) -> &[Option<SignalDirection>] {
+            &self.direction
+        }
+    }
+
+    /// Ichimoku cloud components. The senkou spans are aligned with the timestamps they are projected onto,
+    /// while the chikou span at each timestamp is the close price of the displacement periods ahead.
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct IchimokuCloud {
+        pub(super) tenkan_sen: Vec<f32>,
+        pub(super) kijun_sen: Vec<f32>,
+        pub(super) senkou_span_a: Vec<f32>,
+        pub(super) senkou_span_b: Vec<f32>,
+        pub(super) chikou_span: Vec<f32>,
+    }
+
+    impl IchimokuCloud {
+        pub fn get_tenkan_sen(&self) -> &[f32] {
+            &self.tenkan_sen
+        }
+
+        pub fn get_kijun_sen(&self) -> &[f32] {
+            &self.kijun_sen
+        }
+
+        pub fn get_senkou_span_a(&self) -> &[f32] {
+            &self.senkou_span_a
+        }
+
+        pub fn get_senkou_span_b(&self) -> &[f32] {
+            &self.senkou_span_b
+        }
+
+        pub fn get_chikou_span(&self) -> &[f32] {
+            &self.chikou_span
+        }
+    }
+
+    /// Discrete signal raised at a given bar.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct SignalEvent {
+        pub(super) index: usize,
+        pub(super) timestamp: chrono::DateTime<chrono::Utc>,
+        pub(super) direction: SignalDirection,
+    }
+
+    impl SignalEvent {
+        pub fn get_index(&self) -> usize {
+            self.index
+        }
+
+        pub fn get_timestamp(&self) -> chrono::DateTime<chrono::Utc> {
+            self.timestamp
+        }
+
+        pub fn get_direction(&self) -> SignalDirection {
+            self.direction
+        }
+    }
+}
+
+/// Validates the lengths of the pricing data of the record, returning the length of the arrays.
+fn validate_record<T>(record: &T) -> Result<usize, CalculationError>
+where
+    T: Prices,
+{
+    validate_ohlc_lengths(
+        record.get_open_prices(),
+        record.get_high_prices(),
+        record.get_low_prices(),
+        record.get_close_prices(),
+    )
+}
+
+/// Wilder's directional movement index. +DI and -DI are available after the first period, the ADX after two periods.
+pub fn directional_movement<T>(
+    record: &T,
+    period: usize,
+) -> Result<structs::DirectionalMovement, CalculationError>
+where
+    T: Prices,
+{
+    validate_period(period)?;
+    let length = validate_record(record)?;
+    let high = record.get_high_prices();
+    let low = record.get_low_prices();
+    let close = record.get_close_prices();
+
+    let mut plus_di = vec![f32::NAN; length];
+    let mut minus_di = vec![f32::NAN; length];
+    let mut adx = vec![f32::NAN; length];
+    if length <= period {
+        return Ok(structs::DirectionalMovement {
+            plus_di,
+            minus_di,
+            adx,
+        });
+    }
+
+    let mut smoothed_tr = 0.0;
+    let mut smoothed_plus_dm = 0.0;
+    let mut smoothed_minus_dm = 0.0;
+    let mut directional_indexes = vec![f32::NAN; length];
+    for idx in 1..length {
+        let true_range = (high[idx] - low[idx])
+            .max((high[idx] - close[idx - 1]).abs())
+            .max((low[idx] - close[idx - 1]).abs());
+        let up_move = high[idx] - high[idx - 1];
+        let down_move = low[idx - 1] - low[idx];
+        let plus_dm = if up_move > down_move && up_move > 0.0 {
+            up_move
+        } else {
+            0.0
+        };
+        let minus_dm = if down_move > up_move && down_move > 0.0 {
+            down_move
+        } else {
+            0.0
+        };
+
+        // The first smoothed values are the sums over the first period, followed by Wilder's smoothing
+        if idx <= period {
+            smoothed_tr += true_range;
+            smoothed_plus_dm += plus_dm;
+            smoothed_minus_dm += minus_dm;
+            if idx < period {
+                continue;
+            }
+        } else {
+            smoothed_tr = smoothed_tr - smoothed_tr / period as f32 + true_range;
+            smoothed_plus_dm = smoothed_plus_dm - smoothed_plus_dm / period as f32 + plus_dm;
+            smoothed_minus_dm = smoothed_minus_dm - smoothed_minus_dm / period as f32 + minus_dm;
+        }
+
+        plus_di[idx] = 100.0 * smoothed_plus_dm / smoothed_tr;
+        minus_di[idx] = 100.0 * smoothed_minus_dm / smoothed_tr;
+        let di_sum = plus_di[idx] + minus_di[idx];
+        directional_indexes[idx] = if di_sum == 0.0 {
+            0.0
+        } else {
+            100.0 * (plus_di[idx] - minus_di[idx]).abs() / di_sum
+        };
+
+        // The ADX is seeded with the average of the first period of directional indexes
+        let first_adx = 2 * period - 1;
+        if idx == first_adx {
+            adx[idx] = directional_indexes[period..=idx].iter().sum::<f32>() / period as f32;
+        } else if idx > first_adx {
+            adx[idx] =
+                (adx[idx - 1] * (period - 1) as f32 + directional_indexes[idx]) / period as f32;
+        }
+    }
+
+    Ok(structs::DirectionalMovement {
+        plus_di,
+        minus_di,
+        adx,
+    })
+}
+
+/// Aroon up and down, based on the number of bars since the highest high and lowest low of the last period + 1 bars.
+pub fn aroon<T>(record: &T, period: usize) -> Result<structs::AroonSeries, CalculationError>
+where
+    T: Prices,
+{
+    validate_period(period)?;
+    let length = validate_record(record)?;
+    let high = record.get_high_prices();
+    let low = record.get_low_prices();
+
+    let mut up = vec![f32::NAN; length];
+    let mut down = vec![f32::NAN; length];
+    for idx in period..length {
+        // The most recent extreme is used in case of ties
+        let window = idx - period..=idx;
+        let highest = window
+            .clone()
+            .reduce(|best, x| if high[x] >= high[best] { x } else { best })
+            .unwrap_or(idx);
+        let lowest = window
+            .reduce(|best, x| if low[x] <= low[best] { x } else { best })
+            .unwrap_or(idx);
+        up[idx] = 100.0 * (period - (idx - highest)) as f32 / period as f32;
+        down[idx] = 100.0 * (period - (idx - lowest)) as f32 / period as f32;
+    }
+
+    Ok(structs::AroonSeries {
+        oscillator: up.iter().zip(&down).map(|(u, d)| u - d).collect(),
+        up,
+        down,
+    })
+}
+
+/// Wilder's parabolic SAR. The acceleration factor starts at the step, increasing by the step on every new extreme
+/// point, up to the maximum acceleration. The initial trend is taken from the direction of the first close to close move.
+pub fn parabolic_sar<T>(
+    record: &T,
+    acceleration_step: f32,
+    max_acceleration: f32,
+) -> Result<structs::TrailingStopSeries, CalculationError>
+where
+    T: Prices,
+{
+    if acceleration_step <= 0.0 || max_acceleration < acceleration_step {
+        return Err(CalculationError::InvalidParameterError(format!(
+            "Acceleration step: {} must be positive and no greater than the maximum acceleration: {}.",
+            acceleration_step, max_acceleration
+        )));
+    }
+    let length = validate_record(record)?;
+    let high = record.get_high_prices();
+    let low = record.get_low_prices();
+    let close = record.get_close_prices();
+
+    let mut stop = vec![f32::NAN; length];
+    let mut direction = vec![None; length];
+    if length < 2 {
+        return Ok(structs::TrailingStopSeries { stop, direction });
+    }
+
+    let mut is_long = close[1] >= close[0];
+    let mut sar = if is_long {
+        low[0].min(low[1])
+    } else {
+        high[0].max(high[1])
+    };
+    let mut extreme_point = if is_long { high[1] } else { low[1] };
+    let mut acceleration = acceleration_step;
+    stop[1] = sar;
+    direction[1] = Some(if is_long {
+        enums::SignalDirection::Bullish
+    } else {
+        enums::SignalDirection::Bearish
+    });
+
+    for idx in 2..length {
+        sar += acceleration * (extreme_point - sar);
+        if is_long {
+            // The SAR may not move into the range of the previous two bars
+            sar = sar.min(low[idx - 1]).min(low[idx - 2]);
+            if low[idx] < sar {
+                is_long = false;
+                sar = extreme_point;
+                extreme_point = low[idx];
+                acceleration = acceleration_step;
+            } else if high[idx] > extreme_point {
+                extreme_point = high[idx];
+                acceleration = (acceleration + acceleration_step).min(max_acceleration);
+            }
+        } else {
+            sar = sar.max(high[idx - 1]).max(high[idx - 2]);
+            if high[idx] > sar {
+                is_long = true;
+                sar = extreme_point;
+                extreme_point = high[idx];
+                acceleration = acceleration_step;
+            } else if low[idx] < extreme_point {
+                extreme_point = low[idx];
+                acceleration = (acceleration + acceleration_step).min(max_acceleration);
+            }
+        }
+        stop[idx] = sar;
+        direction[idx] = Some(if is_long {
+            enums::SignalDirection::Bullish
+        } else {
+            enums::SignalDirection::Bearish
+        });
+    }
+
+    Ok(structs::TrailingStopSeries { stop, direction })
+}
+
+/// Supertrend: the midpoint of the high and low +/- the multiplier times the ATR, ratcheted in the direction of the trend.
+/// The stop is the lower band during uptrends and the upper band during downtrends.
+pub fn supertrend<T>(
+    record: &T,
+    atr_period: usize,
+    multiplier: f32,
+) -> Result<structs::TrailingStopSeries, CalculationError>
+where
+    T: Prices,
+{
+    let average_true_range = atr(record, atr_period)?;
+    let length = average_true_range.len();
+    let high = record.get_high_prices();
+    let low = record.get_low_prices();
+    let close = record.get_close_prices();
+
+    let mut stop = vec![f32::NAN; length];
+    let mut direction = vec![None; length];
+    let mut final_upper = f32::NAN;
+    let mut final_lower = f32::NAN;
+    for idx in atr_period - 1..length {
+        let midpoint = (high[idx] + low[idx]) / 2.0;
+        let basic_upper = midpoint + multiplier * average_true_range[idx];
+        let basic_lower = midpoint - multiplier * average_true_range[idx];
+
+        let previous_direction = if idx == 0 { None } else { direction[idx - 1] };
+        let current_direction = match previous_direction {
+            None => {
+                final_upper = basic_upper;
+                final_lower = basic_lower;
+                if close[idx] >= midpoint {
+                    enums::SignalDirection::Bullish
+                } else {
+                    enums::SignalDirection::Bearish
+                }
+            }
+            Some(previous_direction) => {
+                // The bands only move against the trend once the previous close has broken through them
+                if basic_upper < final_upper || close[idx - 1] > final_upper {
+                    final_upper = basic_upper;
+                }
+                if basic_lower > final_lower || close[idx - 1] < final_lower {
+                    final_lower = basic_lower;
+                }
+                match previous_direction {
+                    enums::SignalDirection::Bullish if close[idx] < final_lower => {
+                        enums::SignalDirection::Bearish
+                    }
+                    enums::SignalDirection::Bearish if close[idx] > final_upper => {
+                        enums::SignalDirection::Bullish
+                    }
+                    _ => previous_direction,
+                }
+            }
+        };
+
+        stop[idx] = match current_direction {
+            enums::SignalDirection::Bullish => final_lower,
+            enums::SignalDirection::Bearish => final_upper,
+        };
+        direction[idx] = Some(current_direction);
+    }
+
+    Ok(structs::TrailingStopSeries { stop, direction })
+}
+
+/// Ichimoku cloud with the conversion (tenkan), base (kijun) and span B periods, projected by the displacement periods.
+/// The conventional parameters are 9, 26, 52 and 26.
+pub fn ichimoku<T>(
+    record: &T,
+    conversion_period: usize,
+    base_period: usize,
+    span_b_period: usize,
+    displacement: usize,
+) -> Result<structs::IchimokuCloud, CalculationError>
+where
+    T: Prices,
+{
+    validate_period(conversion_period)?;
+    validate_period(base_period)?;
+    validate_period(span_b_period)?;
+    let length = validate_record(record)?;
+    let high = record.get_high_prices();
+    let low = record.get_low_prices();
+    let close = record.get_close_prices();
+
+    let midpoint = |period: usize| -> Vec<f32> {
+        rolling_max(high, period)
+            .into_iter()
+            .zip(rolling_min(low, period))
+            .map(|(highest, lowest)| (highest + lowest) / 2.0)
+            .collect()
+    };
+    let tenkan_sen = midpoint(conversion_period);
+    let kijun_sen = midpoint(base_period);
+    let span_b = midpoint(span_b_period);
+
+    let mut senkou_span_a = vec![f32::NAN; length];
+    let mut senkou_span_b = vec![f32::NAN; length];
+    for idx in displacement..length {
+        senkou_span_a[idx] = (tenkan_sen[idx - displacement] + kijun_sen[idx - displacement]) / 2.0;
+        senkou_span_b[idx] = span_b[idx - displacement];
+    }
+    let chikou_span = (0..length)
+        .map(|idx| match close.get(idx + displacement) {
+            Some(value) => *value,
+            None => f32::NAN,
+        })
+        .collect();
+
+    Ok(structs::IchimokuCloud {
+        tenkan_sen,
+        kijun_sen,
+        senkou_span_a,
+        senkou_span_b,
+        chikou_span,
+    })
+}
+
+/// Crossovers of the first line over the second line: bullish when crossing above, bearish when crossing below.
+/// Bars where either line is NaN are skipped.
+pub fn crossover_events(
+    timestamps: &[chrono::DateTime<chrono::Utc>],
+    first: &[f32],
+    second: &[f32],
+) -> Result<Vec<structs::SignalEvent>, CalculationError> {
+    if timestamps.len() != first.len() || second.len() != first.len() {
+        return Err(CalculationError::InconsistentLengthError(format!(
+            "Length of the timestamps array: {} \n Length of the first array: {} \n Length of the second array: {}",
+            timestamps.len(),
+            first.len(),
+            second.len()
+        )));
+    }
+
+    Ok((1..first.len())
+        .filter_map(|idx| {
+            let previous_spread = first[idx - 1] - second[idx - 1];
+            let current_spread = first[idx] - second[idx];
+            let direction = if previous_spread <= 0.0 && current_spread > 0.0 {
+                enums::SignalDirection::Bullish
+            } else if previous_spread >= 0.0 && current_spread < 0.0 {
+                enums::SignalDirection::Bearish
+            } else {
+                return None; // Comparisons involving NaN are false, hence also skipped here
+            };
+            Some(structs::SignalEvent {
+                index: idx,
+                timestamp: timestamps[idx],
+                direction,
+            })
+        })
+        .collect())
+}
+
+/// Flips in the trend direction of a trend following indicator, e.g. the parabolic SAR or Supertrend.
+pub fn flip_events(
+    timestamps: &[chrono::DateTime<chrono::Utc>],
+    series: &structs::TrailingStopSeries,
+) -> Result<Vec<structs::SignalEvent>, CalculationError> {
+    if timestamps.len() != series.direction.len() {
+        return Err(CalculationError::InconsistentLengthError(format!(
+            "Length of the timestamps array: {} \n Length of the direction array: {}",
+            timestamps.len(),
+            series.direction.len()
+        )));
+    }
+
+    Ok((1..series.direction.len())
+        .filter_map(
+            |idx| match (series.direction[idx - 1], series.direction[idx]) {
+                (Some(previous), Some(current)) if previous != current => {
+                    Some(structs::SignalEvent {
+                        index: idx,
+                        timestamp: timestamps[idx],
+                        direction: current,
+                    })
+                }
+                _ => None,
+            },
+        )
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datasets;
+    use crate::datasets::traits::Timestamps;
+
+    /// Steady uptrend for the first half, followed by a steady downtrend.
+    fn up_then_down() -> datasets::structs::YahooFinancePriceRecord {
+        let closes = (0..10)
+            .map(|x| 10.0 + x as f32)
+            .chain((0..10).map(|x| 19.0 - 2.0 * x as f32))
+            .collect::<Vec<f32>>();
+        datasets::structs::YahooFinancePriceRecord::from_hlc(
+            &closes
+                .iter()
+                .map(|&close| (close + 0.5, close - 0.5, close))
+                .collect::<Vec<(f32, f32, f32)>>(),
+        )
+    }
+
+    #[test]
+    fn test_directional_movement() {
+        let record = datasets::structs::YahooFinancePriceRecord::from_hlc(&[
+            (10.0, 9.0, 9.5),
+            (11.0, 10.0, 10.5),
+            (12.0, 11.0, 11.5),
+            (13.0, 12.0, 12.5),
+        ]);
+        let result = directional_movement(&record, 2).unwrap();
+        assert!(result.get_plus_di()[1].is_nan());
+        // +DM of 1 and TR of 1.5 on every bar, no -DM
+        assert!((result.get_plus_di()[2] - 100.0 / 1.5).abs() < 1e-4);
+        assert!(result.get_minus_di()[3] == 0.0);
+        assert!(result.get_adx()[2].is_nan() && (result.get_adx()[3] - 100.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_aroon() {
+        let record = datasets::structs::YahooFinancePriceRecord::from_hlc(&[
+            (10.0, 5.0, 8.0),
+            (12.0, 6.0, 8.0),
+            (11.0, 7.0, 8.0),
+            (9.0, 4.0, 8.0),
+        ]);
+        let result = aroon(&record, 2).unwrap();
+        assert!(result.get_up()[1].is_nan());
+        // Index 2: highest high 1 bar ago, lowest low 2 bars ago
+        assert!(result.get_up()[2] == 50.0 && result.get_down()[2] == 0.0);
+        // Index 3: highest high 2 bars ago, lowest low on the current bar
+        assert!(result.get_oscillator()[3] == -100.0);
+    }
+
+    #[test]
+    fn test_trend_flips() {
+        let record = up_then_down();
+        let sar = parabolic_sar(&record, 0.02, 0.2).unwrap();
+        assert!(sar.get_direction()[0].is_none());
+        assert!(sar.get_direction()[9] == Some(enums::SignalDirection::Bullish));
+        assert!(sar.get_stop()[9] < record.get_low_prices()[9]);
+        let sar_flips = flip_events(record.get_timestamps(), &sar).unwrap();
+        assert!(sar_flips.len() == 1);
+        assert!(sar_flips[0].get_direction() == enums::SignalDirection::Bearish);
+        assert!(sar_flips[0].get_index() > 10);
+
+        let supertrend_series = supertrend(&record, 3, 2.0).unwrap();
+        assert!(supertrend_series.get_direction()[1].is_none());
+        let supertrend_flips = flip_events(record.get_timestamps(), &supertrend_series).unwrap();
+        assert!(supertrend_flips.len() == 1);
+        assert!(supertrend_flips[0].get_direction() == enums::SignalDirection::Bearish);
+        assert!(parabolic_sar(&record, 0.3, 0.2).is_err());
+    }
+
+    #[test]
+    fn test_ichimoku_and_crossovers() {
+        let record = up_then_down();
+        let cloud = ichimoku(&record, 2, 4, 6, 3).unwrap();
+        // Tenkan at index 1: (high 11.5 + low 9.5) / 2
+        assert!(cloud.get_tenkan_sen()[0].is_nan() && cloud.get_tenkan_sen()[1] == 10.5);
+        assert!(
+            cloud.get_senkou_span_a()[6]
+                == (cloud.get_tenkan_sen()[3] + cloud.get_kijun_sen()[3]) / 2.0
+        );
+        assert!(cloud.get_chikou_span()[0] == 13.0 && cloud.get_chikou_span()[17].is_nan());
+
+        let crossovers = crossover_events(
+            record.get_timestamps(),
+            cloud.get_tenkan_sen(),
+            cloud.get_kijun_sen(),
+        )
+        .unwrap();
+        assert!(crossovers.len() == 1);
+        assert!(crossovers[0].get_direction() == enums::SignalDirection::Bearish);
+        assert!(
+            crossovers[0].get_timestamp() == record.get_timestamps()[crossovers[0].get_index()]
+        );
+    }
+}
@@ -0,0 +1,430 @@
+//! Volume-based indicators, consuming the volume of the record together with its pricing data.
+
+use chrono::Datelike;
+
+use super::validate_period;
+use crate::datasets::traits::{Prices, Timestamps, Volume};
+use crate::errors::CalculationError;
+use crate::volatility::validate_ohlc_lengths;
+
+pub mod enums {
+
+    /// Anchor at which the cumulative sums of the VWAP are reset.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum VwapAnchor {
+        Session, // Resets on every calendar day (UTC)
+        Weekly,  // Resets on every ISO week
+        Monthly,
+        Bars(usize), // Resets after every N bars
+    }
+}
+
+pub mod structs {
+
+    /// Volume traded at each price level, over equally spaced price bins.
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct VolumeProfile {
+        pub(super) bin_edges: Vec<f32>, // num_of_bins + 1 edges, from the lowest low to the highest high
+        pub(super) volumes: Vec<f32>,
+    }
+
+    impl VolumeProfile {
+        pub fn get_bin_edges(&self) -> &[f32] {
+            &self.bin_edges
+        }
+
+        pub fn get_volumes(&self) -> &[f32] {
+            &self.volumes
+        }
+
+        /// Midpoint of each price bin.
+        pub fn get_price_levels(&self) -> Vec<f32> {
+            self.bin_edges
+                .windows(2)
+                .map(|edges| (edges[0] + edges[1]) / 2.0)
+                .collect()
+        }
+
+        /// Price level with the highest traded volume, the lowest such level in case of ties.
+        pub fn get_point_of_control(&self) -> Option<f32> {
+            let idx = (0..self.volumes.len()).reduce(|best, idx| {
+                if self.volumes[idx] > self.volumes[best] {
+                    idx
+                } else {
+                    best
+                }
+            })?;
+            Some((self.bin_edges[idx] + self.bin_edges[idx + 1]) / 2.0)
+        }
+    }
+}
+
+/// Validates the lengths of the pricing data against the volume, returning the length of the arrays.
+fn validate_volume_length<T>(record: &T) -> Result<usize, CalculationError>
+where
+    T: Prices + Volume,
+{
+    let length = validate_ohlc_lengths(
+        record.get_open_prices(),
+        record.get_high_prices(),
+        record.get_low_prices(),
+        record.get_close_prices(),
+    )?;
+    if record.get_volume().len() != length {
+        return Err(CalculationError::InconsistentLengthError(format!(
+            "Length of the prices array: {} \n Length of the volume array: {}",
+            length,
+            record.get_volume().len()
+        )));
+    }
+    Ok(length)
+}
+
+/// Typical price of each bar: (high + low + close) / 3.
+fn typical_prices<T>(record: &T) -> Vec<f32>
+where
+    T: Prices,
+{
+    let high = record.get_high_prices();
+    let low = record.get_low_prices();
+    record
+        .get_close_prices()
+        .iter()
+        .enumerate()
+        .map(|(idx, close)| (high[idx] + low[idx] + close) / 3.0)
+        .collect()
+}
+
+/// Close location value of each bar: +1 when closing at the high, -1 when closing at the low.
+/// Bars without a range are treated as neutral.
+fn money_flow_multipliers<T>(record: &T) -> Vec<f32>
+where
+    T: Prices,
+{
+    let high = record.get_high_prices();
+    let low = record.get_low_prices();
+    record
+        .get_close_prices()
+        .iter()
+        .enumerate()
+        .map(|(idx, close)| {
+            let range = high[idx] - low[idx];
+            if range == 0.0 {
+                return 0.0;
+            }
+            ((close - low[idx]) - (high[idx] - close)) / range
+        })
+        .collect()
+}
+
+/// On-balance volume: the volume is added on up closes and subtracted on down closes, starting from 0.
+pub fn obv<T>(record: &T) -> Result<Vec<f32>, CalculationError>
+where
+    T: Prices + Volume,
+{
+    let length = validate_volume_length(record)?;
+    let close = record.get_close_prices();
+    let volume = record.get_volume();
+
+    let mut result = Vec::with_capacity(length);
+    let mut running_total = 0.0_f64;
+    for idx in 0..length {
+        if idx > 0 {
+            if close[idx] > close[idx - 1] {
+                running_total += volume[idx] as f64;
+            } else if close[idx] < close[idx - 1] {
+                running_total -= volume[idx] as f64;
+            }
+        }
+        result.push(running_total as f32);
+    }
+    Ok(result)
+}
+
+/// Accumulation/distribution line: running sum of the volume weighted by the close location value.
+pub fn accumulation_distribution<T>(record: &T) -> Result<Vec<f32>, CalculationError>
+where
+    T: Prices + Volume,
+{
+    validate_volume_length(record)?;
+    let volume = record.get_volume();
+
+    let mut running_total = 0.0_f64;
+    Ok(money_flow_multipliers(record)
+        .into_iter()
+        .zip(volume)
+        .map(|(multiplier, volume)| {
+            running_total += multiplier as f64 * *volume as f64;
+            running_total as f32
+        })
+        .collect())
+}
+
+/// Chaikin money flow: sum of the money flow volume over the period, divided by the total volume of the period.
+pub fn chaikin_money_flow<T>(record: &T, period: usize) -> Result<Vec<f32>, CalculationError>
+where
+    T: Prices + Volume,
+{
+    validate_period(period)?;
+    let length = validate_volume_length(record)?;
+    let volume = record.get_volume();
+    let multipliers = money_flow_multipliers(record);
+
+    Ok((0..length)
+        .map(|idx| {
+            if idx + 1 < period {
+                return f32::NAN;
+            }
+            let window = idx + 1 - period..=idx;
+            let money_flow_volume: f64 = window
+                .clone()
+                .map(|x| multipliers[x] as f64 * volume[x] as f64)
+                .sum();
+            let total_volume: f64 = window.map(|x| volume[x] as f64).sum();
+            (money_flow_volume / total_volume) as f32
+        })
+        .collect())
+}
+
+/// Money flow index: volume weighted RSI over the typical prices, between 0 and 100.
+pub fn money_flow_index<T>(record: &T, period: usize) -> Result<Vec<f32>, CalculationError>
+where
+    T: Prices + Volume,
+{
+    validate_period(period)?;
+    let length = validate_volume_length(record)?;
+    let volume = record.get_volume();
+    let typical = typical_prices(record);
+
+    // Positive and negative raw money flows, based on the direction of the typical price
+    let flows = (0..length)
+        .map(|idx| {
+            if idx == 0 {
+                return (0.0, 0.0);
+            }
+            let raw_money_flow = typical[idx] as f64 * volume[idx] as f64;
+            if typical[idx] > typical[idx - 1] {
+                (raw_money_flow, 0.0)
+            } else if typical[idx] < typical[idx - 1] {
+                (0.0, raw_money_flow)
+            } else {
+                (0.0, 0.0)
+            }
+        })
+        .collect::<Vec<(f64, f64)>>();
+
+    Ok((0..length)
+        .map(|idx| {
+            if idx < period {
+                return f32::NAN;
+            }
+            let (positive, negative) = flows[idx + 1 - period..=idx]
+                .iter()
+                .fold((0.0, 0.0), |acc, flow| (acc.0 + flow.0, acc.1 + flow.1));
+            if negative == 0.0 {
+                return 100.0;
+            }
+            (100.0 - 100.0 / (1.0 + positive / negative)) as f32
+        })
+        .collect())
+}
+
+/// Volume weighted average price of the typical prices, with the cumulative sums reset at every anchor.
+pub fn vwap<T>(record: &T, anchor: enums::VwapAnchor) -> Result<Vec<f32>, CalculationError>
+where
+    T: Prices + Volume + Timestamps,
+{
+    let length = validate_volume_length(record)?;
+    let timestamps = record.get_timestamps();
+    if timestamps.len() != length {
+        return Err(CalculationError::InconsistentLengthError(format!(
+            "Length of the timestamps array: {} \n Length of the prices array: {}",
+            timestamps.len(),
+            length
+        )));
+    }
+    if let enums::VwapAnchor::Bars(num_of_bars) = anchor {
+        validate_period(num_of_bars)?;
+    }
+    let volume = record.get_volume();
+    let typical = typical_prices(record);
+
+    // Key identifying the bucket of each bar, the sums are reset whenever the key changes
+    let bucket_key = |idx: usize| -> (i32, usize) {
+        let timestamp = timestamps[idx];
+        match anchor {
+            enums::VwapAnchor::Session => (timestamp.year(), timestamp.ordinal() as usize),
+            enums::VwapAnchor::Weekly => {
+                let week = timestamp.iso_week();
+                (week.year(), week.week() as usize)
+            }
+            enums::VwapAnchor::Monthly => (timestamp.year(), timestamp.month() as usize),
+            enums::VwapAnchor::Bars(num_of_bars) => (0, idx / num_of_bars),
+        }
+    };
+
+    let mut result = Vec::with_capacity(length);
+    let mut weighted_sum = 0.0_f64;
+    let mut total_volume = 0.0_f64;
+    for idx in 0..length {
+        if idx > 0 && bucket_key(idx) != bucket_key(idx - 1) {
+            weighted_sum = 0.0;
+            total_volume = 0.0;
+        }
+        weighted_sum += typical[idx] as f64 * volume[idx] as f64;
+        total_volume += volume[idx] as f64;
+        result.push((weighted_sum / total_volume) as f32);
+    }
+    Ok(result)
+}
+
+/// Volume of each bar relative to the average volume of the preceding period bars.
+pub fn relative_volume<T>(record: &T, period: usize) -> Result<Vec<f32>, CalculationError>
+where
+    T: Volume,
+{
+    validate_period(period)?;
+    let volume = record.get_volume();
+
+    Ok((0..volume.len())
+        .map(|idx| {
+            if idx < period {
+                return f32::NAN;
+            }
+            let average_volume = volume[idx - period..idx]
+                .iter()
+                .map(|&x| x as f64)
+                .sum::<f64>()
+                / period as f64;
+            (volume[idx] as f64 / average_volume) as f32
+        })
+        .collect())
+}
+
+/// Histogram of the traded volume by price level. The volume of each bar is spread evenly across its high - low range.
+pub fn volume_profile<T>(
+    record: &T,
+    num_of_bins: usize,
+) -> Result<structs::VolumeProfile, CalculationError>
+where
+    T: Prices + Volume,
+{
+    if num_of_bins == 0 {
+        return Err(CalculationError::InvalidParameterError(
+            "Number of price bins must be greater than 0.".to_string(),
+        ));
+    }
+    let length = validate_volume_length(record)?;
+    if length == 0 {
+        return Err(CalculationError::InsufficientDataError(
+            "Volume profile requires at least 1 bar.".to_string(),
+        ));
+    }
+    let high = record.get_high_prices();
+    let low = record.get_low_prices();
+    let close = record.get_close_prices();
+    let volume = record.get_volume();
+
+    let lowest = low.iter().copied().fold(f32::INFINITY, f32::min);
+    let highest = high.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let bin_width = (highest - lowest) / num_of_bins as f32;
+    let bin_edges = (0..=num_of_bins)
+        .map(|x| lowest + bin_width * x as f32)
+        .collect::<Vec<f32>>();
+    let bin_index = |price: f32| -> usize {
+        if bin_width == 0.0 {
+            return 0;
+        }
+        (((price - lowest) / bin_width) as usize).min(num_of_bins - 1)
+    };
+
+    let mut volumes = vec![0.0_f32; num_of_bins];
+    for idx in 0..length {
+        let range = high[idx] - low[idx];
+        if range == 0.0 {
+            volumes[bin_index(close[idx])] += volume[idx] as f32;
+            continue;
+        }
+        // Allocating the volume in proportion to the overlap of the bar's range with each bin
+        for bin in bin_index(low[idx])..=bin_index(high[idx]) {
+            let overlap = high[idx].min(bin_edges[bin + 1]) - low[idx].max(bin_edges[bin]);
+            if overlap > 0.0 {
+                volumes[bin] += volume[idx] as f32 * overlap / range;
+            }
+        }
+    }
+
+    Ok(structs::VolumeProfile { bin_edges, volumes })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datasets;
+
+    #[test]
+    fn test_obv_and_accumulation_distribution() {
+        let record = datasets::structs::YahooFinancePriceRecord::from_hlcv(&[
+            (11.0, 9.0, 10.0, 100),
+            (12.0, 10.0, 12.0, 200),
+            (12.0, 10.0, 11.0, 300),
+            (12.0, 10.0, 11.0, 400),
+        ]);
+        assert!(obv(&record).unwrap() == vec![0.0, 200.0, -100.0, -100.0]);
+        // Multipliers: 0, 1, 0, 0
+        assert!(accumulation_distribution(&record).unwrap() == vec![0.0, 200.0, 200.0, 200.0]);
+        let cmf = chaikin_money_flow(&record, 2).unwrap();
+        assert!(cmf[0].is_nan() && (cmf[1] - 200.0 / 300.0).abs() < 1e-6 && cmf[3] == 0.0);
+    }
+
+    #[test]
+    fn test_money_flow_index() {
+        let record = datasets::structs::YahooFinancePriceRecord::from_hlcv(&[
+            (3.0, 3.0, 3.0, 100),
+            (6.0, 6.0, 6.0, 100),
+            (3.0, 3.0, 3.0, 200),
+            (6.0, 6.0, 6.0, 100),
+        ]);
+        let result = money_flow_index(&record, 2).unwrap();
+        assert!(result[1].is_nan());
+        // Positive flow: 600, negative flow: 600
+        assert!((result[2] - 50.0).abs() < 1e-4);
+        // Positive flow: 600, negative flow: 600
+        assert!((result[3] - 50.0).abs() < 1e-4);
+        assert!(money_flow_index(&record, 0).is_err());
+    }
+
+    #[test]
+    fn test_vwap_and_relative_volume() {
+        let record = datasets::structs::YahooFinancePriceRecord::from_hlcv(&[
+            (3.0, 3.0, 3.0, 100),
+            (6.0, 6.0, 6.0, 200),
+            (9.0, 9.0, 9.0, 100),
+            (3.0, 3.0, 3.0, 400),
+        ]);
+        // 2022-01-03 is a Monday, hence all bars fall within the same week
+        let weekly = vwap(&record, enums::VwapAnchor::Weekly).unwrap();
+        assert!((weekly[2] - 6.0).abs() < 1e-6);
+        let buckets = vwap(&record, enums::VwapAnchor::Bars(2)).unwrap();
+        assert!((buckets[1] - 5.0).abs() < 1e-6 && (buckets[3] - 4.2).abs() < 1e-6);
+        let sessions = vwap(&record, enums::VwapAnchor::Session).unwrap();
+        assert!(sessions == vec![3.0, 6.0, 9.0, 3.0]);
+
+        let relative = relative_volume(&record, 2).unwrap();
+        assert!(relative[1].is_nan() && (relative[2] - 100.0 / 150.0).abs() < 1e-6);
+        assert!((relative[3] - 400.0 / 150.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_volume_profile() {
+        let record = datasets::structs::YahooFinancePriceRecord::from_hlcv(&[
+            (12.0, 10.0, 11.0, 100),
+            (14.0, 12.0, 13.0, 300),
+        ]);
+        let profile = volume_profile(&record, 4).unwrap();
+        assert!(profile.get_bin_edges() == [10.0, 11.0, 12.0, 13.0, 14.0]);
+        assert!(profile.get_volumes() == [50.0, 50.0, 150.0, 150.0]);
+        assert!(profile.get_point_of_control() == Some(12.5));
+        assert!(volume_profile(&record, 0).is_err());
+    }
+}
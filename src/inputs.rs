@@ -26,9 +26,11 @@ pub mod enums {
         DualPrice(BasePriceType, BasePriceType),
     }
 
-    #[derive(Clone, Copy)]
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
     pub enum AggregationPeriod {
         Weekly,
+        Monthly,
+        Quarterly,
     }
 }
 
@@ -72,7 +74,7 @@ pub fn get_input() -> Result<InputArgs, errors::InputError> {
     // Getting the user input
     let user_prompt = "Please provide the user input in the following format: <ticker symbol> <price type> <aggregation period> <start date> <end date> \n
     * <price type> - Accepted values are: 'high', 'low', 'open', 'close'. For compound calculations (such as price deltas), provide the two values in the following syntax: <price_type 1>|<price_type 2>. \n
-    * <aggregation period> - Accepted values are: 'weekly'.\n
+    * <aggregation period> - Accepted values are: 'weekly', 'monthly', 'quarterly'.\n
     * <start date> - Provided in the following format: YYYY-MM-DD.\n
     * <end date> - Provided in the following format: YYYY-MM-DD.
     ";
@@ -137,6 +139,8 @@ pub fn get_input() -> Result<InputArgs, errors::InputError> {
     // Matching on the PriceType
     let aggregation_period = match input_args.get(2).unwrap() as &str {
         "weekly" => enums::AggregationPeriod::Weekly,
+        "monthly" => enums::AggregationPeriod::Monthly,
+        "quarterly" => enums::AggregationPeriod::Quarterly,
         _ => {
             return Err(errors::InputError::InvalidAggregationPeriod(format!(
                 "Value passed for the aggregation period is invalid."
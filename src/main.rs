@@ -1,13 +1,30 @@
 use datasets::traits::{Prices, Timestamps};
 use functions::AggregationFunctions;
 
+mod backtest;
+mod bars;
+mod benchmark;
 mod datasets;
+mod drawdowns;
 mod enums;
 mod errors;
 mod functions;
+mod gaps;
+mod indicators;
 mod inputs;
 mod parsers;
+mod patterns;
+mod performance;
+mod portfolio;
 mod requests;
+mod returns;
+mod simulation;
+mod statistics;
+mod support_resistance;
+mod synthetic;
+mod trades;
+mod value_at_risk;
+mod volatility;
 
 enum ValueTypes<'a, T> {
     SingleValues(&'a [T]),
@@ -77,15 +94,15 @@ fn main() {
             let timestamps = dataset.get_timestamps();
 
             // Grouping into period groups
-            let grouped = match input_args.get_aggregation_period() {
-                inputs::enums::AggregationPeriod::Weekly => {
-                    match functions::Grouping::groupby_weekly(timestamps, price_values) {
-                        Ok(i) => i,
-                        Err(e) => {
-                            println!("Error encountered! See the following error raised: {}.", e);
-                            return ();
-                        }
-                    }
+            let grouped = match functions::Grouping::groupby_period(
+                timestamps,
+                price_values,
+                input_args.get_aggregation_period(),
+            ) {
+                Ok(i) => i,
+                Err(e) => {
+                    println!("Error encountered! See the following error raised: {}.", e);
+                    return ();
                 }
             };
 
@@ -133,6 +150,7 @@ fn main() {
                 2 - Price delta percentage. This will be calculated by using the formula for each timestamp: (({} price - {} price)/{} price) * 100.\n
                 3 - Trailing Price delta. This will be calculated by using the formula for each timestamp: ({} price - previous {} price).\n
                 4 - Trailing Price delta percentage. This will be calculated by using the formula for each timestamp: (({} price - previous {} price)/ previous {} price) * 100.\n
+                5 - ATR scaled trailing price delta. This will be calculated by using the formula for each timestamp: ({} price - previous {} price) / previous 14-period ATR.\n
                 ", price_type_first_str
                 , price_type_second_str
                 , price_type_first_str
@@ -142,6 +160,8 @@ fn main() {
                 , price_type_second_str
                 , price_type_first_str
                 , price_type_second_str
+                , price_type_second_str
+                , price_type_first_str
                 , price_type_second_str);
                 let input_string = match inputs::stdin(&prompt_msg) {
                     Ok(i) => i,
@@ -193,6 +213,20 @@ fn main() {
                             });
                         break result;
                     }
+                    "5" => {
+                        match indicators::bands::atr_scaled_trailing_delta(
+                            &dataset,
+                            price_type_first,
+                            price_type_second,
+                            14,
+                        ) {
+                            Ok(i) => break i,
+                            Err(e) => {
+                                println!("Error encountered! See the following error raised: {}.", e);
+                                return;
+                            }
+                        }
+                    }
                     _ => {
                         println!(
                             "Unknown summary function provided. Please input a valid summary function!"
@@ -204,15 +238,15 @@ fn main() {
 
             // Grouping into period groups
             let timestamps = dataset.get_timestamps();
-            let grouped = match input_args.get_aggregation_period() {
-                inputs::enums::AggregationPeriod::Weekly => {
-                    match functions::Grouping::groupby_weekly(timestamps, &price_values) {
-                        Ok(i) => i,
-                        Err(e) => {
-                            println!("Error encountered! See the following error raised: {}.", e);
-                            return ();
-                        }
-                    }
+            let grouped = match functions::Grouping::groupby_period(
+                timestamps,
+                &price_values,
+                input_args.get_aggregation_period(),
+            ) {
+                Ok(i) => i,
+                Err(e) => {
+                    println!("Error encountered! See the following error raised: {}.", e);
+                    return ();
                 }
             };
 
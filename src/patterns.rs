@@ -0,0 +1,497 @@
+//! Objective: Detect candlestick patterns over the daily OHLC prices of a record, and summarize the returns following each pattern.
+
+use std::collections::BTreeMap;
+
+use crate::datasets::traits::{Prices, Timestamps};
+use crate::errors::CalculationError;
+use crate::functions;
+use crate::indicators::trend::enums::SignalDirection;
+use crate::volatility::validate_ohlc_lengths;
+
+pub mod enums {
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize)]
+    pub enum CandlestickPattern {
+        Doji,
+        Hammer,
+        BullishEngulfing,
+        BearishEngulfing,
+        BullishHarami,
+        BearishHarami,
+        MorningStar,
+        EveningStar,
+        ThreeWhiteSoldiers,
+        ThreeBlackCrows,
+        InsideBar,
+        OutsideBar,
+    }
+}
+
+pub mod structs {
+    use super::enums::CandlestickPattern;
+
+    /// Body and shadow thresholds of the pattern definitions, expressed as fractions of the bar's high - low range
+    /// unless stated otherwise.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct PatternThresholds {
+        pub(super) doji_body_ratio: f32,       // Maximum body of a doji
+        pub(super) small_body_ratio: f32, // Maximum body of the middle bar of a star, and the inner bar of a harami
+        pub(super) long_body_ratio: f32,  // Minimum body of the bars of a star, soldiers and crows
+        pub(super) shadow_to_body_ratio: f32, // Minimum lower shadow of a hammer, as a multiple of its body
+        pub(super) opposite_shadow_ratio: f32, // Maximum upper shadow of a hammer
+    }
+
+    impl Default for PatternThresholds {
+        fn default() -> Self {
+            PatternThresholds {
+                doji_body_ratio: 0.1,
+                small_body_ratio: 0.3,
+                long_body_ratio: 0.6,
+                shadow_to_body_ratio: 2.0,
+                opposite_shadow_ratio: 0.1,
+            }
+        }
+    }
+
+    /// Pattern completed on the bar at the given index.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct PatternOccurrence {
+        pub(super) pattern: CandlestickPattern,
+        pub(super) index: usize,
+        pub(super) timestamp: chrono::DateTime<chrono::Utc>,
+    }
+
+    impl PatternOccurrence {
+        pub fn get_pattern(&self) -> CandlestickPattern {
+            self.pattern
+        }
+
+        pub fn get_index(&self) -> usize {
+            self.index
+        }
+
+        pub fn get_timestamp(&self) -> chrono::DateTime<chrono::Utc> {
+            self.timestamp
+        }
+    }
+
+    /// Close to close returns over the horizon following the occurrences of a pattern.
+    #[derive(Clone, Copy, Debug, PartialEq, serde::Serialize)]
+    pub struct ForwardReturnSummary {
+        pub(super) pattern: CandlestickPattern,
+        pub(super) horizon: usize,
+        pub(super) num_of_occurrences: usize, // Occurrences without enough subsequent bars are excluded
+        pub(super) mean_return: f32,
+        pub(super) median_return: f32,
+        pub(super) hit_rate: f32, // Fraction of positive forward returns
+    }
+
+    impl ForwardReturnSummary {
+        pub fn get_pattern(&self) -> CandlestickPattern {
+            self.pattern
+        }
+
+        pub fn get_horizon(&self) -> usize {
+            self.horizon
+        }
+
+        pub fn get_num_of_occurrences(&self) -> usize {
+            self.num_of_occurrences
+        }
+
+        pub fn get_mean_return(&self) -> f32 {
+            self.mean_return
+        }
+
+        pub fn get_median_return(&self) -> f32 {
+            self.median_return
+        }
+
+        pub fn get_hit_rate(&self) -> f32 {
+            self.hit_rate
+        }
+    }
+}
+
+impl enums::CandlestickPattern {
+    /// Direction implied by the pattern, None for the indecision patterns.
+    pub fn get_direction(&self) -> Option<SignalDirection> {
+        match self {
+            enums::CandlestickPattern::Hammer
+            | enums::CandlestickPattern::BullishEngulfing
+            | enums::CandlestickPattern::BullishHarami
+            | enums::CandlestickPattern::MorningStar
+            | enums::CandlestickPattern::ThreeWhiteSoldiers => Some(SignalDirection::Bullish),
+            enums::CandlestickPattern::BearishEngulfing
+            | enums::CandlestickPattern::BearishHarami
+            | enums::CandlestickPattern::EveningStar
+            | enums::CandlestickPattern::ThreeBlackCrows => Some(SignalDirection::Bearish),
+            enums::CandlestickPattern::Doji
+            | enums::CandlestickPattern::InsideBar
+            | enums::CandlestickPattern::OutsideBar => None,
+        }
+    }
+}
+
+impl structs::PatternThresholds {
+    pub fn new(
+        doji_body_ratio: f32,
+        small_body_ratio: f32,
+        long_body_ratio: f32,
+        shadow_to_body_ratio: f32,
+        opposite_shadow_ratio: f32,
+    ) -> Result<Self, CalculationError> {
+        let ratios_are_ordered = 0.0 < doji_body_ratio
+            && doji_body_ratio <= small_body_ratio
+            && small_body_ratio < long_body_ratio
+            && long_body_ratio <= 1.0;
+        if !ratios_are_ordered || shadow_to_body_ratio <= 0.0 || opposite_shadow_ratio < 0.0 {
+            return Err(CalculationError::InvalidParameterError(format!(
+                "Body ratios must satisfy 0 < doji: {} <= small: {} < long: {} <= 1, with positive shadow ratios: {} and {}.",
+                doji_body_ratio, small_body_ratio, long_body_ratio, shadow_to_body_ratio, opposite_shadow_ratio
+            )));
+        }
+        Ok(structs::PatternThresholds {
+            doji_body_ratio,
+            small_body_ratio,
+            long_body_ratio,
+            shadow_to_body_ratio,
+            opposite_shadow_ratio,
+        })
+    }
+}
+
+/// Geometry of a single candle.
+#[derive(Clone, Copy)]
+struct Candle {
+    open: f32,
+    high: f32,
+    low: f32,
+    close: f32,
+}
+
+impl Candle {
+    fn body(&self) -> f32 {
+        (self.close - self.open).abs()
+    }
+
+    fn range(&self) -> f32 {
+        self.high - self.low
+    }
+
+    fn body_top(&self) -> f32 {
+        self.open.max(self.close)
+    }
+
+    fn body_bottom(&self) -> f32 {
+        self.open.min(self.close)
+    }
+
+    fn upper_shadow(&self) -> f32 {
+        self.high - self.body_top()
+    }
+
+    fn lower_shadow(&self) -> f32 {
+        self.body_bottom() - self.low
+    }
+
+    fn is_bullish(&self) -> bool {
+        self.close > self.open
+    }
+
+    fn is_bearish(&self) -> bool {
+        self.close < self.open
+    }
+
+    /// Body is at most the ratio of the range. Bars without a range are excluded.
+    fn has_small_body(&self, ratio: f32) -> bool {
+        self.range() > 0.0 && self.body() <= ratio * self.range()
+    }
+
+    fn has_long_body(&self, ratio: f32) -> bool {
+        self.range() > 0.0 && self.body() >= ratio * self.range()
+    }
+}
+
+/// Patterns completed on the current candle, given the preceding candles (most recent last).
+fn match_patterns(
+    candles: &[Candle],
+    thresholds: &structs::PatternThresholds,
+) -> Vec<enums::CandlestickPattern> {
+    let mut patterns = Vec::new();
+    let current = candles[candles.len() - 1];
+
+    // Single candle patterns
+    if current.has_small_body(thresholds.doji_body_ratio) {
+        patterns.push(enums::CandlestickPattern::Doji);
+    } else if current.range() > 0.0
+        && current.lower_shadow() >= thresholds.shadow_to_body_ratio * current.body()
+        && current.upper_shadow() <= thresholds.opposite_shadow_ratio * current.range()
+    {
+        patterns.push(enums::CandlestickPattern::Hammer);
+    }
+
+    // Two candle patterns
+    if candles.len() >= 2 {
+        let previous = candles[candles.len() - 2];
+        let engulfs_body = current.body_top() >= previous.body_top()
+            && current.body_bottom() <= previous.body_bottom()
+            && current.body() > previous.body();
+        if engulfs_body && previous.is_bearish() && current.is_bullish() {
+            patterns.push(enums::CandlestickPattern::BullishEngulfing);
+        } else if engulfs_body && previous.is_bullish() && current.is_bearish() {
+            patterns.push(enums::CandlestickPattern::BearishEngulfing);
+        }
+
+        let within_body = current.body_top() <= previous.body_top()
+            && current.body_bottom() >= previous.body_bottom()
+            && current.body() <= thresholds.small_body_ratio * previous.body();
+        if within_body && previous.has_long_body(thresholds.long_body_ratio) {
+            if previous.is_bearish() {
+                patterns.push(enums::CandlestickPattern::BullishHarami);
+            } else if previous.is_bullish() {
+                patterns.push(enums::CandlestickPattern::BearishHarami);
+            }
+        }
+
+        if current.high < previous.high && current.low > previous.low {
+            patterns.push(enums::CandlestickPattern::InsideBar);
+        } else if current.high > previous.high && current.low < previous.low {
+            patterns.push(enums::CandlestickPattern::OutsideBar);
+        }
+    }
+
+    // Three candle patterns
+    if candles.len() >= 3 {
+        let first = candles[candles.len() - 3];
+        let middle = candles[candles.len() - 2];
+        let first_midpoint = (first.open + first.close) / 2.0;
+        let is_star = first.has_long_body(thresholds.long_body_ratio)
+            && middle.has_small_body(thresholds.small_body_ratio)
+            && current.has_long_body(thresholds.long_body_ratio);
+        if is_star
+            && first.is_bearish()
+            && middle.body_top() <= first.close
+            && current.is_bullish()
+            && current.close > first_midpoint
+        {
+            patterns.push(enums::CandlestickPattern::MorningStar);
+        } else if is_star
+            && first.is_bullish()
+            && middle.body_bottom() >= first.close
+            && current.is_bearish()
+            && current.close < first_midpoint
+        {
+            patterns.push(enums::CandlestickPattern::EveningStar);
+        }
+
+        let window = [first, middle, current];
+        let all_long = window
+            .iter()
+            .all(|candle| candle.has_long_body(thresholds.long_body_ratio));
+        // Each candle opens within the body of the previous candle and closes beyond its close
+        let soldiers = window.windows(2).all(|pair| {
+            pair[1].is_bullish()
+                && pair[1].open >= pair[0].body_bottom()
+                && pair[1].open <= pair[0].body_top()
+                && pair[1].close > pair[0].close
+        });
+        let crows = window.windows(2).all(|pair| {
+            pair[1].is_bearish()
+                && pair[1].open >= pair[0].body_bottom()
+                && pair[1].open <= pair[0].body_top()
+                && pair[1].close < pair[0].close
+        });
+        if all_long && first.is_bullish() && soldiers {
+            patterns.push(enums::CandlestickPattern::ThreeWhiteSoldiers);
+        } else if all_long && first.is_bearish() && crows {
+            patterns.push(enums::CandlestickPattern::ThreeBlackCrows);
+        }
+    }
+
+    patterns
+}
+
+/// Scans the record for candlestick patterns, returning the dated occurrences in chronological order.
+/// Multi-candle patterns are dated on the bar completing the pattern.
+pub fn detect_patterns<T>(
+    record: &T,
+    thresholds: &structs::PatternThresholds,
+) -> Result<Vec<structs::PatternOccurrence>, CalculationError>
+where
+    T: Prices + Timestamps,
+{
+    let open = record.get_open_prices();
+    let high = record.get_high_prices();
+    let low = record.get_low_prices();
+    let close = record.get_close_prices();
+    let length = validate_ohlc_lengths(open, high, low, close)?;
+    let timestamps = record.get_timestamps();
+    if timestamps.len() != length {
+        return Err(CalculationError::InconsistentLengthError(format!(
+            "Length of the timestamps array: {} \n Length of the prices array: {}",
+            timestamps.len(),
+            length
+        )));
+    }
+
+    let candles = (0..length)
+        .map(|idx| Candle {
+            open: open[idx],
+            high: high[idx],
+            low: low[idx],
+            close: close[idx],
+        })
+        .collect::<Vec<Candle>>();
+
+    Ok((0..length)
+        .flat_map(|idx| {
+            let start = idx.saturating_sub(2);
+            match_patterns(&candles[start..=idx], thresholds)
+                .into_iter()
+                .map(move |pattern| structs::PatternOccurrence {
+                    pattern,
+                    index: idx,
+                    timestamp: timestamps[idx],
+                })
+        })
+        .collect())
+}
+
+/// Summarizes the close to close returns over the horizon following each occurrence, grouped by pattern.
+pub fn forward_return_summary<T>(
+    record: &T,
+    occurrences: &[structs::PatternOccurrence],
+    horizon: usize,
+) -> Result<Vec<structs::ForwardReturnSummary>, CalculationError>
+where
+    T: Prices,
+{
+    if horizon == 0 {
+        return Err(CalculationError::InvalidParameterError(
+            "Forward return horizon must be greater than 0.".to_string(),
+        ));
+    }
+    let close = record.get_close_prices();
+
+    let mut grouped_returns: BTreeMap<enums::CandlestickPattern, Vec<f32>> = BTreeMap::new();
+    occurrences
+        .iter()
+        .filter(|occurrence| occurrence.index + horizon < close.len())
+        .for_each(|occurrence| {
+            grouped_returns
+                .entry(occurrence.pattern)
+                .or_default()
+                .push(close[occurrence.index + horizon] / close[occurrence.index] - 1.0)
+        });
+
+    Ok(grouped_returns
+        .into_iter()
+        .map(|(pattern, mut forward_returns)| {
+            forward_returns.sort_by(|a, b| a.total_cmp(b));
+            let count = forward_returns.len();
+            let median_return =
+                functions::percentile_from_sorted_array(50, &forward_returns).unwrap(); // Infallible for f32 arrays
+            structs::ForwardReturnSummary {
+                pattern,
+                horizon,
+                num_of_occurrences: count,
+                mean_return: forward_returns.iter().sum::<f32>() / count as f32,
+                median_return,
+                hit_rate: forward_returns.iter().filter(|&&x| x > 0.0).count() as f32
+                    / count as f32,
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datasets;
+    use chrono::TimeZone;
+
+    fn patterns_at(
+        occurrences: &[structs::PatternOccurrence],
+        index: usize,
+    ) -> Vec<enums::CandlestickPattern> {
+        occurrences
+            .iter()
+            .filter(|x| x.get_index() == index)
+            .map(|x| x.get_pattern())
+            .collect()
+    }
+
+    #[test]
+    fn test_single_and_two_candle_patterns() {
+        let record = datasets::structs::YahooFinancePriceRecord::from_ohlc(&[
+            (10.0, 10.5, 7.5, 8.0), // Long bearish candle
+            (8.55, 9.0, 7.0, 8.5),  // Doji within the previous body
+            (7.8, 10.2, 6.9, 10.0), // Bullish engulfing, outside bar
+            (9.5, 10.0, 8.0, 9.95), // Hammer within the previous body, inside bar
+        ]);
+        let occurrences = detect_patterns(&record, &Default::default()).unwrap();
+        assert!(patterns_at(&occurrences, 0).is_empty());
+        assert!(patterns_at(&occurrences, 1).contains(&enums::CandlestickPattern::Doji));
+        assert!(patterns_at(&occurrences, 1).contains(&enums::CandlestickPattern::BullishHarami));
+        assert!(
+            patterns_at(&occurrences, 2)
+                == vec![
+                    enums::CandlestickPattern::BullishEngulfing,
+                    enums::CandlestickPattern::OutsideBar
+                ]
+        );
+        assert!(
+            patterns_at(&occurrences, 3)
+                == vec![
+                    enums::CandlestickPattern::Hammer,
+                    enums::CandlestickPattern::BearishHarami,
+                    enums::CandlestickPattern::InsideBar
+                ]
+        );
+        assert!(occurrences[0].get_timestamp() == chrono::Utc.ymd(2022, 1, 4).and_hms(0, 0, 0));
+    }
+
+    #[test]
+    fn test_three_candle_patterns() {
+        let record = datasets::structs::YahooFinancePriceRecord::from_ohlc(&[
+            (10.0, 10.2, 7.8, 8.0),   // Long bearish candle
+            (7.8, 8.0, 7.0, 7.6),     // Small body below the first close
+            (7.7, 9.7, 7.6, 9.5),     // Long bullish candle closing above the first midpoint
+            (9.4, 11.1, 9.3, 11.0),   // Second soldier
+            (10.9, 12.6, 10.8, 12.5), // Third soldier
+        ]);
+        let occurrences = detect_patterns(&record, &Default::default()).unwrap();
+        assert!(patterns_at(&occurrences, 2).contains(&enums::CandlestickPattern::MorningStar));
+        assert!(
+            patterns_at(&occurrences, 4).contains(&enums::CandlestickPattern::ThreeWhiteSoldiers)
+        );
+        assert!(
+            enums::CandlestickPattern::MorningStar.get_direction()
+                == Some(SignalDirection::Bullish)
+        );
+    }
+
+    #[test]
+    fn test_forward_return_summary() {
+        let record = datasets::structs::YahooFinancePriceRecord::from_ohlc(&[
+            (10.0, 10.5, 7.5, 8.0),
+            (8.55, 9.0, 7.0, 8.5),
+            (7.8, 10.2, 6.9, 10.0),
+            (9.5, 10.0, 8.0, 9.95),
+        ]);
+        let occurrences = detect_patterns(&record, &Default::default()).unwrap();
+        let summary = forward_return_summary(&record, &occurrences, 1).unwrap();
+        let doji = summary
+            .iter()
+            .find(|x| x.get_pattern() == enums::CandlestickPattern::Doji)
+            .unwrap();
+        assert!(doji.get_num_of_occurrences() == 1 && doji.get_hit_rate() == 1.0);
+        assert!((doji.get_mean_return() - (10.0 / 8.5 - 1.0)).abs() < 1e-6);
+        // The hammer on the last bar has no subsequent bar
+        assert!(summary
+            .iter()
+            .all(|x| x.get_pattern() != enums::CandlestickPattern::Hammer));
+        assert!(structs::PatternThresholds::new(0.4, 0.3, 0.6, 2.0, 0.1).is_err());
+    }
+}
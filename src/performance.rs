@@ -0,0 +1,457 @@
+//! Objective: Provide risk-adjusted performance statistics for a ticker over the TickerInfo date range.
+
+use std::collections::HashMap;
+
+use crate::datasets::structs::TickerInfo;
+use crate::datasets::traits::{Description, Prices, Timestamps};
+use crate::drawdowns;
+use crate::enums::BarFrequency;
+use crate::errors::CalculationError;
+use crate::returns;
+use crate::statistics;
+
+pub mod structs {
+
+    #[derive(Debug, Clone, PartialEq, serde::Serialize)]
+    pub struct PerformanceSummary {
+        pub(super) ticker: String,
+        pub(super) start_date: String,
+        pub(super) end_date: String,
+        pub(super) num_of_periods: usize,
+        pub(super) cagr: f32,
+        pub(super) annualized_volatility: f32,
+        pub(super) sharpe_ratio: f32,
+        pub(super) sortino_ratio: f32,
+        pub(super) calmar_ratio: f32,
+        pub(super) omega_ratio: f32,
+        pub(super) information_ratio: Option<f32>, // Only available when a benchmark is provided
+        pub(super) tracking_error: Option<f32>,
+        pub(super) hit_rate: f32,
+        pub(super) best_period: f32,
+        pub(super) worst_period: f32,
+        pub(super) skewness: f32,
+        pub(super) kurtosis: f32,
+    }
+
+    impl PerformanceSummary {
+        pub fn get_ticker(&self) -> &str {
+            &self.ticker
+        }
+
+        pub fn get_num_of_periods(&self) -> usize {
+            self.num_of_periods
+        }
+
+        pub fn get_cagr(&self) -> f32 {
+            self.cagr
+        }
+
+        pub fn get_annualized_volatility(&self) -> f32 {
+            self.annualized_volatility
+        }
+
+        pub fn get_sharpe_ratio(&self) -> f32 {
+            self.sharpe_ratio
+        }
+
+        pub fn get_sortino_ratio(&self) -> f32 {
+            self.sortino_ratio
+        }
+
+        pub fn get_calmar_ratio(&self) -> f32 {
+            self.calmar_ratio
+        }
+
+        pub fn get_omega_ratio(&self) -> f32 {
+            self.omega_ratio
+        }
+
+        pub fn get_information_ratio(&self) -> Option<f32> {
+            self.information_ratio
+        }
+
+        pub fn get_tracking_error(&self) -> Option<f32> {
+            self.tracking_error
+        }
+
+        pub fn get_hit_rate(&self) -> f32 {
+            self.hit_rate
+        }
+
+        pub fn get_best_period(&self) -> f32 {
+            self.best_period
+        }
+
+        pub fn get_worst_period(&self) -> f32 {
+            self.worst_period
+        }
+
+        pub fn get_skewness(&self) -> f32 {
+            self.skewness
+        }
+
+        /// Excess kurtosis of the returns (0 for a normal distribution).
+        pub fn get_kurtosis(&self) -> f32 {
+            self.kurtosis
+        }
+    }
+
+    impl std::fmt::Display for PerformanceSummary {
+        fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            let _format_optional = |x: Option<f32>| match x {
+                Some(i) => format!("{:.4}", i),
+                None => "N/A".to_string(),
+            };
+
+            writeln!(
+                formatter,
+                "Performance summary for {} ({} to {}, {} periods)",
+                self.ticker, self.start_date, self.end_date, self.num_of_periods
+            )?;
+            writeln!(formatter, "CAGR: {:.4}", self.cagr)?;
+            writeln!(
+                formatter,
+                "Annualized volatility: {:.4}",
+                self.annualized_volatility
+            )?;
+            writeln!(formatter, "Sharpe ratio: {:.4}", self.sharpe_ratio)?;
+            writeln!(formatter, "Sortino ratio: {:.4}", self.sortino_ratio)?;
+            writeln!(formatter, "Calmar ratio: {:.4}", self.calmar_ratio)?;
+            writeln!(formatter, "Omega ratio: {:.4}", self.omega_ratio)?;
+            writeln!(
+                formatter,
+                "Information ratio: {}",
+                _format_optional(self.information_ratio)
+            )?;
+            writeln!(
+                formatter,
+                "Tracking error: {}",
+                _format_optional(self.tracking_error)
+            )?;
+            writeln!(formatter, "Hit rate: {:.4}", self.hit_rate)?;
+            writeln!(formatter, "Best period: {:.4}", self.best_period)?;
+            writeln!(formatter, "Worst period: {:.4}", self.worst_period)?;
+            writeln!(formatter, "Skewness: {:.4}", self.skewness)?;
+            write!(formatter, "Excess kurtosis: {:.4}", self.kurtosis)
+        }
+    }
+}
+
+/// Index range of the timestamps falling within the start and end datetimes (inclusive). Timestamps must be sorted.
+pub(crate) fn range_indexes(
+    timestamps: &[chrono::DateTime<chrono::Utc>],
+    start_datetime: chrono::DateTime<chrono::Utc>,
+    end_datetime: chrono::DateTime<chrono::Utc>,
+) -> std::ops::Range<usize> {
+    let start = timestamps.partition_point(|x| *x < start_datetime);
+    let end = timestamps.partition_point(|x| *x <= end_datetime);
+    start..end.max(start)
+}
+
+/// Aligns two series on their common timestamps, returning the (timestamps, first values, second values).
+pub(crate) fn align_series(
+    first_timestamps: &[chrono::DateTime<chrono::Utc>],
+    first_values: &[f32],
+    second_timestamps: &[chrono::DateTime<chrono::Utc>],
+    second_values: &[f32],
+) -> (Vec<chrono::DateTime<chrono::Utc>>, Vec<f32>, Vec<f32>) {
+    let second_lookup = second_timestamps
+        .iter()
+        .zip(second_values)
+        .collect::<HashMap<_, _>>();
+
+    let mut timestamps = Vec::new();
+    let mut first = Vec::new();
+    let mut second = Vec::new();
+    for (timestamp, first_value) in first_timestamps.iter().zip(first_values) {
+        if let Some(&&second_value) = second_lookup.get(timestamp) {
+            timestamps.push(*timestamp);
+            first.push(*first_value);
+            second.push(second_value);
+        }
+    }
+    (timestamps, first, second)
+}
+
+/// Compound annual growth rate between the first and last values, based on the calendar days elapsed.
+pub fn cagr(
+    timestamps: &[chrono::DateTime<chrono::Utc>],
+    values: &[f32],
+) -> Result<f32, CalculationError> {
+    if values.len() < 2 || timestamps.len() != values.len() {
+        return Err(CalculationError::InsufficientDataError(format!(
+            "CAGR requires at least 2 aligned values, got {} timestamps and {} values.",
+            timestamps.len(),
+            values.len()
+        )));
+    }
+
+    let years = (timestamps[timestamps.len() - 1] - timestamps[0]).num_days() as f64 / 365.25;
+    let growth = values[values.len() - 1] as f64 / values[0] as f64;
+    Ok((growth.powf(1.0 / years) - 1.0) as f32)
+}
+
+/// Annualized Sharpe ratio of the returns against an annualized risk-free rate.
+pub fn sharpe_ratio(returns: &[f32], risk_free_rate: f32, frequency: BarFrequency) -> f32 {
+    let excess = excess_over_rate(returns, risk_free_rate, frequency);
+    statistics::mean(&excess) / statistics::sample_std(&excess)
+        * frequency.periods_per_year().sqrt()
+}
+
+/// Annualized Sortino ratio, penalizing only the returns falling below the risk-free rate.
+pub fn sortino_ratio(returns: &[f32], risk_free_rate: f32, frequency: BarFrequency) -> f32 {
+    let excess = excess_over_rate(returns, risk_free_rate, frequency);
+    let downside_deviation =
+        (excess.iter().map(|x| x.min(0.0).powi(2)).sum::<f32>() / excess.len() as f32).sqrt();
+    statistics::mean(&excess) / downside_deviation * frequency.periods_per_year().sqrt()
+}
+
+/// Omega ratio: probability weighted gains over losses relative to the per-period risk-free rate.
+pub fn omega_ratio(returns: &[f32], risk_free_rate: f32, frequency: BarFrequency) -> f32 {
+    let excess = excess_over_rate(returns, risk_free_rate, frequency);
+    let gains: f32 = excess.iter().map(|x| x.max(0.0)).sum();
+    let losses: f32 = excess.iter().map(|x| (-x).max(0.0)).sum();
+    gains / losses
+}
+
+/// Annualized tracking error of the returns against the benchmark returns.
+pub fn tracking_error(returns: &[f32], benchmark_returns: &[f32], frequency: BarFrequency) -> f32 {
+    let active = active_returns(returns, benchmark_returns);
+    statistics::sample_std(&active) * frequency.periods_per_year().sqrt()
+}
+
+/// Annualized active return over the tracking error.
+pub fn information_ratio(
+    returns: &[f32],
+    benchmark_returns: &[f32],
+    frequency: BarFrequency,
+) -> f32 {
+    let active = active_returns(returns, benchmark_returns);
+    statistics::mean(&active) * frequency.periods_per_year()
+        / tracking_error(returns, benchmark_returns, frequency)
+}
+
+/// Fraction of the periods with positive returns.
+pub fn hit_rate(returns: &[f32]) -> f32 {
+    returns.iter().filter(|&&x| x > 0.0).count() as f32 / returns.len() as f32
+}
+
+fn excess_over_rate(returns: &[f32], risk_free_rate: f32, frequency: BarFrequency) -> Vec<f32> {
+    let rates = vec![risk_free_rate; returns.len()];
+    returns::excess_returns(returns, &rates, frequency).unwrap() // Arrays are equal in length by construction
+}
+
+fn active_returns(returns: &[f32], benchmark_returns: &[f32]) -> Vec<f32> {
+    returns
+        .iter()
+        .zip(benchmark_returns)
+        .map(|(r, b)| r - b)
+        .collect()
+}
+
+/// Computes the performance summary of the record over the TickerInfo date range.
+/// The risk-free rate is provided as an annualized rate (e.g. 0.03 for 3%).
+pub fn performance_summary<T>(
+    record: &T,
+    ticker_info: &TickerInfo,
+    basis: returns::enums::ReturnBasis,
+    risk_free_rate: f32,
+) -> Result<structs::PerformanceSummary, CalculationError>
+where
+    T: Prices + Timestamps + Description,
+{
+    let range = range_indexes(
+        record.get_timestamps(),
+        ticker_info.get_start_datetime(),
+        ticker_info.get_end_datetime(),
+    );
+    let timestamps = &record.get_timestamps()[range.clone()];
+    let prices = &returns::get_basis_prices(record, basis)[range];
+
+    summarize(
+        record.get_ticker_symbol(),
+        timestamps,
+        prices,
+        None,
+        risk_free_rate,
+    )
+}
+
+/// Computes the performance summary of the record relative to a benchmark over the TickerInfo date range.
+/// Only the timestamps common to both records are considered.
+pub fn performance_summary_with_benchmark<T, U>(
+    record: &T,
+    benchmark: &U,
+    ticker_info: &TickerInfo,
+    basis: returns::enums::ReturnBasis,
+    risk_free_rate: f32,
+) -> Result<structs::PerformanceSummary, CalculationError>
+where
+    T: Prices + Timestamps + Description,
+    U: Prices + Timestamps,
+{
+    let (timestamps, prices, benchmark_prices) = align_series(
+        record.get_timestamps(),
+        returns::get_basis_prices(record, basis),
+        benchmark.get_timestamps(),
+        returns::get_basis_prices(benchmark, basis),
+    );
+    let range = range_indexes(
+        &timestamps,
+        ticker_info.get_start_datetime(),
+        ticker_info.get_end_datetime(),
+    );
+
+    summarize(
+        record.get_ticker_symbol(),
+        &timestamps[range.clone()],
+        &prices[range.clone()],
+        Some(&benchmark_prices[range]),
+        risk_free_rate,
+    )
+}
+
+fn summarize(
+    ticker: &str,
+    timestamps: &[chrono::DateTime<chrono::Utc>],
+    prices: &[f32],
+    benchmark_prices: Option<&[f32]>,
+    risk_free_rate: f32,
+) -> Result<structs::PerformanceSummary, CalculationError> {
+    if prices.len() < 3 {
+        return Err(CalculationError::InsufficientDataError(format!(
+            "Performance summary requires at least 3 prices within the date range, got {}.",
+            prices.len()
+        )));
+    }
+
+    let frequency = returns::detect_frequency(timestamps)?;
+    let period_returns = returns::simple_returns(prices)[1..].to_vec(); // Dropping the leading NaN
+    let cagr = cagr(timestamps, prices)?;
+    let max_drawdown = drawdowns::drawdown_series(prices)
+        .into_iter()
+        .fold(0.0_f32, f32::min);
+
+    let (information_ratio, tracking_error) = match benchmark_prices {
+        Some(benchmark_prices) => {
+            let benchmark_returns = &returns::simple_returns(benchmark_prices)[1..];
+            (
+                Some(information_ratio(
+                    &period_returns,
+                    benchmark_returns,
+                    frequency,
+                )),
+                Some(tracking_error(
+                    &period_returns,
+                    benchmark_returns,
+                    frequency,
+                )),
+            )
+        }
+        None => (None, None),
+    };
+
+    Ok(structs::PerformanceSummary {
+        ticker: ticker.to_string(),
+        start_date: timestamps[0].format("%Y-%m-%d").to_string(),
+        end_date: timestamps[timestamps.len() - 1]
+            .format("%Y-%m-%d")
+            .to_string(),
+        num_of_periods: period_returns.len(),
+        cagr,
+        annualized_volatility: returns::annualize_volatility(
+            statistics::sample_std(&period_returns),
+            frequency,
+        ),
+        sharpe_ratio: sharpe_ratio(&period_returns, risk_free_rate, frequency),
+        sortino_ratio: sortino_ratio(&period_returns, risk_free_rate, frequency),
+        calmar_ratio: cagr / max_drawdown.abs(),
+        omega_ratio: omega_ratio(&period_returns, risk_free_rate, frequency),
+        information_ratio,
+        tracking_error,
+        hit_rate: hit_rate(&period_returns),
+        best_period: period_returns.iter().copied().fold(f32::MIN, f32::max),
+        worst_period: period_returns.iter().copied().fold(f32::MAX, f32::min),
+        skewness: statistics::skewness(&period_returns),
+        kurtosis: statistics::excess_kurtosis(&period_returns),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datasets;
+    use crate::enums::Currency;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_ratios() {
+        let returns = [0.01, -0.02, 0.03, 0.01];
+        assert!((hit_rate(&returns) - 0.75).abs() < 1e-6);
+        assert!((omega_ratio(&returns, 0.0, BarFrequency::Daily) - 2.5).abs() < 1e-5);
+
+        let expected_sharpe =
+            statistics::mean(&returns) / statistics::sample_std(&returns) * 252.0_f32.sqrt();
+        assert!((sharpe_ratio(&returns, 0.0, BarFrequency::Daily) - expected_sharpe).abs() < 1e-4);
+
+        let downside = (0.0004_f32 / 4.0).sqrt();
+        let expected_sortino = statistics::mean(&returns) / downside * 252.0_f32.sqrt();
+        assert!(
+            (sortino_ratio(&returns, 0.0, BarFrequency::Daily) - expected_sortino).abs() < 1e-3
+        );
+    }
+
+    #[test]
+    fn test_performance_summary() {
+        let record = datasets::structs::YahooFinancePriceRecord::from_closes(&[
+            100.0, 102.0, 99.0, 104.0, 103.0, 107.0,
+        ])
+        .with_ticker_symbol("TEST");
+        let benchmark = datasets::structs::YahooFinancePriceRecord::from_closes(&[
+            50.0, 50.5, 50.0, 51.0, 51.5, 52.0,
+        ])
+        .with_ticker_symbol("BENCH");
+        let ticker_info =
+            TickerInfo::new("TEST", "2022-01-04", "2022-01-08", Currency::Usd).unwrap();
+
+        let summary = performance_summary(
+            &record,
+            &ticker_info,
+            returns::enums::ReturnBasis::Close,
+            0.0,
+        )
+        .unwrap();
+        assert!(summary.get_num_of_periods() == 4);
+        assert!(summary.get_information_ratio().is_none());
+        assert!((summary.get_best_period() - 0.05050505).abs() < 1e-5);
+        assert!((summary.get_worst_period() + 0.02941176).abs() < 1e-5);
+
+        let summary = performance_summary_with_benchmark(
+            &record,
+            &benchmark,
+            &ticker_info,
+            returns::enums::ReturnBasis::Close,
+            0.0,
+        )
+        .unwrap();
+        assert!(summary.get_tracking_error().is_some());
+        assert!(format!("{}", summary).contains("Performance summary for TEST"));
+    }
+
+    #[test]
+    fn test_align_series() {
+        let first_timestamps = (0..4)
+            .map(|x| chrono::Utc.ymd(2022, 1, 3).and_hms(0, 0, 0) + chrono::Duration::days(x))
+            .collect::<Vec<_>>();
+        let second_timestamps = vec![first_timestamps[1], first_timestamps[3]];
+        let (timestamps, first, second) = align_series(
+            &first_timestamps,
+            &[1.0, 2.0, 3.0, 4.0],
+            &second_timestamps,
+            &[20.0, 40.0],
+        );
+        assert!(timestamps == second_timestamps);
+        assert!(first == vec![2.0, 4.0]);
+        assert!(second == vec![20.0, 40.0]);
+    }
+}
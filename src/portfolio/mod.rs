@@ -0,0 +1,528 @@
+//! Objective: Simulate a portfolio of several tickers held at target weights, aligned on the dates common to all
+//! the records, with periodic or threshold rebalancing, interest on the cash held and dividend reinvestment.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::datasets::traits::{Description, Prices, Timestamps};
+use crate::errors::CalculationError;
+use crate::functions::Grouping;
+use crate::inputs::enums::AggregationPeriod;
+use crate::returns;
+
+pub mod optimization;
+
+pub mod enums {
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum RebalanceRule {
+        Never,                       // Buy and hold from the initial allocation
+        Periodic(AggregationPeriod), // Rebalanced on the first common date of every period
+        /// Rebalanced whenever any weight drifts from its target by more than the absolute threshold
+        /// (e.g. 0.05 for 5 percentage points).
+        Threshold(f32),
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum DividendTreatment {
+        Reinvest,     // Buys more shares of the paying ticker at the close of the ex-date
+        AccrueToCash, // Held as cash until the next rebalance
+    }
+}
+
+pub mod structs {
+    use super::*;
+
+    /// Cash dividend per share, paid to the shares held at the close before the ex-date.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct Dividend {
+        pub(super) ex_date: chrono::DateTime<chrono::Utc>,
+        pub(super) amount: f32,
+    }
+
+    impl Dividend {
+        pub fn new(ex_date: chrono::DateTime<chrono::Utc>, amount: f32) -> Self {
+            Dividend { ex_date, amount }
+        }
+
+        pub fn get_ex_date(&self) -> chrono::DateTime<chrono::Utc> {
+            self.ex_date
+        }
+
+        pub fn get_amount(&self) -> f32 {
+            self.amount
+        }
+    }
+
+    /// Target weights of the tickers, in the order of the records. Weights summing to less than 1 leave the
+    /// remainder in cash, which accrues interest at the annual cash rate.
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct PortfolioConfig {
+        pub(super) target_weights: Vec<f32>,
+        pub(super) initial_value: f32,
+        pub(super) rebalance_rule: enums::RebalanceRule,
+        pub(super) cash_rate: f32,
+        pub(super) dividend_treatment: enums::DividendTreatment,
+        pub(super) price_basis: returns::enums::ReturnBasis,
+    }
+
+    impl PortfolioConfig {
+        pub fn get_target_weights(&self) -> &[f32] {
+            &self.target_weights
+        }
+
+        pub fn get_initial_value(&self) -> f32 {
+            self.initial_value
+        }
+
+        pub fn get_rebalance_rule(&self) -> enums::RebalanceRule {
+            self.rebalance_rule
+        }
+
+        pub fn get_cash_rate(&self) -> f32 {
+            self.cash_rate
+        }
+
+        pub fn get_dividend_treatment(&self) -> enums::DividendTreatment {
+            self.dividend_treatment
+        }
+
+        pub fn get_price_basis(&self) -> returns::enums::ReturnBasis {
+            self.price_basis
+        }
+    }
+
+    /// Simulated portfolio over the common dates. The weights of each date are those drifted into at the close,
+    /// before any rebalancing on that date, except on the first date where they are the initial allocation.
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct PortfolioResult {
+        pub(super) tickers: Vec<String>,
+        pub(super) target_weights: Vec<f32>,
+        pub(super) timestamps: Vec<chrono::DateTime<chrono::Utc>>,
+        pub(super) equity_curve: Vec<f32>,
+        pub(super) weights: Vec<Vec<f32>>, // Element t: weights of the tickers on date t
+        pub(super) cash_weights: Vec<f32>,
+        pub(super) rebalance_indexes: Vec<usize>,
+        pub(super) dividends_received: f32,
+    }
+
+    impl PortfolioResult {
+        pub fn get_tickers(&self) -> &[String] {
+            &self.tickers
+        }
+
+        pub fn get_timestamps(&self) -> &[chrono::DateTime<chrono::Utc>] {
+            &self.timestamps
+        }
+
+        pub fn get_equity_curve(&self) -> &[f32] {
+            &self.equity_curve
+        }
+
+        pub fn get_weights(&self) -> &[Vec<f32>] {
+            &self.weights
+        }
+
+        pub fn get_cash_weights(&self) -> &[f32] {
+            &self.cash_weights
+        }
+
+        /// Indexes of the dates the portfolio was rebalanced on, including the initial allocation.
+        pub fn get_rebalance_indexes(&self) -> &[usize] {
+            &self.rebalance_indexes
+        }
+
+        pub fn get_dividends_received(&self) -> f32 {
+            self.dividends_received
+        }
+
+        /// Largest absolute deviation of any weight from its target, for each date.
+        pub fn get_max_weight_deviations(&self) -> Vec<f32> {
+            self.weights
+                .iter()
+                .map(|weights| {
+                    weights
+                        .iter()
+                        .zip(&self.target_weights)
+                        .map(|(weight, target)| (weight - target).abs())
+                        .fold(0.0, f32::max)
+                })
+                .collect()
+        }
+
+        /// Average fraction of the portfolio held in cash, i.e. the cash drag on the invested returns.
+        pub fn get_mean_cash_weight(&self) -> f32 {
+            self.cash_weights.iter().sum::<f32>() / self.cash_weights.len() as f32
+        }
+
+        pub fn get_total_return(&self) -> f32 {
+            self.equity_curve[self.equity_curve.len() - 1] / self.equity_curve[0] - 1.0
+        }
+    }
+}
+
+impl structs::PortfolioConfig {
+    /// Portfolio held at the target weights from the initial allocation without rebalancing, with no interest on
+    /// cash, dividends reinvested, and valued at the close prices.
+    pub fn new(target_weights: &[f32], initial_value: f32) -> Result<Self, CalculationError> {
+        if target_weights.is_empty()
+            || target_weights.iter().any(|x| x.is_nan() || *x < 0.0)
+            || target_weights.iter().sum::<f32>() > 1.0 + 1e-6
+        {
+            return Err(CalculationError::InvalidParameterError(format!(
+                "Target weights must be non-negative and sum to at most 1. Weights provided: {:?}",
+                target_weights
+            )));
+        }
+        if initial_value.is_nan() || initial_value <= 0.0 {
+            return Err(CalculationError::InvalidParameterError(format!(
+                "Initial portfolio value must be positive. Value provided: {}",
+                initial_value
+            )));
+        }
+        Ok(structs::PortfolioConfig {
+            target_weights: target_weights.to_vec(),
+            initial_value,
+            rebalance_rule: enums::RebalanceRule::Never,
+            cash_rate: 0.0,
+            dividend_treatment: enums::DividendTreatment::Reinvest,
+            price_basis: returns::enums::ReturnBasis::Close,
+        })
+    }
+
+    pub fn with_rebalance_rule(
+        mut self,
+        rebalance_rule: enums::RebalanceRule,
+    ) -> Result<Self, CalculationError> {
+        if let enums::RebalanceRule::Threshold(threshold) = rebalance_rule {
+            if threshold.is_nan() || threshold <= 0.0 {
+                return Err(CalculationError::InvalidParameterError(format!(
+                    "Rebalancing threshold must be positive. Threshold provided: {}",
+                    threshold
+                )));
+            }
+        }
+        self.rebalance_rule = rebalance_rule;
+        Ok(self)
+    }
+
+    /// Annual interest rate earned on the cash held, compounded over the calendar days between dates.
+    pub fn with_cash_rate(mut self, cash_rate: f32) -> Result<Self, CalculationError> {
+        if cash_rate.is_nan() || cash_rate <= -1.0 {
+            return Err(CalculationError::InvalidParameterError(format!(
+                "Cash rate must be greater than -1. Rate provided: {}",
+                cash_rate
+            )));
+        }
+        self.cash_rate = cash_rate;
+        Ok(self)
+    }
+
+    pub fn with_dividend_treatment(mut self, dividend_treatment: enums::DividendTreatment) -> Self {
+        self.dividend_treatment = dividend_treatment;
+        self
+    }
+
+    /// Prices the holdings are valued and traded at. Adjusted close prices already include the dividends, hence
+    /// dividend schedules should not be provided alongside them.
+    pub fn with_price_basis(mut self, price_basis: returns::enums::ReturnBasis) -> Self {
+        self.price_basis = price_basis;
+        self
+    }
+}
+
+// Common dates, and the prices of each record on them
+type AlignedPrices = (Vec<chrono::DateTime<chrono::Utc>>, Vec<Vec<f32>>);
+
+/// Dates common to all the records, in the order of the first record, with the prices of each record on them.
+fn align_records<T>(
+    records: &[&T],
+    basis: returns::enums::ReturnBasis,
+) -> Result<AlignedPrices, CalculationError>
+where
+    T: Prices + Timestamps + Description,
+{
+    let mut lookups = Vec::with_capacity(records.len());
+    for record in records {
+        let prices = returns::get_basis_prices(*record, basis);
+        if prices.len() != record.get_timestamps().len() {
+            return Err(CalculationError::InconsistentLengthError(format!(
+                "Length of the timestamps array of {}: {} \n Length of the prices array: {}",
+                record.get_ticker_symbol(),
+                record.get_timestamps().len(),
+                prices.len()
+            )));
+        }
+        lookups.push(
+            record
+                .get_timestamps()
+                .iter()
+                .zip(prices)
+                .collect::<HashMap<_, _>>(),
+        );
+    }
+
+    let common =
+        lookups[1..]
+            .iter()
+            .fold(lookups[0].keys().collect::<HashSet<_>>(), |acc, lookup| {
+                acc.into_iter()
+                    .filter(|x| lookup.contains_key(*x))
+                    .collect()
+            });
+    let timestamps = records[0]
+        .get_timestamps()
+        .iter()
+        .filter(|x| common.contains(x))
+        .copied()
+        .collect::<Vec<_>>();
+    let prices = lookups
+        .iter()
+        .map(|lookup| timestamps.iter().map(|x| *lookup[x]).collect())
+        .collect();
+    Ok((timestamps, prices))
+}
+
+/// Simulates the portfolio over the dates common to all the records, allocating to the target weights at the
+/// first close. Dividend schedules are either empty or provided for every record, in the order of the records.
+/// Trades are in fractional shares without transaction costs, at the close of the rebalancing date.
+pub fn simulate_portfolio<T>(
+    records: &[&T],
+    dividends: &[Vec<structs::Dividend>],
+    config: &structs::PortfolioConfig,
+) -> Result<structs::PortfolioResult, CalculationError>
+where
+    T: Prices + Timestamps + Description,
+{
+    let num_of_assets = records.len();
+    if num_of_assets != config.target_weights.len() {
+        return Err(CalculationError::InconsistentLengthError(format!(
+            "Number of records: {} \n Number of target weights: {}",
+            num_of_assets,
+            config.target_weights.len()
+        )));
+    }
+    if !dividends.is_empty() && dividends.len() != num_of_assets {
+        return Err(CalculationError::InconsistentLengthError(format!(
+            "Number of records: {} \n Number of dividend schedules: {}",
+            num_of_assets,
+            dividends.len()
+        )));
+    }
+
+    let (timestamps, prices) = align_records(records, config.price_basis)?;
+    let length = timestamps.len();
+    if length < 2 {
+        return Err(CalculationError::InsufficientDataError(format!(
+            "At least 2 dates common to all the records are required, got {}.",
+            length
+        )));
+    }
+    if prices.iter().flatten().any(|x| x.is_nan() || *x <= 0.0) {
+        return Err(CalculationError::InvalidParameterError(
+            "Prices on the common dates must be positive.".to_string(),
+        ));
+    }
+
+    // Dividends going ex on or before the first date are not received
+    let schedules = (0..num_of_assets)
+        .map(|idx| {
+            let mut schedule = dividends
+                .get(idx)
+                .map(|x| {
+                    x.iter()
+                        .filter(|dividend| dividend.ex_date > timestamps[0])
+                        .copied()
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default();
+            schedule.sort_by_key(|dividend| dividend.ex_date);
+            schedule
+        })
+        .collect::<Vec<_>>();
+    let mut next_dividends = vec![0; num_of_assets];
+
+    let mut result = structs::PortfolioResult {
+        tickers: records
+            .iter()
+            .map(|record| record.get_ticker_symbol().to_owned())
+            .collect(),
+        target_weights: config.target_weights.clone(),
+        timestamps: timestamps.clone(),
+        equity_curve: Vec::with_capacity(length),
+        weights: Vec::with_capacity(length),
+        cash_weights: Vec::with_capacity(length),
+        rebalance_indexes: Vec::new(),
+        dividends_received: 0.0,
+    };
+    let mut shares = vec![0.0_f32; num_of_assets];
+    let mut cash = config.initial_value;
+
+    for idx in 0..length {
+        if idx > 0 {
+            let years = (timestamps[idx] - timestamps[idx - 1]).num_days() as f32 / 365.25;
+            cash *= (1.0 + config.cash_rate).powf(years);
+        }
+
+        for asset in 0..num_of_assets {
+            while let Some(dividend) = schedules[asset].get(next_dividends[asset]) {
+                if dividend.ex_date > timestamps[idx] {
+                    break;
+                }
+                let amount = shares[asset] * dividend.amount;
+                result.dividends_received += amount;
+                match config.dividend_treatment {
+                    enums::DividendTreatment::Reinvest => {
+                        shares[asset] += amount / prices[asset][idx]
+                    }
+                    enums::DividendTreatment::AccrueToCash => cash += amount,
+                }
+                next_dividends[asset] += 1;
+            }
+        }
+
+        let equity = cash
+            + (0..num_of_assets)
+                .map(|asset| shares[asset] * prices[asset][idx])
+                .sum::<f32>();
+        let weights = (0..num_of_assets)
+            .map(|asset| shares[asset] * prices[asset][idx] / equity)
+            .collect::<Vec<f32>>();
+
+        let is_rebalance = idx == 0
+            || match config.rebalance_rule {
+                enums::RebalanceRule::Never => false,
+                enums::RebalanceRule::Periodic(period) => {
+                    Grouping::period_start(&timestamps[idx], period)
+                        != Grouping::period_start(&timestamps[idx - 1], period)
+                }
+                enums::RebalanceRule::Threshold(threshold) => weights
+                    .iter()
+                    .zip(&config.target_weights)
+                    .any(|(weight, target)| (weight - target).abs() > threshold),
+            };
+        if is_rebalance {
+            for asset in 0..num_of_assets {
+                shares[asset] = config.target_weights[asset] * equity / prices[asset][idx];
+            }
+            cash = equity * (1.0 - config.target_weights.iter().sum::<f32>());
+            result.rebalance_indexes.push(idx);
+        }
+
+        if idx == 0 {
+            result.weights.push(config.target_weights.clone());
+            result.cash_weights.push(cash / equity);
+        } else {
+            result.cash_weights.push(1.0 - weights.iter().sum::<f32>());
+            result.weights.push(weights);
+        }
+        result.equity_curve.push(equity);
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datasets::structs::YahooFinancePriceRecord;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_buy_and_hold() {
+        // Dates from 27 Jan to 3 Feb, with the second ticker starting a day later
+        let first =
+            YahooFinancePriceRecord::from_closes(&[10.0, 11.0, 12.0, 13.0, 14.0, 15.0, 16.0, 17.0])
+                .with_ticker_symbol("FIRST")
+                .with_start_datetime(chrono::Utc.ymd(2022, 1, 27).and_hms(0, 0, 0));
+        let second =
+            YahooFinancePriceRecord::from_closes(&[20.0, 20.0, 20.0, 20.0, 20.0, 20.0, 20.0, 20.0])
+                .with_ticker_symbol("SECOND")
+                .with_start_datetime(chrono::Utc.ymd(2022, 1, 28).and_hms(0, 0, 0));
+        let config = structs::PortfolioConfig::new(&[0.5, 0.4], 1000.0).unwrap();
+        let result = simulate_portfolio(&[&first, &second], &[], &config).unwrap();
+
+        assert!(result.get_timestamps().len() == 7);
+        assert!(result.get_timestamps()[0] == chrono::Utc.ymd(2022, 1, 28).and_hms(0, 0, 0));
+        // 500 / 11 shares of the first ticker, 20 shares of the second and 100 in cash
+        assert!(
+            (result.get_equity_curve()[6] - (500.0 / 11.0 * 17.0 + 400.0 + 100.0)).abs() < 1e-3
+        );
+        assert!(result.get_rebalance_indexes() == [0]);
+        assert!((result.get_cash_weights()[0] - 0.1).abs() < 1e-6);
+        assert!(
+            (result.get_weights()[6][0] - 500.0 / 11.0 * 17.0 / result.get_equity_curve()[6]).abs()
+                < 1e-6
+        );
+        assert!(result.get_max_weight_deviations()[0] == 0.0);
+        assert!(result.get_max_weight_deviations()[6] > 0.1);
+    }
+
+    #[test]
+    fn test_rebalancing() {
+        let first =
+            YahooFinancePriceRecord::from_closes(&[10.0, 11.0, 12.0, 13.0, 14.0, 15.0, 16.0, 17.0])
+                .with_ticker_symbol("FIRST")
+                .with_start_datetime(chrono::Utc.ymd(2022, 1, 27).and_hms(0, 0, 0));
+        let second = YahooFinancePriceRecord::from_closes(&[20.0; 8])
+            .with_ticker_symbol("SECOND")
+            .with_start_datetime(chrono::Utc.ymd(2022, 1, 27).and_hms(0, 0, 0));
+
+        // Rebalanced on the first date of February, i.e. 1 Feb
+        let monthly = structs::PortfolioConfig::new(&[0.5, 0.5], 1000.0)
+            .unwrap()
+            .with_rebalance_rule(enums::RebalanceRule::Periodic(AggregationPeriod::Monthly))
+            .unwrap();
+        let result = simulate_portfolio(&[&first, &second], &[], &monthly).unwrap();
+        assert!(result.get_rebalance_indexes() == [0, 5]);
+        let equity = result.get_equity_curve();
+        assert!((equity[5] - (50.0 * 15.0 + 500.0)).abs() < 1e-3);
+        assert!((equity[7] - (equity[5] / 2.0 / 15.0 * 17.0 + equity[5] / 2.0)).abs() < 1e-3);
+
+        // Weight of the first ticker drifts past 0.55 once its price reaches 13
+        let threshold = structs::PortfolioConfig::new(&[0.5, 0.5], 1000.0)
+            .unwrap()
+            .with_rebalance_rule(enums::RebalanceRule::Threshold(0.05))
+            .unwrap();
+        let result = simulate_portfolio(&[&first, &second], &[], &threshold).unwrap();
+        assert!(result.get_rebalance_indexes()[1] == 3);
+        assert!(structs::PortfolioConfig::new(&[0.6, 0.5], 1000.0).is_err());
+        assert!(simulate_portfolio(&[&first], &[], &threshold).is_err());
+    }
+
+    #[test]
+    fn test_dividends_and_cash() {
+        let record = YahooFinancePriceRecord::from_closes(&[10.0; 5])
+            .with_ticker_symbol("TEST")
+            .with_start_datetime(chrono::Utc.ymd(2022, 1, 27).and_hms(0, 0, 0));
+        let dividends = vec![vec![structs::Dividend::new(
+            chrono::Utc.ymd(2022, 1, 29).and_hms(0, 0, 0),
+            1.0,
+        )]];
+
+        // 100 shares receiving 100, reinvested into 10 more shares
+        let config = structs::PortfolioConfig::new(&[1.0], 1000.0).unwrap();
+        let result = simulate_portfolio(&[&record], &dividends, &config).unwrap();
+        assert!(result.get_dividends_received() == 100.0);
+        assert!(result.get_equity_curve()[4] == 1100.0 && result.get_cash_weights()[4] == 0.0);
+
+        let accrued = config
+            .clone()
+            .with_dividend_treatment(enums::DividendTreatment::AccrueToCash);
+        let result = simulate_portfolio(&[&record], &dividends, &accrued).unwrap();
+        assert!(result.get_equity_curve()[4] == 1100.0);
+        assert!((result.get_cash_weights()[4] - 100.0 / 1100.0).abs() < 1e-6);
+
+        // Half in cash at 10% a year over 4 days
+        let cash_config = structs::PortfolioConfig::new(&[0.5], 1000.0)
+            .unwrap()
+            .with_cash_rate(0.1)
+            .unwrap();
+        let result = simulate_portfolio(&[&record], &[], &cash_config).unwrap();
+        assert!(
+            (result.get_equity_curve()[4] - (500.0 + 500.0 * 1.1_f32.powf(4.0 / 365.25))).abs()
+                < 1e-3
+        );
+        assert!((result.get_mean_cash_weight() - 0.5).abs() < 1e-3);
+    }
+}
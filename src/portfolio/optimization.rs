@@ -0,0 +1,845 @@
+//! Estimation of expected returns and covariances over several tickers, and the portfolio weights optimizing them.
+//! Optimizations are solved by projected gradient descent onto the fully invested weights within their bounds.
+
+use super::align_records;
+use crate::datasets::traits::{Description, Prices, Timestamps};
+use crate::errors::CalculationError;
+use crate::returns;
+use crate::statistics;
+
+// Iterations and tolerances of the numerical solvers
+const MAX_ITERATIONS: usize = 20000;
+const CONVERGENCE_TOLERANCE: f64 = 1e-12;
+const PROJECTION_ITERATIONS: usize = 100;
+// Risk aversions of the efficient frontier and maximum Sharpe searches, as powers of 10
+const MIN_LOG_RISK_AVERSION: f64 = -2.0;
+const MAX_LOG_RISK_AVERSION: f64 = 4.0;
+
+pub mod enums {
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum CovarianceEstimator {
+        Sample,
+        /// Ledoit-Wolf shrinkage of the sample covariance towards a scaled identity matrix, with the intensity
+        /// estimated from the returns.
+        LedoitWolf,
+    }
+}
+
+pub mod structs {
+
+    /// Annualized expected returns and covariances of the tickers' returns over their common dates.
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct ReturnEstimates {
+        pub(super) tickers: Vec<String>,
+        pub(super) expected_returns: Vec<f32>,
+        pub(super) covariance: Vec<Vec<f32>>,
+        pub(super) shrinkage_intensity: f32, // 0 for the sample covariance
+    }
+
+    impl ReturnEstimates {
+        pub fn get_tickers(&self) -> &[String] {
+            &self.tickers
+        }
+
+        pub fn get_expected_returns(&self) -> &[f32] {
+            &self.expected_returns
+        }
+
+        pub fn get_covariance(&self) -> &[Vec<f32>] {
+            &self.covariance
+        }
+
+        pub fn get_shrinkage_intensity(&self) -> f32 {
+            self.shrinkage_intensity
+        }
+    }
+
+    /// Lower and upper bounds of each weight. Long-only portfolios have lower bounds of 0.
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct WeightBounds {
+        pub(super) lower: Vec<f32>,
+        pub(super) upper: Vec<f32>,
+    }
+
+    impl WeightBounds {
+        pub fn get_lower(&self) -> &[f32] {
+            &self.lower
+        }
+
+        pub fn get_upper(&self) -> &[f32] {
+            &self.upper
+        }
+    }
+
+    /// Fully invested portfolio weights, with their annualized expected return and volatility.
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct PortfolioWeights {
+        pub(super) tickers: Vec<String>,
+        pub(super) weights: Vec<f32>,
+        pub(super) expected_return: f32,
+        pub(super) volatility: f32,
+    }
+
+    impl PortfolioWeights {
+        pub fn get_tickers(&self) -> &[String] {
+            &self.tickers
+        }
+
+        pub fn get_weights(&self) -> &[f32] {
+            &self.weights
+        }
+
+        pub fn get_expected_return(&self) -> f32 {
+            self.expected_return
+        }
+
+        pub fn get_volatility(&self) -> f32 {
+            self.volatility
+        }
+
+        pub fn get_sharpe_ratio(&self, risk_free_rate: f32) -> f32 {
+            (self.expected_return - risk_free_rate) / self.volatility
+        }
+    }
+}
+
+impl structs::WeightBounds {
+    pub fn new(lower: &[f32], upper: &[f32]) -> Result<Self, CalculationError> {
+        if lower.len() != upper.len() {
+            return Err(CalculationError::InconsistentLengthError(format!(
+                "Length of the lower bounds array: {} \n Length of the upper bounds array: {}",
+                lower.len(),
+                upper.len()
+            )));
+        }
+        if lower
+            .iter()
+            .zip(upper)
+            .any(|(low, high)| low.is_nan() || high.is_nan() || low > high)
+            || lower.iter().sum::<f32>() > 1.0 + 1e-6
+            || upper.iter().sum::<f32>() < 1.0 - 1e-6
+        {
+            return Err(CalculationError::InvalidParameterError(format!(
+                "Weight bounds must admit a fully invested portfolio. Lower bounds provided: {:?} \n Upper bounds provided: {:?}",
+                lower, upper
+            )));
+        }
+        Ok(structs::WeightBounds {
+            lower: lower.to_vec(),
+            upper: upper.to_vec(),
+        })
+    }
+
+    /// Long-only weights of at most the maximum weight each.
+    pub fn long_only(num_of_assets: usize, max_weight: f32) -> Result<Self, CalculationError> {
+        structs::WeightBounds::new(&vec![0.0; num_of_assets], &vec![max_weight; num_of_assets])
+    }
+}
+
+/// Estimates the expected returns and covariances from the simple returns of the records over their common dates,
+/// annualized at the bar frequency detected from the dates.
+pub fn estimate_returns<T>(
+    records: &[&T],
+    basis: returns::enums::ReturnBasis,
+    estimator: enums::CovarianceEstimator,
+) -> Result<structs::ReturnEstimates, CalculationError>
+where
+    T: Prices + Timestamps + Description,
+{
+    if records.len() < 2 {
+        return Err(CalculationError::InsufficientDataError(format!(
+            "At least 2 records are required, got {}.",
+            records.len()
+        )));
+    }
+    let (timestamps, prices) = align_records(records, basis)?;
+    if timestamps.len() < 3 {
+        return Err(CalculationError::InsufficientDataError(format!(
+            "At least 3 dates common to all the records are required, got {}.",
+            timestamps.len()
+        )));
+    }
+    let periods_per_year = returns::detect_frequency(&timestamps)?.periods_per_year() as f64;
+    let asset_returns = prices
+        .iter()
+        .map(|x| returns::simple_returns(x)[1..].to_vec())
+        .collect::<Vec<Vec<f32>>>();
+    if asset_returns.iter().flatten().any(|x| !x.is_finite()) {
+        return Err(CalculationError::InvalidParameterError(
+            "Prices on the common dates must be positive.".to_string(),
+        ));
+    }
+
+    let (covariance, shrinkage_intensity) = match estimator {
+        enums::CovarianceEstimator::Sample => (sample_covariance_matrix(&asset_returns), 0.0),
+        enums::CovarianceEstimator::LedoitWolf => ledoit_wolf(&asset_returns),
+    };
+    Ok(structs::ReturnEstimates {
+        tickers: records
+            .iter()
+            .map(|record| record.get_ticker_symbol().to_owned())
+            .collect(),
+        expected_returns: asset_returns
+            .iter()
+            .map(|x| (statistics::mean(x) as f64 * periods_per_year) as f32)
+            .collect(),
+        covariance: covariance
+            .iter()
+            .map(|row| row.iter().map(|x| (x * periods_per_year) as f32).collect())
+            .collect(),
+        shrinkage_intensity: shrinkage_intensity as f32,
+    })
+}
+
+fn sample_covariance_matrix(asset_returns: &[Vec<f32>]) -> Vec<Vec<f64>> {
+    asset_returns
+        .iter()
+        .map(|first| {
+            asset_returns
+                .iter()
+                .map(|second| statistics::sample_covariance(first, second) as f64)
+                .collect()
+        })
+        .collect()
+}
+
+/// Ledoit and Wolf (2004) shrinkage of the maximum likelihood covariance towards the scaled identity matrix,
+/// returning the shrunk covariance and the shrinkage intensity.
+fn ledoit_wolf(asset_returns: &[Vec<f32>]) -> (Vec<Vec<f64>>, f64) {
+    let num_of_assets = asset_returns.len();
+    let num_of_observations = asset_returns[0].len();
+    let demeaned = asset_returns
+        .iter()
+        .map(|x| {
+            let mean = statistics::mean(x) as f64;
+            x.iter().map(|value| *value as f64 - mean).collect()
+        })
+        .collect::<Vec<Vec<f64>>>();
+    let covariance = (0..num_of_assets)
+        .map(|i| {
+            (0..num_of_assets)
+                .map(|j| {
+                    (0..num_of_observations)
+                        .map(|t| demeaned[i][t] * demeaned[j][t])
+                        .sum::<f64>()
+                        / num_of_observations as f64
+                })
+                .collect()
+        })
+        .collect::<Vec<Vec<f64>>>();
+
+    // Squared Frobenius norms are normalized by the number of assets
+    let scale = (0..num_of_assets).map(|i| covariance[i][i]).sum::<f64>() / num_of_assets as f64;
+    let dispersion = (0..num_of_assets)
+        .flat_map(|i| (0..num_of_assets).map(move |j| (i, j)))
+        .map(|(i, j)| {
+            let target = if i == j { scale } else { 0.0 };
+            (covariance[i][j] - target).powi(2)
+        })
+        .sum::<f64>()
+        / num_of_assets as f64;
+    let estimation_error = (0..num_of_observations)
+        .map(|t| {
+            (0..num_of_assets)
+                .flat_map(|i| (0..num_of_assets).map(move |j| (i, j)))
+                .map(|(i, j)| (demeaned[i][t] * demeaned[j][t] - covariance[i][j]).powi(2))
+                .sum::<f64>()
+                / num_of_assets as f64
+        })
+        .sum::<f64>()
+        / (num_of_observations as f64).powi(2);
+    let intensity = if dispersion > 0.0 {
+        estimation_error.min(dispersion) / dispersion
+    } else {
+        1.0
+    };
+
+    let shrunk = (0..num_of_assets)
+        .map(|i| {
+            (0..num_of_assets)
+                .map(|j| {
+                    let target = if i == j { scale } else { 0.0 };
+                    intensity * target + (1.0 - intensity) * covariance[i][j]
+                })
+                .collect()
+        })
+        .collect();
+    (shrunk, intensity)
+}
+
+/// Estimates and bounds converted for the solvers.
+struct Problem {
+    expected_returns: Vec<f64>,
+    covariance: Vec<Vec<f64>>,
+    lower: Vec<f64>,
+    upper: Vec<f64>,
+}
+
+impl Problem {
+    fn new(
+        estimates: &structs::ReturnEstimates,
+        bounds: &structs::WeightBounds,
+    ) -> Result<Self, CalculationError> {
+        if bounds.lower.len() != estimates.expected_returns.len() {
+            return Err(CalculationError::InconsistentLengthError(format!(
+                "Number of tickers: {} \n Number of weight bounds: {}",
+                estimates.expected_returns.len(),
+                bounds.lower.len()
+            )));
+        }
+        let to_f64 = |x: &[f32]| x.iter().map(|value| *value as f64).collect::<Vec<f64>>();
+        Ok(Problem {
+            expected_returns: to_f64(&estimates.expected_returns),
+            covariance: estimates.covariance.iter().map(|row| to_f64(row)).collect(),
+            lower: to_f64(&bounds.lower),
+            upper: to_f64(&bounds.upper),
+        })
+    }
+
+    fn covariance_product(&self, weights: &[f64]) -> Vec<f64> {
+        self.covariance
+            .iter()
+            .map(|row| row.iter().zip(weights).map(|(x, w)| x * w).sum())
+            .collect()
+    }
+
+    fn variance(&self, weights: &[f64]) -> f64 {
+        self.covariance_product(weights)
+            .iter()
+            .zip(weights)
+            .map(|(x, w)| x * w)
+            .sum()
+    }
+
+    fn expected_return(&self, weights: &[f64]) -> f64 {
+        self.expected_returns
+            .iter()
+            .zip(weights)
+            .map(|(x, w)| x * w)
+            .sum()
+    }
+
+    /// Euclidean projection onto the weights summing to 1 within their bounds, i.e. the weights clipped to their
+    /// bounds after shifting by the constant found by bisection.
+    fn project(&self, weights: &[f64]) -> Vec<f64> {
+        let shifted = |shift: f64| {
+            weights
+                .iter()
+                .zip(self.lower.iter().zip(&self.upper))
+                .map(|(w, (low, high))| (w - shift).max(*low).min(*high))
+                .collect::<Vec<f64>>()
+        };
+        // The sum of the shifted weights decreases in the shift
+        let mut low_shift = weights
+            .iter()
+            .zip(&self.upper)
+            .map(|(w, high)| w - high)
+            .fold(f64::INFINITY, f64::min);
+        let mut high_shift = weights
+            .iter()
+            .zip(&self.lower)
+            .map(|(w, low)| w - low)
+            .fold(f64::NEG_INFINITY, f64::max);
+        for _ in 0..PROJECTION_ITERATIONS {
+            let shift = (low_shift + high_shift) / 2.0;
+            if shifted(shift).iter().sum::<f64>() > 1.0 {
+                low_shift = shift;
+            } else {
+                high_shift = shift;
+            }
+        }
+        shifted((low_shift + high_shift) / 2.0)
+    }
+
+    /// Maximizes the expected return less half the risk aversion times the variance by projected gradient descent.
+    /// A risk aversion of infinity minimizes the variance.
+    fn solve_mean_variance(&self, risk_aversion: f64) -> Vec<f64> {
+        let num_of_assets = self.expected_returns.len();
+        let (variance_scale, return_scale) = if risk_aversion.is_infinite() {
+            (1.0, 0.0)
+        } else {
+            (risk_aversion, 1.0)
+        };
+        // Step of the inverse of the Lipschitz constant, bounded by the largest absolute row sum
+        let lipschitz = variance_scale
+            * self
+                .covariance
+                .iter()
+                .map(|row| row.iter().map(|x| x.abs()).sum::<f64>())
+                .fold(0.0, f64::max);
+        let step = 1.0 / lipschitz.max(1e-12);
+
+        let mut weights = self.project(&vec![1.0 / num_of_assets as f64; num_of_assets]);
+        for _ in 0..MAX_ITERATIONS {
+            let gradient = self.covariance_product(&weights);
+            let candidate = weights
+                .iter()
+                .zip(gradient.iter().zip(&self.expected_returns))
+                .map(|(w, (g, mu))| w - step * (variance_scale * g - return_scale * mu))
+                .collect::<Vec<f64>>();
+            let projected = self.project(&candidate);
+            let change = projected
+                .iter()
+                .zip(&weights)
+                .map(|(x, y)| (x - y).powi(2))
+                .sum::<f64>();
+            weights = projected;
+            if change < CONVERGENCE_TOLERANCE {
+                break;
+            }
+        }
+        weights
+    }
+
+    fn sharpe_ratio(&self, weights: &[f64], risk_free_rate: f64) -> f64 {
+        (self.expected_return(weights) - risk_free_rate) / self.variance(weights).sqrt()
+    }
+
+    fn to_portfolio_weights(
+        &self,
+        estimates: &structs::ReturnEstimates,
+        weights: &[f64],
+    ) -> structs::PortfolioWeights {
+        structs::PortfolioWeights {
+            tickers: estimates.tickers.clone(),
+            weights: weights.iter().map(|x| *x as f32).collect(),
+            expected_return: self.expected_return(weights) as f32,
+            volatility: self.variance(weights).sqrt() as f32,
+        }
+    }
+}
+
+/// Weights of minimum variance.
+pub fn minimum_variance(
+    estimates: &structs::ReturnEstimates,
+    bounds: &structs::WeightBounds,
+) -> Result<structs::PortfolioWeights, CalculationError> {
+    let problem = Problem::new(estimates, bounds)?;
+    let weights = problem.solve_mean_variance(f64::INFINITY);
+    Ok(problem.to_portfolio_weights(estimates, &weights))
+}
+
+/// Weights maximizing the expected return less half the risk aversion times the variance.
+pub fn mean_variance(
+    estimates: &structs::ReturnEstimates,
+    bounds: &structs::WeightBounds,
+    risk_aversion: f32,
+) -> Result<structs::PortfolioWeights, CalculationError> {
+    if risk_aversion.is_nan() || risk_aversion <= 0.0 {
+        return Err(CalculationError::InvalidParameterError(format!(
+            "Risk aversion must be positive. Risk aversion provided: {}",
+            risk_aversion
+        )));
+    }
+    let problem = Problem::new(estimates, bounds)?;
+    let weights = problem.solve_mean_variance(risk_aversion as f64);
+    Ok(problem.to_portfolio_weights(estimates, &weights))
+}
+
+/// Efficient portfolios at risk aversions spaced logarithmically between 0.01 and 10000, together with the minimum
+/// variance portfolio, in ascending order of volatility.
+pub fn efficient_frontier(
+    estimates: &structs::ReturnEstimates,
+    bounds: &structs::WeightBounds,
+    num_of_points: usize,
+) -> Result<Vec<structs::PortfolioWeights>, CalculationError> {
+    if num_of_points < 2 {
+        return Err(CalculationError::InvalidParameterError(format!(
+            "Efficient frontier requires at least 2 points, got {}.",
+            num_of_points
+        )));
+    }
+    let problem = Problem::new(estimates, bounds)?;
+    let mut frontier = (0..num_of_points - 1)
+        .map(|idx| {
+            let log_risk_aversion = MIN_LOG_RISK_AVERSION
+                + (MAX_LOG_RISK_AVERSION - MIN_LOG_RISK_AVERSION) * idx as f64
+                    / (num_of_points - 2).max(1) as f64;
+            problem.solve_mean_variance(10_f64.powf(log_risk_aversion))
+        })
+        .chain(std::iter::once(problem.solve_mean_variance(f64::INFINITY)))
+        .map(|weights| problem.to_portfolio_weights(estimates, &weights))
+        .collect::<Vec<structs::PortfolioWeights>>();
+    // Volatilities are finite for valid covariance estimates
+    frontier.sort_by(|a, b| a.volatility.partial_cmp(&b.volatility).unwrap());
+    Ok(frontier)
+}
+
+/// Weights of the maximum Sharpe ratio along the efficient frontier, located over a grid of risk aversions and
+/// refined by golden section search.
+pub fn maximum_sharpe(
+    estimates: &structs::ReturnEstimates,
+    bounds: &structs::WeightBounds,
+    risk_free_rate: f32,
+) -> Result<structs::PortfolioWeights, CalculationError> {
+    let problem = Problem::new(estimates, bounds)?;
+    let risk_free_rate = risk_free_rate as f64;
+    let sharpe_at = |log_risk_aversion: f64| {
+        let weights = problem.solve_mean_variance(10_f64.powf(log_risk_aversion));
+        let sharpe_ratio = problem.sharpe_ratio(&weights, risk_free_rate);
+        (
+            if sharpe_ratio.is_nan() {
+                f64::NEG_INFINITY
+            } else {
+                sharpe_ratio
+            },
+            weights,
+        )
+    };
+
+    let num_of_steps: usize = 24;
+    let step = (MAX_LOG_RISK_AVERSION - MIN_LOG_RISK_AVERSION) / num_of_steps as f64;
+    let best_step = (0..=num_of_steps)
+        .map(|idx| (idx, sharpe_at(MIN_LOG_RISK_AVERSION + idx as f64 * step).0))
+        .reduce(|best, x| if x.1 > best.1 { x } else { best })
+        .unwrap() // Unreachable, as the grid is non-empty
+        .0;
+
+    let golden_ratio = (5.0_f64.sqrt() - 1.0) / 2.0;
+    let mut low = MIN_LOG_RISK_AVERSION + best_step.saturating_sub(1) as f64 * step;
+    let mut high = MIN_LOG_RISK_AVERSION + (best_step + 1).min(num_of_steps) as f64 * step;
+    for _ in 0..40 {
+        let first = high - golden_ratio * (high - low);
+        let second = low + golden_ratio * (high - low);
+        if sharpe_at(first).0 >= sharpe_at(second).0 {
+            high = second;
+        } else {
+            low = first;
+        }
+    }
+    let (_, weights) = sharpe_at((low + high) / 2.0);
+    Ok(problem.to_portfolio_weights(estimates, &weights))
+}
+
+/// Fractions of the portfolio variance contributed by each weight.
+pub fn risk_contributions(
+    estimates: &structs::ReturnEstimates,
+    weights: &[f32],
+) -> Result<Vec<f32>, CalculationError> {
+    if weights.len() != estimates.expected_returns.len() {
+        return Err(CalculationError::InconsistentLengthError(format!(
+            "Number of tickers: {} \n Number of weights: {}",
+            estimates.expected_returns.len(),
+            weights.len()
+        )));
+    }
+    let weights = weights.iter().map(|x| *x as f64).collect::<Vec<f64>>();
+    let problem = Problem {
+        expected_returns: vec![0.0; weights.len()],
+        covariance: estimates
+            .covariance
+            .iter()
+            .map(|row| row.iter().map(|x| *x as f64).collect())
+            .collect(),
+        lower: Vec::new(),
+        upper: Vec::new(),
+    };
+    let variance = problem.variance(&weights);
+    Ok(problem
+        .covariance_product(&weights)
+        .iter()
+        .zip(&weights)
+        .map(|(x, w)| (w * x / variance) as f32)
+        .collect())
+}
+
+/// Weights with equal risk contributions, solved by cyclical coordinate descent on the log barrier formulation of
+/// Spinu (2013). The weights are then projected onto the bounds, such that the risk contributions are only equal
+/// where the bounds are not binding.
+pub fn equal_risk_contribution(
+    estimates: &structs::ReturnEstimates,
+    bounds: &structs::WeightBounds,
+) -> Result<structs::PortfolioWeights, CalculationError> {
+    let problem = Problem::new(estimates, bounds)?;
+    let num_of_assets = problem.expected_returns.len();
+    if (0..num_of_assets).any(|i| problem.covariance[i][i] <= 0.0) {
+        return Err(CalculationError::InvalidParameterError(
+            "Equal risk contributions require positive variances.".to_string(),
+        ));
+    }
+
+    // Minimizes 0.5 * y'Σy - sum(ln y) / n, whose solution is proportional to the equal risk contribution weights
+    let budget = 1.0 / num_of_assets as f64;
+    let mut scaled = (0..num_of_assets)
+        .map(|i| 1.0 / problem.covariance[i][i].sqrt())
+        .collect::<Vec<f64>>();
+    for _ in 0..MAX_ITERATIONS {
+        let mut change = 0.0;
+        for i in 0..num_of_assets {
+            let cross = (0..num_of_assets)
+                .filter(|j| *j != i)
+                .map(|j| problem.covariance[i][j] * scaled[j])
+                .sum::<f64>();
+            let variance = problem.covariance[i][i];
+            let updated =
+                (-cross + (cross.powi(2) + 4.0 * variance * budget).sqrt()) / (2.0 * variance);
+            change += (updated - scaled[i]).powi(2);
+            scaled[i] = updated;
+        }
+        if change < CONVERGENCE_TOLERANCE {
+            break;
+        }
+    }
+    let total = scaled.iter().sum::<f64>();
+    let weights = problem.project(&scaled.iter().map(|x| x / total).collect::<Vec<f64>>());
+    Ok(problem.to_portfolio_weights(estimates, &weights))
+}
+
+/// Order of the assets as the leaves of a single linkage clustering over the correlation distances, placing
+/// similar assets next to each other.
+fn quasi_diagonal_order(correlation: &[Vec<f64>]) -> Vec<usize> {
+    let num_of_assets = correlation.len();
+    // Distances between the correlation distance vectors of the assets
+    let distance = correlation
+        .iter()
+        .map(|x| x.iter().map(|rho| (0.5 * (1.0 - rho)).max(0.0).sqrt()))
+        .map(|x| x.collect::<Vec<f64>>())
+        .collect::<Vec<Vec<f64>>>();
+    let linkage_distance = (0..num_of_assets)
+        .map(|i| {
+            (0..num_of_assets)
+                .map(|j| {
+                    (0..num_of_assets)
+                        .map(|k| (distance[i][k] - distance[j][k]).powi(2))
+                        .sum::<f64>()
+                        .sqrt()
+                })
+                .collect()
+        })
+        .collect::<Vec<Vec<f64>>>();
+
+    // Merges the closest pair of clusters until a single cluster remains, keeping each cluster's leaf order
+    let mut clusters = (0..num_of_assets)
+        .map(|i| vec![i])
+        .collect::<Vec<Vec<usize>>>();
+    while clusters.len() > 1 {
+        let mut closest = (0, 1, f64::INFINITY);
+        for a in 0..clusters.len() {
+            for b in a + 1..clusters.len() {
+                let single_linkage = clusters[a]
+                    .iter()
+                    .flat_map(|i| clusters[b].iter().map(move |j| (*i, *j)))
+                    .map(|(i, j)| linkage_distance[i][j])
+                    .fold(f64::INFINITY, f64::min);
+                if single_linkage < closest.2 {
+                    closest = (a, b, single_linkage);
+                }
+            }
+        }
+        let merged = clusters.remove(closest.1);
+        clusters[closest.0].extend(merged);
+    }
+    clusters.remove(0)
+}
+
+/// Hierarchical risk parity weights of Lopez de Prado (2016): assets are ordered by a single linkage clustering of
+/// their correlations, then the weights are split by recursive bisection in inverse proportion to the variance of
+/// each half under inverse variance weights. The weights are then projected onto the bounds.
+pub fn hierarchical_risk_parity(
+    estimates: &structs::ReturnEstimates,
+    bounds: &structs::WeightBounds,
+) -> Result<structs::PortfolioWeights, CalculationError> {
+    let problem = Problem::new(estimates, bounds)?;
+    let num_of_assets = problem.expected_returns.len();
+    if (0..num_of_assets).any(|i| problem.covariance[i][i] <= 0.0) {
+        return Err(CalculationError::InvalidParameterError(
+            "Hierarchical risk parity requires positive variances.".to_string(),
+        ));
+    }
+    let correlation = (0..num_of_assets)
+        .map(|i| {
+            (0..num_of_assets)
+                .map(|j| {
+                    problem.covariance[i][j]
+                        / (problem.covariance[i][i] * problem.covariance[j][j]).sqrt()
+                })
+                .collect()
+        })
+        .collect::<Vec<Vec<f64>>>();
+
+    let cluster_variance = |cluster: &[usize]| {
+        let inverse_variances = cluster
+            .iter()
+            .map(|i| 1.0 / problem.covariance[*i][*i])
+            .collect::<Vec<f64>>();
+        let total = inverse_variances.iter().sum::<f64>();
+        cluster
+            .iter()
+            .zip(&inverse_variances)
+            .flat_map(|(i, wi)| {
+                cluster
+                    .iter()
+                    .zip(&inverse_variances)
+                    .map(move |(j, wj)| (*i, *j, wi * wj))
+            })
+            .map(|(i, j, w)| w * problem.covariance[i][j])
+            .sum::<f64>()
+            / total.powi(2)
+    };
+
+    let mut weights = vec![1.0; num_of_assets];
+    let mut stack = vec![quasi_diagonal_order(&correlation)];
+    while let Some(cluster) = stack.pop() {
+        if cluster.len() < 2 {
+            continue;
+        }
+        let (left, right) = cluster.split_at(cluster.len() / 2);
+        let left_variance = cluster_variance(left);
+        let right_variance = cluster_variance(right);
+        let left_allocation = 1.0 - left_variance / (left_variance + right_variance);
+        left.iter().for_each(|i| weights[*i] *= left_allocation);
+        right
+            .iter()
+            .for_each(|i| weights[*i] *= 1.0 - left_allocation);
+        stack.push(left.to_vec());
+        stack.push(right.to_vec());
+    }
+    let weights = problem.project(&weights);
+    Ok(problem.to_portfolio_weights(estimates, &weights))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datasets::structs::YahooFinancePriceRecord;
+
+    fn test_estimates(expected_returns: &[f32], covariance: &[&[f32]]) -> structs::ReturnEstimates {
+        structs::ReturnEstimates {
+            tickers: (0..expected_returns.len())
+                .map(|idx| format!("T{}", idx))
+                .collect(),
+            expected_returns: expected_returns.to_vec(),
+            covariance: covariance.iter().map(|row| row.to_vec()).collect(),
+            shrinkage_intensity: 0.0,
+        }
+    }
+
+    #[test]
+    fn test_estimate_returns() {
+        let first =
+            YahooFinancePriceRecord::from_returns(&[0.01, -0.02, 0.015, 0.005, -0.01, 0.02])
+                .with_ticker_symbol("FIRST");
+        let second =
+            YahooFinancePriceRecord::from_returns(&[0.005, -0.01, 0.01, 0.0, -0.004, 0.012])
+                .with_ticker_symbol("SECOND");
+        let third = YahooFinancePriceRecord::from_returns(&[-0.01, 0.01, 0.0, 0.02, -0.015, 0.005])
+            .with_ticker_symbol("THIRD");
+        let records = [&first, &second, &third];
+
+        let sample = estimate_returns(
+            &records,
+            returns::enums::ReturnBasis::Close,
+            enums::CovarianceEstimator::Sample,
+        )
+        .unwrap();
+        let first_returns = returns::simple_returns(first.get_close_prices());
+        assert!(
+            (sample.get_covariance()[0][0]
+                - statistics::sample_variance(&first_returns[1..]) * 252.0)
+                .abs()
+                < 1e-6
+        );
+        assert!(sample.get_covariance()[0][1] == sample.get_covariance()[1][0]);
+        assert!(sample.get_shrinkage_intensity() == 0.0);
+
+        // Shrinkage pulls the correlations towards 0, with the average variance preserved
+        let shrunk = estimate_returns(
+            &records,
+            returns::enums::ReturnBasis::Close,
+            enums::CovarianceEstimator::LedoitWolf,
+        )
+        .unwrap();
+        let intensity = shrunk.get_shrinkage_intensity();
+        assert!(intensity > 0.0 && intensity <= 1.0);
+        let trace =
+            |x: &structs::ReturnEstimates| (0..3).map(|i| x.get_covariance()[i][i]).sum::<f32>();
+        // The shrunk covariance is based on the maximum likelihood variances, scaled by 5 / 6 of the sample variances
+        assert!((trace(&shrunk) - trace(&sample) * 5.0 / 6.0).abs() < 1e-5);
+        assert!(
+            shrunk.get_covariance()[0][1].abs() < sample.get_covariance()[0][1].abs() * 5.0 / 6.0
+        );
+        assert!(estimate_returns(
+            &[&first],
+            returns::enums::ReturnBasis::Close,
+            enums::CovarianceEstimator::Sample
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_minimum_variance() {
+        // Analytic weight of the first asset: (0.09 - 0.01) / (0.04 + 0.09 - 0.02) = 8 / 11
+        let estimates = test_estimates(&[0.05, 0.1], &[&[0.04, 0.01], &[0.01, 0.09]]);
+        let bounds = structs::WeightBounds::long_only(2, 1.0).unwrap();
+        let result = minimum_variance(&estimates, &bounds).unwrap();
+        assert!((result.get_weights()[0] - 8.0 / 11.0).abs() < 1e-4);
+        assert!((result.get_weights().iter().sum::<f32>() - 1.0).abs() < 1e-5);
+
+        let capped = structs::WeightBounds::long_only(2, 0.6).unwrap();
+        let result = minimum_variance(&estimates, &capped).unwrap();
+        assert!((result.get_weights()[0] - 0.6).abs() < 1e-4);
+        assert!(structs::WeightBounds::long_only(2, 0.4).is_err());
+        assert!(minimum_variance(
+            &estimates,
+            &structs::WeightBounds::long_only(3, 1.0).unwrap()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_efficient_frontier_and_maximum_sharpe() {
+        // Uncorrelated assets: the tangency weights are proportional to (mu - rf) / variance, i.e. 2:1
+        let estimates = test_estimates(&[0.06, 0.1], &[&[0.01, 0.0], &[0.0, 0.04]]);
+        let bounds = structs::WeightBounds::long_only(2, 1.0).unwrap();
+        let tangency = maximum_sharpe(&estimates, &bounds, 0.02).unwrap();
+        assert!((tangency.get_weights()[0] - 2.0 / 3.0).abs() < 1e-3);
+
+        let frontier = efficient_frontier(&estimates, &bounds, 10).unwrap();
+        assert!(frontier.len() == 10);
+        assert!(frontier
+            .windows(2)
+            .all(|pair| pair[0].get_expected_return() <= pair[1].get_expected_return() + 1e-6));
+        // No frontier portfolio has a higher Sharpe ratio than the tangency portfolio
+        assert!(frontier
+            .iter()
+            .all(|x| x.get_sharpe_ratio(0.02) <= tangency.get_sharpe_ratio(0.02) + 1e-4));
+        // The least risk averse portfolio is fully invested in the asset of the highest expected return
+        assert!((frontier[9].get_weights()[1] - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_risk_parity() {
+        let estimates = test_estimates(
+            &[0.05, 0.07, 0.09],
+            &[
+                &[0.01, 0.006, 0.0],
+                &[0.006, 0.04, 0.012],
+                &[0.0, 0.012, 0.09],
+            ],
+        );
+        let bounds = structs::WeightBounds::long_only(3, 1.0).unwrap();
+        let erc = equal_risk_contribution(&estimates, &bounds).unwrap();
+        let contributions = risk_contributions(&estimates, erc.get_weights()).unwrap();
+        assert!(contributions.iter().all(|x| (x - 1.0 / 3.0).abs() < 1e-4));
+
+        // Uncorrelated assets: inverse volatility for ERC, and inverse variance for HRP
+        let diagonal = test_estimates(
+            &[0.05, 0.07, 0.09],
+            &[&[0.01, 0.0, 0.0], &[0.0, 0.04, 0.0], &[0.0, 0.0, 0.16]],
+        );
+        let erc = equal_risk_contribution(&diagonal, &bounds).unwrap();
+        assert!((erc.get_weights()[0] - 4.0 / 7.0).abs() < 1e-4);
+        let hrp = hierarchical_risk_parity(&diagonal, &bounds).unwrap();
+        assert!((hrp.get_weights()[0] - 16.0 / 21.0).abs() < 1e-4);
+        assert!((hrp.get_weights()[2] - 1.0 / 21.0).abs() < 1e-4);
+
+        // With correlated assets, the weights still decrease with the variances
+        let hrp = hierarchical_risk_parity(&estimates, &bounds).unwrap();
+        assert!((hrp.get_weights().iter().sum::<f32>() - 1.0).abs() < 1e-5);
+        assert!(
+            hrp.get_weights()[0] > hrp.get_weights()[1]
+                && hrp.get_weights()[1] > hrp.get_weights()[2]
+        );
+    }
+}
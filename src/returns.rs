@@ -0,0 +1,294 @@
+//! Objective: Provide return series calculations over the pricing data of a record.
+
+use crate::datasets::traits::{Prices, Timestamps};
+use crate::enums::BarFrequency;
+use crate::errors::CalculationError;
+
+pub mod enums {
+
+    /// Price series used as the basis of the return calculations.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum ReturnBasis {
+        Close,
+        AdjClose,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum ReturnType {
+        Simple,
+        Log,
+        Cumulative,
+        MultiPeriod(usize),
+    }
+}
+
+/// Returns the price series of the record corresponding to the provided basis.
+pub fn get_basis_prices<T>(record: &T, basis: enums::ReturnBasis) -> &[f32]
+where
+    T: Prices,
+{
+    match basis {
+        enums::ReturnBasis::Close => record.get_close_prices(),
+        enums::ReturnBasis::AdjClose => record.get_adj_close_prices(),
+    }
+}
+
+/// Calculates the return series of the record. Output is aligned with the timestamps of the record.
+pub fn calculate_returns<T>(
+    record: &T,
+    basis: enums::ReturnBasis,
+    return_type: enums::ReturnType,
+) -> Result<Vec<f32>, CalculationError>
+where
+    T: Prices,
+{
+    let prices = get_basis_prices(record, basis);
+    match return_type {
+        enums::ReturnType::Simple => Ok(simple_returns(prices)),
+        enums::ReturnType::Log => Ok(log_returns(prices)),
+        enums::ReturnType::Cumulative => Ok(cumulative_returns(prices)),
+        enums::ReturnType::MultiPeriod(periods) => multi_period_returns(prices, periods),
+    }
+}
+
+/// Simple return for each timestamp: (price / previous price) - 1. The first value is NaN.
+pub fn simple_returns(prices: &[f32]) -> Vec<f32> {
+    let mut result = Vec::with_capacity(prices.len());
+    if prices.is_empty() {
+        return result;
+    }
+    result.push(f32::NAN);
+    prices
+        .windows(2)
+        .for_each(|window| result.push(window[1] / window[0] - 1.0));
+    result
+}
+
+/// Log return for each timestamp: ln(price / previous price). The first value is NaN.
+pub fn log_returns(prices: &[f32]) -> Vec<f32> {
+    let mut result = Vec::with_capacity(prices.len());
+    if prices.is_empty() {
+        return result;
+    }
+    result.push(f32::NAN);
+    prices
+        .windows(2)
+        .for_each(|window| result.push((window[1] / window[0]).ln()));
+    result
+}
+
+/// Cumulative return of each timestamp relative to the first price of the series.
+pub fn cumulative_returns(prices: &[f32]) -> Vec<f32> {
+    match prices.first() {
+        Some(&base_price) => prices
+            .iter()
+            .map(|price| price / base_price - 1.0)
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+/// Simple return over the trailing number of periods. The first `periods` values are NaN.
+pub fn multi_period_returns(prices: &[f32], periods: usize) -> Result<Vec<f32>, CalculationError> {
+    if periods == 0 {
+        return Err(CalculationError::InvalidParameterError(
+            "Number of periods for the multi-period returns must be greater than 0.".to_string(),
+        ));
+    }
+
+    let result = (0..prices.len())
+        .map(|idx| {
+            if idx < periods {
+                f32::NAN
+            } else {
+                prices[idx] / prices[idx - periods] - 1.0
+            }
+        })
+        .collect();
+
+    Ok(result)
+}
+
+/// Detects the bar frequency based on the median spacing between consecutive timestamps. Intraday bars are rejected,
+/// as annualizing their statistics requires the trading hours of the exchange.
+pub fn detect_frequency(
+    timestamps: &[chrono::DateTime<chrono::Utc>],
+) -> Result<BarFrequency, CalculationError> {
+    if timestamps.len() < 2 {
+        return Err(CalculationError::InsufficientDataError(format!(
+            "At least 2 timestamps are required to detect the bar frequency, got {}.",
+            timestamps.len()
+        )));
+    }
+
+    let mut spacings = timestamps
+        .windows(2)
+        .map(|window| (window[1] - window[0]).num_hours() as f32 / 24.0)
+        .collect::<Vec<f32>>();
+    spacings.sort_by(|a, b| a.partial_cmp(b).unwrap()); // Spacings are derived from integers, hence never NaN.
+    let median_spacing = spacings[spacings.len() / 2];
+    // Daylight saving time shifts may shorten a daily spacing by an hour
+    if median_spacing < 23.0 / 24.0 {
+        return Err(CalculationError::InvalidParameterError(format!(
+            "Bars must be spaced at least a day apart to detect the bar frequency. Median spacing in days: {}",
+            median_spacing
+        )));
+    }
+
+    let frequency = if median_spacing <= 4.0 {
+        BarFrequency::Daily // Weekends and public holidays produce gaps of up to 4 days
+    } else if median_spacing <= 10.0 {
+        BarFrequency::Weekly
+    } else if median_spacing <= 45.0 {
+        BarFrequency::Monthly
+    } else if median_spacing <= 120.0 {
+        BarFrequency::Quarterly
+    } else {
+        BarFrequency::Yearly
+    };
+
+    Ok(frequency)
+}
+
+/// Annualizes the compounded return of the provided return series. NaN values are skipped.
+pub fn annualize_returns(
+    returns: &[f32],
+    frequency: BarFrequency,
+) -> Result<f32, CalculationError> {
+    let valid_returns = returns.iter().filter(|x| !x.is_nan()).collect::<Vec<_>>();
+    if valid_returns.is_empty() {
+        return Err(CalculationError::InsufficientDataError(
+            "No valid returns available for annualization.".to_string(),
+        ));
+    }
+
+    let growth = valid_returns
+        .iter()
+        .fold(1.0_f64, |acc, &&x| acc * (1.0 + x as f64));
+    let exponent = frequency.periods_per_year() as f64 / valid_returns.len() as f64;
+
+    Ok((growth.powf(exponent) - 1.0) as f32)
+}
+
+/// Scales a per-period volatility into an annualized volatility.
+pub fn annualize_volatility(volatility: f32, frequency: BarFrequency) -> f32 {
+    volatility * frequency.periods_per_year().sqrt()
+}
+
+/// Annualized compounded return of the record, using the detected bar frequency.
+pub fn annualized_return<T>(record: &T, basis: enums::ReturnBasis) -> Result<f32, CalculationError>
+where
+    T: Prices + Timestamps,
+{
+    let frequency = detect_frequency(record.get_timestamps())?;
+    let returns = simple_returns(get_basis_prices(record, basis));
+    annualize_returns(&returns, frequency)
+}
+
+/// Per-period returns in excess of the risk-free rate.
+/// The risk-free rates are provided as annualized rates (e.g. 0.03 for 3%), aligned with the returns array.
+pub fn excess_returns(
+    returns: &[f32],
+    risk_free_rates: &[f32],
+    frequency: BarFrequency,
+) -> Result<Vec<f32>, CalculationError> {
+    if returns.len() != risk_free_rates.len() {
+        return Err(CalculationError::InconsistentLengthError(format!(
+            "Length of the returns array: {} \n Length of the risk-free rates array: {}",
+            returns.len(),
+            risk_free_rates.len()
+        )));
+    }
+
+    let periods_per_year = frequency.periods_per_year();
+    let result = returns
+        .iter()
+        .zip(risk_free_rates)
+        .map(|(ret, rate)| {
+            let periodic_rate = (1.0 + rate).powf(1.0 / periods_per_year) - 1.0; // De-annualizing the risk-free rate
+            ret - periodic_rate
+        })
+        .collect();
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datasets;
+    use chrono::TimeZone;
+
+    fn approx_eq(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-5
+    }
+
+    #[test]
+    fn test_simple_returns() {
+        let result = simple_returns(&[100.0, 110.0, 99.0]);
+        assert!(result[0].is_nan());
+        assert!(approx_eq(result[1], 0.1));
+        assert!(approx_eq(result[2], -0.1));
+    }
+
+    #[test]
+    fn test_log_and_cumulative_returns() {
+        let record =
+            datasets::structs::YahooFinancePriceRecord::from_closes(&[100.0, 110.0, 99.0, 108.9]);
+        let log =
+            calculate_returns(&record, enums::ReturnBasis::Close, enums::ReturnType::Log).unwrap();
+        assert!(approx_eq(log[1], 1.1_f32.ln()));
+
+        let cumulative = calculate_returns(
+            &record,
+            enums::ReturnBasis::AdjClose,
+            enums::ReturnType::Cumulative,
+        )
+        .unwrap();
+        assert!(approx_eq(cumulative[0], 0.0));
+        assert!(approx_eq(cumulative[3], 0.089));
+    }
+
+    #[test]
+    fn test_multi_period_returns() {
+        let result = multi_period_returns(&[100.0, 110.0, 99.0, 108.9], 2).unwrap();
+        assert!(result[0].is_nan() && result[1].is_nan());
+        assert!(approx_eq(result[2], -0.01));
+        assert!(approx_eq(result[3], -0.01));
+        assert!(multi_period_returns(&[100.0], 0).is_err());
+    }
+
+    #[test]
+    fn test_detect_frequency() {
+        let daily = (0..10)
+            .map(|x| chrono::Utc.ymd(2022, 1, 3).and_hms(0, 0, 0) + chrono::Duration::days(x))
+            .collect::<Vec<_>>();
+        assert!(detect_frequency(&daily).unwrap() == BarFrequency::Daily);
+
+        let weekly = (0..10)
+            .map(|x| chrono::Utc.ymd(2022, 1, 3).and_hms(0, 0, 0) + chrono::Duration::weeks(x))
+            .collect::<Vec<_>>();
+        assert!(detect_frequency(&weekly).unwrap() == BarFrequency::Weekly);
+
+        // Intraday bars cannot be annualized as daily bars
+        let hourly = (0..10)
+            .map(|x| chrono::Utc.ymd(2022, 1, 3).and_hms(0, 0, 0) + chrono::Duration::hours(x))
+            .collect::<Vec<_>>();
+        assert!(detect_frequency(&hourly).is_err());
+        let minutes = (0..10)
+            .map(|x| chrono::Utc.ymd(2022, 1, 3).and_hms(0, 0, 0) + chrono::Duration::minutes(x))
+            .collect::<Vec<_>>();
+        assert!(detect_frequency(&minutes).is_err());
+    }
+
+    #[test]
+    fn test_annualize_and_excess_returns() {
+        let annualized = annualize_returns(&[f32::NAN, 0.01, 0.01], BarFrequency::Monthly).unwrap();
+        assert!(approx_eq(annualized, 1.01_f32.powi(12) - 1.0));
+
+        let excess = excess_returns(&[0.01, 0.02], &[0.0, 0.1], BarFrequency::Yearly).unwrap();
+        assert!(approx_eq(excess[0], 0.01));
+        assert!(approx_eq(excess[1], -0.08));
+        assert!(excess_returns(&[0.01], &[], BarFrequency::Yearly).is_err());
+    }
+}
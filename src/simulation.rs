@@ -0,0 +1,584 @@
+//! Objective: Simulate synthetic price paths calibrated from the log returns of a record, to test findings
+//! against randomness and to project probability cones of future prices.
+
+use rand::Rng;
+use rand::SeedableRng;
+use rand_distr::Distribution;
+
+use crate::datasets::structs::YahooFinancePriceRecord;
+use crate::datasets::traits::{Description, Prices, Timestamps, Volume};
+use crate::errors::CalculationError;
+use crate::functions;
+use crate::returns;
+use crate::statistics;
+
+// Grid of the GARCH(1,1) coefficients searched by the fit, refined once around the best point
+const GARCH_GRID_STEP: f64 = 0.02;
+const GARCH_MAX_PERSISTENCE: f64 = 0.999;
+
+pub mod enums {
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum PathModel {
+        GeometricBrownianMotion, // Normal log returns of the historical mean and standard deviation
+        Bootstrap,               // Historical log returns drawn with replacement
+        /// Blocks of consecutive historical log returns of the given length, wrapping around the end of the
+        /// history, preserving the short-term autocorrelation and volatility clustering.
+        BlockBootstrap(usize),
+        /// GARCH(1,1) variance fitted to the historical log returns, with normal innovations.
+        Garch,
+    }
+}
+
+pub mod structs {
+    use super::*;
+
+    /// GARCH(1,1) model of the log returns: r(t) = mu + e(t), with the variance of e(t) following
+    /// omega + alpha * e(t-1)^2 + beta * variance(t-1).
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct GarchParameters {
+        pub(super) mu: f64,
+        pub(super) omega: f64,
+        pub(super) alpha: f64,
+        pub(super) beta: f64,
+        pub(super) last_variance: f64, // Conditional variance of the return following the fitted returns
+    }
+
+    impl GarchParameters {
+        pub fn get_mu(&self) -> f64 {
+            self.mu
+        }
+
+        pub fn get_omega(&self) -> f64 {
+            self.omega
+        }
+
+        pub fn get_alpha(&self) -> f64 {
+            self.alpha
+        }
+
+        pub fn get_beta(&self) -> f64 {
+            self.beta
+        }
+
+        pub fn get_last_variance(&self) -> f64 {
+            self.last_variance
+        }
+
+        pub fn get_unconditional_variance(&self) -> f64 {
+            self.omega / (1.0 - self.alpha - self.beta)
+        }
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct SimulationConfig {
+        pub(super) model: enums::PathModel,
+        pub(super) num_of_paths: usize,
+        pub(super) seed: u64,
+        pub(super) basis: returns::enums::ReturnBasis,
+    }
+
+    impl SimulationConfig {
+        pub fn get_model(&self) -> enums::PathModel {
+            self.model
+        }
+
+        pub fn get_num_of_paths(&self) -> usize {
+            self.num_of_paths
+        }
+
+        pub fn get_seed(&self) -> u64 {
+            self.seed
+        }
+
+        pub fn get_basis(&self) -> returns::enums::ReturnBasis {
+            self.basis
+        }
+    }
+
+    /// Percentiles of the simulated prices for each bar ahead of the last close.
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct ProbabilityCone {
+        pub(super) last_price: f32,
+        pub(super) percentiles: Vec<usize>,
+        pub(super) levels: Vec<Vec<f32>>, // Element [k][h]: price at the kth percentile, h + 1 bars ahead
+    }
+
+    impl ProbabilityCone {
+        pub fn get_last_price(&self) -> f32 {
+            self.last_price
+        }
+
+        pub fn get_percentiles(&self) -> &[usize] {
+            &self.percentiles
+        }
+
+        pub fn get_levels(&self) -> &[Vec<f32>] {
+            &self.levels
+        }
+
+        pub fn get_horizon(&self) -> usize {
+            self.levels[0].len()
+        }
+    }
+}
+
+impl structs::SimulationConfig {
+    /// Simulation over the close prices of the record.
+    pub fn new(
+        model: enums::PathModel,
+        num_of_paths: usize,
+        seed: u64,
+    ) -> Result<Self, CalculationError> {
+        if num_of_paths == 0 {
+            return Err(CalculationError::InvalidParameterError(
+                "At least 1 path must be simulated.".to_string(),
+            ));
+        }
+        if model == enums::PathModel::BlockBootstrap(0) {
+            return Err(CalculationError::InvalidParameterError(
+                "Bootstrap block length must be at least 1.".to_string(),
+            ));
+        }
+        Ok(structs::SimulationConfig {
+            model,
+            num_of_paths,
+            seed,
+            basis: returns::enums::ReturnBasis::Close,
+        })
+    }
+
+    pub fn with_basis(mut self, basis: returns::enums::ReturnBasis) -> Self {
+        self.basis = basis;
+        self
+    }
+}
+
+/// Negative log likelihood of the demeaned returns under the GARCH(1,1) variance, up to a constant, together with
+/// the conditional variance following the last return. The variance recursion starts at the sample variance.
+fn garch_negative_log_likelihood(
+    residuals: &[f64],
+    sample_variance: f64,
+    omega: f64,
+    alpha: f64,
+    beta: f64,
+) -> (f64, f64) {
+    let mut variance = sample_variance;
+    let mut total = 0.0;
+    for residual in residuals {
+        total += variance.ln() + residual.powi(2) / variance;
+        variance = omega + alpha * residual.powi(2) + beta * variance;
+    }
+    (0.5 * total, variance)
+}
+
+/// Fits a GARCH(1,1) model to the log returns by maximum likelihood, with the unconditional variance targeted at
+/// the sample variance, over a grid of the alpha and beta coefficients refined around its best point.
+pub fn fit_garch(log_returns: &[f32]) -> Result<structs::GarchParameters, CalculationError> {
+    let log_returns = statistics::drop_nan(log_returns);
+    if log_returns.len() < 10 {
+        return Err(CalculationError::InsufficientDataError(format!(
+            "At least 10 returns are required to fit a GARCH model, got {}.",
+            log_returns.len()
+        )));
+    }
+    let mu = log_returns.iter().map(|x| *x as f64).sum::<f64>() / log_returns.len() as f64;
+    let residuals = log_returns
+        .iter()
+        .map(|x| *x as f64 - mu)
+        .collect::<Vec<f64>>();
+    let sample_variance = residuals.iter().map(|x| x.powi(2)).sum::<f64>() / residuals.len() as f64;
+    if sample_variance <= 0.0 {
+        return Err(CalculationError::InvalidParameterError(
+            "GARCH model requires returns of positive variance.".to_string(),
+        ));
+    }
+
+    let evaluate = |alpha: f64, beta: f64| {
+        let omega = sample_variance * (1.0 - alpha - beta);
+        garch_negative_log_likelihood(&residuals, sample_variance, omega, alpha, beta).0
+    };
+    let search = |alphas: Vec<f64>, betas: Vec<f64>| {
+        let mut best = (f64::INFINITY, 0.0, 0.0);
+        for alpha in &alphas {
+            for beta in &betas {
+                if alpha + beta > GARCH_MAX_PERSISTENCE {
+                    continue;
+                }
+                let value = evaluate(*alpha, *beta);
+                if value < best.0 {
+                    best = (value, *alpha, *beta);
+                }
+            }
+        }
+        best
+    };
+    let grid = |start: f64, end: f64, step: f64| {
+        let num_of_steps = ((end - start) / step).round() as usize;
+        (0..=num_of_steps)
+            .map(|idx| start + idx as f64 * step)
+            .filter(|x| *x >= 0.0 && *x < 1.0)
+            .collect::<Vec<f64>>()
+    };
+
+    let (_, alpha, beta) = search(
+        grid(0.0, 0.5, GARCH_GRID_STEP),
+        grid(0.0, GARCH_MAX_PERSISTENCE, GARCH_GRID_STEP),
+    );
+    let fine_step = GARCH_GRID_STEP / 10.0;
+    let (_, alpha, beta) = search(
+        grid(alpha - GARCH_GRID_STEP, alpha + GARCH_GRID_STEP, fine_step),
+        grid(beta - GARCH_GRID_STEP, beta + GARCH_GRID_STEP, fine_step),
+    );
+    let omega = sample_variance * (1.0 - alpha - beta);
+    let (_, last_variance) =
+        garch_negative_log_likelihood(&residuals, sample_variance, omega, alpha, beta);
+    Ok(structs::GarchParameters {
+        mu,
+        omega,
+        alpha,
+        beta,
+        last_variance,
+    })
+}
+
+/// Log returns generator calibrated from the historical log returns.
+enum Generator {
+    Normal(rand_distr::Normal<f64>),
+    Bootstrap(Vec<f64>),
+    BlockBootstrap(Vec<f64>, usize),
+    Garch(structs::GarchParameters),
+}
+
+impl Generator {
+    fn new(model: enums::PathModel, log_returns: &[f32]) -> Result<Self, CalculationError> {
+        let log_returns = statistics::drop_nan(log_returns);
+        if log_returns.len() < 2 {
+            return Err(CalculationError::InsufficientDataError(format!(
+                "At least 2 returns are required for the simulation, got {}.",
+                log_returns.len()
+            )));
+        }
+        let history = log_returns.iter().map(|x| *x as f64).collect::<Vec<f64>>();
+        Ok(match model {
+            enums::PathModel::GeometricBrownianMotion => Generator::Normal(
+                rand_distr::Normal::new(
+                    statistics::mean(&log_returns) as f64,
+                    statistics::sample_std(&log_returns) as f64,
+                )
+                .map_err(|e| CalculationError::InvalidParameterError(format!("{}", e)))?,
+            ),
+            enums::PathModel::Bootstrap => Generator::Bootstrap(history),
+            enums::PathModel::BlockBootstrap(block_length) => {
+                Generator::BlockBootstrap(history, block_length)
+            }
+            enums::PathModel::Garch => Generator::Garch(fit_garch(&log_returns)?),
+        })
+    }
+
+    /// Simulated log returns over the length, with the GARCH variance starting from the given variance.
+    fn generate<R>(&self, length: usize, initial_variance: Option<f64>, rng: &mut R) -> Vec<f64>
+    where
+        R: Rng,
+    {
+        match self {
+            Generator::Normal(distribution) => {
+                (0..length).map(|_| distribution.sample(rng)).collect()
+            }
+            Generator::Bootstrap(history) => (0..length)
+                .map(|_| history[rng.gen_range(0..history.len())])
+                .collect(),
+            Generator::BlockBootstrap(history, block_length) => {
+                let mut result = Vec::with_capacity(length);
+                while result.len() < length {
+                    let start = rng.gen_range(0..history.len());
+                    (0..*block_length)
+                        .take(length - result.len())
+                        .for_each(|offset| result.push(history[(start + offset) % history.len()]));
+                }
+                result
+            }
+            Generator::Garch(parameters) => {
+                let mut variance =
+                    initial_variance.unwrap_or_else(|| parameters.get_unconditional_variance());
+                (0..length)
+                    .map(|_| {
+                        let z: f64 = rng.sample(rand_distr::StandardNormal);
+                        let residual = variance.sqrt() * z;
+                        variance = parameters.omega
+                            + parameters.alpha * residual.powi(2)
+                            + parameters.beta * variance;
+                        parameters.mu + residual
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Alternative histories of the record: synthetic paths over the record's timestamps starting from its first price,
+/// with the volume of the record. Each bar opens at the previous close, with the high and low bounding the open and
+/// close, such that the paths implement the price traits consistently.
+pub fn simulate_paths<T>(
+    record: &T,
+    config: &structs::SimulationConfig,
+) -> Result<Vec<YahooFinancePriceRecord>, CalculationError>
+where
+    T: Prices + Timestamps + Volume + Description,
+{
+    let prices = returns::get_basis_prices(record, config.basis);
+    let timestamps = record.get_timestamps();
+    let volume = record.get_volume();
+    if timestamps.len() != prices.len() || volume.len() != prices.len() {
+        return Err(CalculationError::InconsistentLengthError(format!(
+            "Length of the timestamps array: {} \n Length of the volume array: {} \n Length of the prices array: {}",
+            timestamps.len(),
+            volume.len(),
+            prices.len()
+        )));
+    }
+    let generator = Generator::new(config.model, &returns::log_returns(prices))?;
+    let mut rng = rand::rngs::StdRng::seed_from_u64(config.seed);
+
+    Ok((0..config.num_of_paths)
+        .map(|_| {
+            let path_returns = generator.generate(prices.len() - 1, None, &mut rng);
+            let mut path = YahooFinancePriceRecord::new(
+                record.get_ticker_symbol(),
+                prices.len(),
+                record.get_currency(),
+            );
+            let mut close = prices[0] as f64;
+            for idx in 0..prices.len() {
+                let open = close;
+                if idx > 0 {
+                    close *= path_returns[idx - 1].exp();
+                }
+                path.push_record(
+                    timestamps[idx],
+                    open as f32,
+                    open.max(close) as f32,
+                    open.min(close) as f32,
+                    close as f32,
+                    close as f32,
+                    volume[idx],
+                );
+            }
+            path
+        })
+        .collect())
+}
+
+/// Percentiles of the prices simulated over the horizon following the last price of the record. GARCH paths start
+/// from the conditional variance following the last return.
+pub fn probability_cone<T>(
+    record: &T,
+    config: &structs::SimulationConfig,
+    horizon: usize,
+    percentiles: &[usize],
+) -> Result<structs::ProbabilityCone, CalculationError>
+where
+    T: Prices,
+{
+    if horizon == 0 || percentiles.is_empty() || percentiles.iter().any(|p| *p > 100) {
+        return Err(CalculationError::InvalidParameterError(format!(
+            "Probability cone requires a positive horizon and percentiles within [0, 100]. Horizon provided: {} \n Percentiles provided: {:?}",
+            horizon, percentiles
+        )));
+    }
+    let prices = returns::get_basis_prices(record, config.basis);
+    let generator = Generator::new(config.model, &returns::log_returns(prices))?;
+    let initial_variance = match &generator {
+        Generator::Garch(parameters) => Some(parameters.last_variance),
+        _ => None,
+    };
+    let last_price = prices[prices.len() - 1];
+    let mut rng = rand::rngs::StdRng::seed_from_u64(config.seed);
+
+    // Element [h][n]: price of the nth path, h + 1 bars ahead
+    let mut simulated = vec![Vec::with_capacity(config.num_of_paths); horizon];
+    for _ in 0..config.num_of_paths {
+        let mut cumulative = 0.0;
+        for (step, log_return) in generator
+            .generate(horizon, initial_variance, &mut rng)
+            .iter()
+            .enumerate()
+        {
+            cumulative += log_return;
+            simulated[step].push((last_price as f64 * cumulative.exp()) as f32);
+        }
+    }
+    simulated
+        .iter_mut()
+        .for_each(|x| x.sort_by(|a, b| a.partial_cmp(b).unwrap())); // Finite for finite returns
+
+    let levels = percentiles
+        .iter()
+        .map(|p| {
+            simulated
+                .iter()
+                .map(|x| functions::percentile_from_sorted_array(*p, x))
+                .collect::<Result<Vec<f32>, _>>()
+                .map_err(|e| CalculationError::InvalidParameterError(format!("{}", e)))
+        })
+        .collect::<Result<Vec<Vec<f32>>, CalculationError>>()?;
+    Ok(structs::ProbabilityCone {
+        last_price,
+        percentiles: percentiles.to_vec(),
+        levels,
+    })
+}
+
+/// Empirical p-value of a statistic of the record, such as a weekly percentile, against its distribution over the
+/// simulated paths: the fraction of paths whose statistic is at least as large as the record's.
+pub fn empirical_p_value<F>(
+    record: &YahooFinancePriceRecord,
+    config: &structs::SimulationConfig,
+    statistic: F,
+) -> Result<f32, CalculationError>
+where
+    F: Fn(&YahooFinancePriceRecord) -> Result<f32, CalculationError>,
+{
+    let observed = statistic(record)?;
+    let paths = simulate_paths(record, config)?;
+    let mut num_of_extremes = 0;
+    for path in &paths {
+        if statistic(path)? >= observed {
+            num_of_extremes += 1;
+        }
+    }
+    Ok(num_of_extremes as f32 / paths.len() as f32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::synthetic;
+
+    fn record(num_of_bars: usize) -> YahooFinancePriceRecord {
+        let config = synthetic::structs::SyntheticConfig::new(
+            "TEST",
+            crate::enums::Currency::Usd,
+            chrono::NaiveDate::from_ymd(2022, 1, 3),
+            num_of_bars,
+            42,
+        )
+        .unwrap();
+        synthetic::generate_record(&config).unwrap()
+    }
+
+    #[test]
+    fn test_simulate_paths() {
+        let record = record(200);
+        for model in [
+            enums::PathModel::GeometricBrownianMotion,
+            enums::PathModel::Bootstrap,
+            enums::PathModel::BlockBootstrap(5),
+            enums::PathModel::Garch,
+        ] {
+            let config = structs::SimulationConfig::new(model, 3, 7).unwrap();
+            let paths = simulate_paths(&record, &config).unwrap();
+            assert!(paths.len() == 3);
+            for path in &paths {
+                assert!(path.get_timestamps() == record.get_timestamps());
+                assert!(path.get_volume() == record.get_volume());
+                assert!(path.get_close_prices()[0] == record.get_close_prices()[0]);
+                for idx in 0..path.get_close_prices().len() {
+                    assert!(path.get_high_prices()[idx] >= path.get_open_prices()[idx]);
+                    assert!(path.get_high_prices()[idx] >= path.get_close_prices()[idx]);
+                    assert!(path.get_low_prices()[idx] <= path.get_open_prices()[idx]);
+                    assert!(path.get_low_prices()[idx] <= path.get_close_prices()[idx]);
+                }
+            }
+            // Same seed, same paths
+            let repeated = simulate_paths(&record, &config).unwrap();
+            assert!(repeated
+                .iter()
+                .zip(&paths)
+                .all(|(x, y)| x.get_close_prices() == y.get_close_prices()));
+        }
+
+        // Bootstrapped returns are drawn from the history
+        let config = structs::SimulationConfig::new(enums::PathModel::Bootstrap, 1, 1).unwrap();
+        let history = returns::log_returns(record.get_close_prices());
+        let simulated =
+            returns::log_returns(simulate_paths(&record, &config).unwrap()[0].get_close_prices());
+        assert!(simulated[1..]
+            .iter()
+            .all(|x| history[1..].iter().any(|y| (x - y).abs() < 1e-4)));
+
+        assert!(structs::SimulationConfig::new(enums::PathModel::Bootstrap, 0, 1).is_err());
+        assert!(structs::SimulationConfig::new(enums::PathModel::BlockBootstrap(0), 1, 1).is_err());
+        let empty = YahooFinancePriceRecord::new("TEST", 0, crate::enums::Currency::Usd);
+        assert!(simulate_paths(&empty, &config).is_err());
+    }
+
+    #[test]
+    fn test_fit_garch() {
+        // Returns of a known GARCH(1,1) process
+        let parameters = structs::GarchParameters {
+            mu: 0.0,
+            omega: 1e-5,
+            alpha: 0.1,
+            beta: 0.85,
+            last_variance: 2e-4,
+        };
+        let mut rng = rand::rngs::StdRng::seed_from_u64(5);
+        let log_returns = Generator::Garch(parameters)
+            .generate(2000, None, &mut rng)
+            .iter()
+            .map(|x| *x as f32)
+            .collect::<Vec<f32>>();
+
+        let fitted = fit_garch(&log_returns).unwrap();
+        assert!(fitted.get_alpha() > 0.03 && fitted.get_alpha() < 0.2);
+        assert!(fitted.get_beta() > 0.7 && fitted.get_beta() < 0.95);
+        assert!(fitted.get_alpha() + fitted.get_beta() <= GARCH_MAX_PERSISTENCE);
+        let variance = log_returns
+            .iter()
+            .map(|x| (*x as f64 - fitted.get_mu()).powi(2))
+            .sum::<f64>()
+            / log_returns.len() as f64;
+        assert!((fitted.get_unconditional_variance() / variance - 1.0).abs() < 1e-6);
+        assert!(fit_garch(&log_returns[..5]).is_err());
+    }
+
+    #[test]
+    fn test_probability_cone() {
+        let record = record(200);
+        let config =
+            structs::SimulationConfig::new(enums::PathModel::GeometricBrownianMotion, 500, 3)
+                .unwrap();
+        let cone = probability_cone(&record, &config, 20, &[5, 50, 95]).unwrap();
+        assert!(cone.get_horizon() == 20);
+        assert!(cone.get_last_price() == *record.get_close_prices().last().unwrap());
+        for step in 0..20 {
+            assert!(cone.get_levels()[0][step] <= cone.get_levels()[1][step]);
+            assert!(cone.get_levels()[1][step] <= cone.get_levels()[2][step]);
+        }
+        // The cone widens with the horizon
+        let width = |step: usize| cone.get_levels()[2][step] - cone.get_levels()[0][step];
+        assert!(width(19) > width(0));
+        assert!(probability_cone(&record, &config, 0, &[50]).is_err());
+        assert!(probability_cone(&record, &config, 5, &[101]).is_err());
+    }
+
+    #[test]
+    fn test_empirical_p_value() {
+        let record = record(200);
+        let config =
+            structs::SimulationConfig::new(enums::PathModel::BlockBootstrap(10), 50, 11).unwrap();
+        // Paths open each bar at the previous close, whereas the record gaps overnight
+        let gap = |x: &YahooFinancePriceRecord| {
+            Ok((x.get_open_prices()[1] - x.get_close_prices()[0]).abs())
+        };
+        assert!(gap(&record).unwrap() > 0.0);
+        // Every path's gap of 0 falls short of the record's gap, while every path strictly exceeds its negation
+        assert!(empirical_p_value(&record, &config, gap).unwrap() == 0.0);
+        let negative_gap = |x: &YahooFinancePriceRecord| Ok(-gap(x)?);
+        assert!(empirical_p_value(&record, &config, negative_gap).unwrap() == 1.0);
+        // Every path starts at the record's first close, and ties count as at least as large
+        let first_price = |x: &YahooFinancePriceRecord| Ok(x.get_close_prices()[0]);
+        assert!(empirical_p_value(&record, &config, first_price).unwrap() == 1.0);
+    }
+}
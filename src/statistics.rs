@@ -0,0 +1,187 @@
+//! Objective: Provide descriptive statistics over arrays of values. NaN values are expected to be filtered out by the caller.
+
+/// Arithmetic mean of the values, NaN if the array is empty.
+pub fn mean(values: &[f32]) -> f32 {
+    let sum: f64 = values.iter().map(|&x| x as f64).sum();
+    (sum / values.len() as f64) as f32
+}
+
+/// Sample variance (N - 1 denominator) of the values.
+pub fn sample_variance(values: &[f32]) -> f32 {
+    let count = values.len() as f64;
+    let mean = mean(values) as f64;
+    let sum_of_squares: f64 = values.iter().map(|&x| (x as f64 - mean).powi(2)).sum();
+    (sum_of_squares / (count - 1.0)) as f32
+}
+
+/// Sample standard deviation (N - 1 denominator) of the values.
+pub fn sample_std(values: &[f32]) -> f32 {
+    sample_variance(values).sqrt()
+}
+
+/// Sample covariance (N - 1 denominator) between two equal length arrays.
+pub fn sample_covariance(first: &[f32], second: &[f32]) -> f32 {
+    let count = first.len() as f64;
+    let first_mean = mean(first) as f64;
+    let second_mean = mean(second) as f64;
+    let sum: f64 = first
+        .iter()
+        .zip(second)
+        .map(|(&x, &y)| (x as f64 - first_mean) * (y as f64 - second_mean))
+        .sum();
+    (sum / (count - 1.0)) as f32
+}
+
+/// Pearson correlation coefficient between two equal length arrays.
+pub fn correlation(first: &[f32], second: &[f32]) -> f32 {
+    sample_covariance(first, second) / (sample_std(first) * sample_std(second))
+}
+
+/// Skewness of the values, based on the population moments.
+pub fn skewness(values: &[f32]) -> f32 {
+    let mean = mean(values) as f64;
+    let count = values.len() as f64;
+    let second_moment: f64 = values
+        .iter()
+        .map(|&x| (x as f64 - mean).powi(2))
+        .sum::<f64>()
+        / count;
+    let third_moment: f64 = values
+        .iter()
+        .map(|&x| (x as f64 - mean).powi(3))
+        .sum::<f64>()
+        / count;
+    (third_moment / second_moment.powf(1.5)) as f32
+}
+
+/// Excess kurtosis of the values (0 for a normal distribution), based on the population moments.
+pub fn excess_kurtosis(values: &[f32]) -> f32 {
+    let mean = mean(values) as f64;
+    let count = values.len() as f64;
+    let second_moment: f64 = values
+        .iter()
+        .map(|&x| (x as f64 - mean).powi(2))
+        .sum::<f64>()
+        / count;
+    let fourth_moment: f64 = values
+        .iter()
+        .map(|&x| (x as f64 - mean).powi(4))
+        .sum::<f64>()
+        / count;
+    (fourth_moment / second_moment.powi(2) - 3.0) as f32
+}
+
+/// Probability density function of the standard normal distribution.
+pub fn normal_pdf(x: f32) -> f32 {
+    (-0.5 * x * x).exp() / (2.0 * std::f32::consts::PI).sqrt()
+}
+
+/// Cumulative distribution function of the standard normal distribution (Abramowitz and Stegun 7.1.26).
+pub fn normal_cdf(x: f32) -> f32 {
+    let z = (x as f64).abs() / std::f64::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.3275911 * z);
+    let polynomial = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let erf = 1.0 - polynomial * (-z * z).exp();
+    let cdf = if x >= 0.0 {
+        0.5 * (1.0 + erf)
+    } else {
+        0.5 * (1.0 - erf)
+    };
+    cdf as f32
+}
+
+/// Inverse of the standard normal cumulative distribution function (Acklam's rational approximation).
+pub fn normal_quantile(probability: f32) -> f32 {
+    const A: [f64; 6] = [
+        -3.969683028665376e+01,
+        2.209460984245205e+02,
+        -2.759285104469687e+02,
+        1.38357751867269e+02,
+        -3.066479806614716e+01,
+        2.506628277459239e+00,
+    ];
+    const B: [f64; 5] = [
+        -5.447609879822406e+01,
+        1.615858368580409e+02,
+        -1.556989798598866e+02,
+        6.680131188771972e+01,
+        -1.328068155288572e+01,
+    ];
+    const C: [f64; 6] = [
+        -7.784894002430293e-03,
+        -3.223964580411365e-01,
+        -2.400758277161838e+00,
+        -2.549732539343734e+00,
+        4.374664141464968e+00,
+        2.938163982698783e+00,
+    ];
+    const D: [f64; 4] = [
+        7.784695709041462e-03,
+        3.224671290700398e-01,
+        2.445134137142996e+00,
+        3.754408661907416e+00,
+    ];
+    const LOWER_REGION: f64 = 0.02425;
+
+    let p = probability as f64;
+    if p <= 0.0 {
+        return f32::NEG_INFINITY;
+    }
+    if p >= 1.0 {
+        return f32::INFINITY;
+    }
+
+    let result = if p < LOWER_REGION {
+        let q = (-2.0 * p.ln()).sqrt();
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    } else if p <= 1.0 - LOWER_REGION {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    } else {
+        let q = (-2.0 * (1.0 - p).ln()).sqrt();
+        -(((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    };
+    result as f32
+}
+
+/// Returns a copy of the values with the NaN values removed.
+pub fn drop_nan(values: &[f32]) -> Vec<f32> {
+    values.iter().copied().filter(|x| !x.is_nan()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_moments() {
+        let values = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        assert!((mean(&values) - 5.0).abs() < 1e-6);
+        assert!((sample_variance(&values) - 32.0 / 7.0).abs() < 1e-5);
+        assert!((skewness(&values) - 0.65625).abs() < 1e-5);
+        assert!((excess_kurtosis(&values) + 0.21875).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_correlation() {
+        let first = [1.0, 2.0, 3.0, 4.0];
+        let second = [2.0, 4.0, 6.0, 8.0];
+        assert!((correlation(&first, &second) - 1.0).abs() < 1e-6);
+        assert!((sample_covariance(&first, &second) - 10.0 / 3.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_normal_distribution() {
+        assert!((normal_cdf(0.0) - 0.5).abs() < 1e-6);
+        assert!((normal_cdf(1.959964) - 0.975).abs() < 1e-5);
+        assert!((normal_quantile(0.975) - 1.959964).abs() < 1e-4);
+        assert!((normal_quantile(0.01) + 2.326348).abs() < 1e-4);
+        assert!((normal_pdf(0.0) - 0.398942).abs() < 1e-5);
+    }
+}
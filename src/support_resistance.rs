@@ -0,0 +1,392 @@
+//! Objective: Detect swing highs and lows from the high and low prices, cluster them into support and resistance levels,
+//! and report the levels nearest to the latest close.
+
+use crate::datasets::traits::{Description, Prices, Timestamps};
+use crate::errors::CalculationError;
+
+pub mod enums {
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum SwingMethod {
+        Fractal(usize), // Extreme beyond the given number of bars on each side
+        ZigZag(f32), // Alternating extremes separated by a reversal of at least the given fraction (e.g. 0.05 for 5%)
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum SwingKind {
+        High,
+        Low,
+    }
+}
+
+pub mod structs {
+    use super::enums::SwingKind;
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct SwingPoint {
+        pub(super) index: usize,
+        pub(super) timestamp: chrono::DateTime<chrono::Utc>,
+        pub(super) price: f32,
+        pub(super) kind: SwingKind,
+    }
+
+    impl SwingPoint {
+        pub fn get_index(&self) -> usize {
+            self.index
+        }
+
+        pub fn get_timestamp(&self) -> chrono::DateTime<chrono::Utc> {
+            self.timestamp
+        }
+
+        pub fn get_price(&self) -> f32 {
+            self.price
+        }
+
+        pub fn get_kind(&self) -> SwingKind {
+            self.kind
+        }
+    }
+
+    /// Price level formed by a cluster of swing points.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct PriceLevel {
+        pub(super) price: f32, // Average price of the swing points within the cluster
+        pub(super) lowest_price: f32,
+        pub(super) highest_price: f32,
+        pub(super) touches: usize,
+        pub(super) first_touch: chrono::DateTime<chrono::Utc>,
+        pub(super) last_touch: chrono::DateTime<chrono::Utc>,
+    }
+
+    impl PriceLevel {
+        pub fn get_price(&self) -> f32 {
+            self.price
+        }
+
+        pub fn get_lowest_price(&self) -> f32 {
+            self.lowest_price
+        }
+
+        pub fn get_highest_price(&self) -> f32 {
+            self.highest_price
+        }
+
+        pub fn get_touches(&self) -> usize {
+            self.touches
+        }
+
+        pub fn get_first_touch(&self) -> chrono::DateTime<chrono::Utc> {
+            self.first_touch
+        }
+
+        pub fn get_last_touch(&self) -> chrono::DateTime<chrono::Utc> {
+            self.last_touch
+        }
+    }
+
+    /// Nearest support (at or below) and resistance (above) levels relative to the latest close of a ticker.
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct NearestLevels {
+        pub(super) ticker: String,
+        pub(super) latest_close: f32,
+        pub(super) support: Option<PriceLevel>,
+        pub(super) resistance: Option<PriceLevel>,
+    }
+
+    impl NearestLevels {
+        pub fn get_ticker(&self) -> &str {
+            &self.ticker
+        }
+
+        pub fn get_latest_close(&self) -> f32 {
+            self.latest_close
+        }
+
+        pub fn get_support(&self) -> Option<&PriceLevel> {
+            self.support.as_ref()
+        }
+
+        pub fn get_resistance(&self) -> Option<&PriceLevel> {
+            self.resistance.as_ref()
+        }
+    }
+}
+
+/// Swing highs and lows of the record in chronological order. Swing highs are taken from the high prices
+/// and swing lows from the low prices. The latest unconfirmed zigzag extreme is excluded.
+pub fn swing_points<T>(
+    record: &T,
+    method: enums::SwingMethod,
+) -> Result<Vec<structs::SwingPoint>, CalculationError>
+where
+    T: Prices + Timestamps,
+{
+    let high = record.get_high_prices();
+    let low = record.get_low_prices();
+    let timestamps = record.get_timestamps();
+    if high.len() != low.len() || timestamps.len() != low.len() {
+        return Err(CalculationError::InconsistentLengthError(format!(
+            "Length of the high array: {} \n Length of the low array: {} \n Length of the timestamps array: {}",
+            high.len(),
+            low.len(),
+            timestamps.len()
+        )));
+    }
+    let swing_point = |index: usize, kind: enums::SwingKind| structs::SwingPoint {
+        index,
+        timestamp: timestamps[index],
+        price: match kind {
+            enums::SwingKind::High => high[index],
+            enums::SwingKind::Low => low[index],
+        },
+        kind,
+    };
+
+    match method {
+        enums::SwingMethod::Fractal(num_of_bars) => {
+            if num_of_bars == 0 {
+                return Err(CalculationError::InvalidParameterError(
+                    "Number of fractal bars must be greater than 0.".to_string(),
+                ));
+            }
+            let mut result = Vec::new();
+            for idx in num_of_bars..high.len().saturating_sub(num_of_bars) {
+                let neighbours = (idx - num_of_bars..=idx + num_of_bars).filter(|&x| x != idx);
+                if neighbours.clone().all(|x| high[idx] > high[x]) {
+                    result.push(swing_point(idx, enums::SwingKind::High));
+                }
+                if neighbours.clone().all(|x| low[idx] < low[x]) {
+                    result.push(swing_point(idx, enums::SwingKind::Low));
+                }
+            }
+            Ok(result)
+        }
+        enums::SwingMethod::ZigZag(threshold) => {
+            if threshold.is_nan() || threshold <= 0.0 {
+                return Err(CalculationError::InvalidParameterError(format!(
+                    "Zigzag threshold must be positive. Threshold provided: {}",
+                    threshold
+                )));
+            }
+            let mut result = Vec::new();
+            if high.is_empty() {
+                return Ok(result);
+            }
+
+            // Candidate extremes of the current leg, with the direction undetermined until the first reversal
+            let mut direction: Option<enums::SwingKind> = None; // Kind of extreme being tracked
+            let mut candidate_high = 0;
+            let mut candidate_low = 0;
+            for idx in 1..high.len() {
+                match direction {
+                    None => {
+                        if high[idx] >= low[candidate_low] * (1.0 + threshold) {
+                            result.push(swing_point(candidate_low, enums::SwingKind::Low));
+                            direction = Some(enums::SwingKind::High);
+                            candidate_high = idx;
+                        } else if low[idx] <= high[candidate_high] * (1.0 - threshold) {
+                            result.push(swing_point(candidate_high, enums::SwingKind::High));
+                            direction = Some(enums::SwingKind::Low);
+                            candidate_low = idx;
+                        } else {
+                            if high[idx] > high[candidate_high] {
+                                candidate_high = idx;
+                            }
+                            if low[idx] < low[candidate_low] {
+                                candidate_low = idx;
+                            }
+                        }
+                    }
+                    Some(enums::SwingKind::High) => {
+                        if high[idx] > high[candidate_high] {
+                            candidate_high = idx;
+                        } else if low[idx] <= high[candidate_high] * (1.0 - threshold) {
+                            result.push(swing_point(candidate_high, enums::SwingKind::High));
+                            direction = Some(enums::SwingKind::Low);
+                            candidate_low = idx;
+                        }
+                    }
+                    Some(enums::SwingKind::Low) => {
+                        if low[idx] < low[candidate_low] {
+                            candidate_low = idx;
+                        } else if high[idx] >= low[candidate_low] * (1.0 + threshold) {
+                            result.push(swing_point(candidate_low, enums::SwingKind::Low));
+                            direction = Some(enums::SwingKind::High);
+                            candidate_high = idx;
+                        }
+                    }
+                }
+            }
+            Ok(result)
+        }
+    }
+}
+
+/// Clusters the swing points into price levels, merging swing points within the tolerance (as a fraction of the
+/// lowest price of the cluster) of each other. Levels with fewer than the minimum touches are dropped.
+/// Returns the levels in ascending order of price.
+pub fn cluster_levels(
+    swings: &[structs::SwingPoint],
+    tolerance: f32,
+    min_touches: usize,
+) -> Result<Vec<structs::PriceLevel>, CalculationError> {
+    if tolerance.is_nan() || tolerance < 0.0 {
+        return Err(CalculationError::InvalidParameterError(format!(
+            "Clustering tolerance must be non-negative. Tolerance provided: {}",
+            tolerance
+        )));
+    }
+
+    let mut sorted_swings = swings.to_vec();
+    sorted_swings.sort_by(|a, b| a.price.total_cmp(&b.price));
+
+    let mut clusters: Vec<Vec<structs::SwingPoint>> = Vec::new();
+    for swing in sorted_swings {
+        match clusters.last_mut() {
+            Some(cluster) if swing.price <= cluster[0].price * (1.0 + tolerance) => {
+                cluster.push(swing)
+            }
+            _ => clusters.push(vec![swing]),
+        }
+    }
+
+    Ok(clusters
+        .into_iter()
+        .filter(|cluster| cluster.len() >= min_touches.max(1))
+        .map(|cluster| structs::PriceLevel {
+            price: cluster.iter().map(|x| x.price).sum::<f32>() / cluster.len() as f32,
+            lowest_price: cluster[0].price,
+            highest_price: cluster[cluster.len() - 1].price,
+            touches: cluster.len(),
+            first_touch: cluster.iter().map(|x| x.timestamp).min().unwrap(), // Clusters are never empty
+            last_touch: cluster.iter().map(|x| x.timestamp).max().unwrap(),
+        })
+        .collect())
+}
+
+/// Support and resistance levels nearest to the latest close of the record.
+pub fn nearest_levels<T>(
+    record: &T,
+    method: enums::SwingMethod,
+    tolerance: f32,
+    min_touches: usize,
+) -> Result<structs::NearestLevels, CalculationError>
+where
+    T: Prices + Timestamps + Description,
+{
+    let latest_close = match record.get_close_prices().last() {
+        Some(close) => *close,
+        None => {
+            return Err(CalculationError::InsufficientDataError(format!(
+                "No pricing data available for {}.",
+                record.get_ticker_symbol()
+            )))
+        }
+    };
+    let levels = cluster_levels(&swing_points(record, method)?, tolerance, min_touches)?;
+
+    Ok(structs::NearestLevels {
+        ticker: record.get_ticker_symbol().to_owned(),
+        latest_close,
+        support: levels
+            .iter()
+            .rev()
+            .find(|level| level.price <= latest_close)
+            .copied(),
+        resistance: levels
+            .iter()
+            .find(|level| level.price > latest_close)
+            .copied(),
+    })
+}
+
+/// Nearest support and resistance levels for each of the records.
+pub fn nearest_levels_by_ticker<T>(
+    records: &[&T],
+    method: enums::SwingMethod,
+    tolerance: f32,
+    min_touches: usize,
+) -> Result<Vec<structs::NearestLevels>, CalculationError>
+where
+    T: Prices + Timestamps + Description,
+{
+    records
+        .iter()
+        .map(|record| nearest_levels(*record, method, tolerance, min_touches))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datasets;
+    use chrono::TimeZone;
+
+    /// Oscillates between highs of about 12 and lows of about 8.
+    fn oscillating_record() -> datasets::structs::YahooFinancePriceRecord {
+        datasets::structs::YahooFinancePriceRecord::from_hlc(&[
+            (10.0, 9.0, 9.5),
+            (12.0, 11.0, 11.5),
+            (11.0, 10.0, 10.5),
+            (9.0, 8.0, 8.5),
+            (11.0, 10.0, 10.5),
+            (12.1, 11.0, 11.55),
+            (11.0, 10.0, 10.5),
+            (9.1, 8.1, 8.6),
+            (9.5, 9.0, 9.25),
+        ])
+    }
+
+    #[test]
+    fn test_fractal_swing_points() {
+        let record = oscillating_record();
+        let swings = swing_points(&record, enums::SwingMethod::Fractal(1)).unwrap();
+        let summary = swings
+            .iter()
+            .map(|x| (x.get_index(), x.get_kind()))
+            .collect::<Vec<(usize, enums::SwingKind)>>();
+        assert!(
+            summary
+                == vec![
+                    (1, enums::SwingKind::High),
+                    (3, enums::SwingKind::Low),
+                    (5, enums::SwingKind::High),
+                    (7, enums::SwingKind::Low)
+                ]
+        );
+        assert!(swings[0].get_price() == 12.0 && swings[1].get_price() == 8.0);
+    }
+
+    #[test]
+    fn test_zigzag_swing_points() {
+        let record = oscillating_record();
+        let swings = swing_points(&record, enums::SwingMethod::ZigZag(0.2)).unwrap();
+        // The initial low at index 0 is confirmed by the high of 12, and the final low remains unconfirmed
+        let indexes = swings.iter().map(|x| x.get_index()).collect::<Vec<usize>>();
+        assert!(indexes == vec![0, 1, 3, 5]);
+        assert!(swings[2].get_kind() == enums::SwingKind::Low);
+        assert!(swing_points(&record, enums::SwingMethod::ZigZag(0.0)).is_err());
+    }
+
+    #[test]
+    fn test_levels() {
+        let record = oscillating_record();
+        let swings = swing_points(&record, enums::SwingMethod::Fractal(1)).unwrap();
+        let levels = cluster_levels(&swings, 0.02, 2).unwrap();
+        assert!(levels.len() == 2);
+        assert!((levels[0].get_price() - 8.05).abs() < 1e-5 && levels[0].get_touches() == 2);
+        assert!(levels[1].get_first_touch() == chrono::Utc.ymd(2022, 1, 4).and_hms(0, 0, 0));
+
+        let nearest = nearest_levels(&record, enums::SwingMethod::Fractal(1), 0.02, 2).unwrap();
+        assert!(nearest.get_latest_close() == 9.25);
+        assert!(nearest.get_support() == Some(&levels[0]));
+        assert!(nearest.get_resistance() == Some(&levels[1]));
+        assert!(
+            nearest_levels_by_ticker(&[&record, &record], enums::SwingMethod::Fractal(1), 0.02, 2)
+                .unwrap()
+                .len()
+                == 2
+        );
+    }
+}
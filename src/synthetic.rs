@@ -0,0 +1,520 @@
+//! Objective: Generate seeded synthetic daily OHLCV records, such that the grouping, aggregation and percentile
+//! functionality can be tested offline and against many randomly generated datasets.
+
+use rand::Rng;
+use rand::SeedableRng;
+use rand_distr::Distribution;
+
+use chrono::Datelike;
+
+use crate::datasets::structs::YahooFinancePriceRecord;
+use crate::enums::Currency;
+use crate::errors::CalculationError;
+
+const TRADING_DAYS_PER_YEAR: f64 = 252.0;
+const OVERNIGHT_VOLATILITY_SHARE: f64 = 0.25; // Share of the daily variance realized between the close and the open
+const RANGE_VOLATILITY_SCALE: f64 = 0.5; // Scale of the high and low extensions beyond the open and close
+const VOLUME_DISPERSION: f64 = 0.3; // Standard deviation of the log volume
+
+pub mod structs {
+    use super::*;
+
+    /// Stock split effective from the given date: each share before the date becomes `ratio` shares.
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct Split {
+        pub(super) date: chrono::NaiveDate,
+        pub(super) ratio: f32,
+    }
+
+    impl Split {
+        pub fn get_date(&self) -> chrono::NaiveDate {
+            self.date
+        }
+
+        pub fn get_ratio(&self) -> f32 {
+            self.ratio
+        }
+    }
+
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct SyntheticConfig {
+        pub(super) ticker: String,
+        pub(super) currency: Currency,
+        pub(super) start_date: chrono::NaiveDate,
+        pub(super) num_of_days: usize, // Trading days in the calendar, before any rows are dropped
+        pub(super) seed: u64,
+        pub(super) initial_price: f32,
+        pub(super) annual_drift: f32,
+        pub(super) annual_volatility: f32,
+        pub(super) mean_volume: f32,
+        pub(super) volume_autocorrelation: f32,
+        pub(super) holidays: Vec<chrono::NaiveDate>,
+        pub(super) splits: Vec<Split>,
+        pub(super) missing_rate: f32,
+    }
+
+    impl SyntheticConfig {
+        pub fn get_ticker(&self) -> &str {
+            &self.ticker
+        }
+
+        pub fn get_currency(&self) -> Currency {
+            self.currency
+        }
+
+        pub fn get_start_date(&self) -> chrono::NaiveDate {
+            self.start_date
+        }
+
+        pub fn get_num_of_days(&self) -> usize {
+            self.num_of_days
+        }
+
+        pub fn get_seed(&self) -> u64 {
+            self.seed
+        }
+
+        pub fn get_holidays(&self) -> &[chrono::NaiveDate] {
+            &self.holidays
+        }
+
+        pub fn get_splits(&self) -> &[Split] {
+            &self.splits
+        }
+
+        pub fn get_missing_rate(&self) -> f32 {
+            self.missing_rate
+        }
+    }
+}
+
+impl structs::Split {
+    pub fn new(date: chrono::NaiveDate, ratio: f32) -> Result<Self, CalculationError> {
+        if !ratio.is_finite() || ratio <= 0.0 {
+            return Err(CalculationError::InvalidParameterError(format!(
+                "Split ratio must be positive. Ratio provided: {}",
+                ratio
+            )));
+        }
+        Ok(structs::Split { date, ratio })
+    }
+}
+
+impl structs::SyntheticConfig {
+    /// Geometric random walk starting at 100 with an annual drift of 8% and an annual volatility of 20%, a mean
+    /// volume of 1,000,000 with an autocorrelation of 0.7, and the fixed-date holidays of the currency's exchange.
+    pub fn new(
+        ticker_symbol: &str,
+        currency: Currency,
+        start_date: chrono::NaiveDate,
+        num_of_days: usize,
+        seed: u64,
+    ) -> Result<Self, CalculationError> {
+        if num_of_days == 0 {
+            return Err(CalculationError::InvalidParameterError(
+                "At least 1 trading day must be generated.".to_string(),
+            ));
+        }
+        // Calendar years spanned by the trading days, with a margin of a year
+        let holidays = (start_date.year()..=start_date.year() + num_of_days as i32 / 240 + 1)
+            .flat_map(|year| fixed_date_holidays(year, currency))
+            .collect();
+        Ok(structs::SyntheticConfig {
+            ticker: ticker_symbol.to_owned(),
+            currency,
+            start_date,
+            num_of_days,
+            seed,
+            initial_price: 100.0,
+            annual_drift: 0.08,
+            annual_volatility: 0.2,
+            mean_volume: 1_000_000.0,
+            volume_autocorrelation: 0.7,
+            holidays,
+            splits: Vec::new(),
+            missing_rate: 0.0,
+        })
+    }
+
+    pub fn with_price_process(
+        mut self,
+        initial_price: f32,
+        annual_drift: f32,
+        annual_volatility: f32,
+    ) -> Result<Self, CalculationError> {
+        if !initial_price.is_finite()
+            || initial_price <= 0.0
+            || !annual_drift.is_finite()
+            || !annual_volatility.is_finite()
+            || annual_volatility < 0.0
+        {
+            return Err(CalculationError::InvalidParameterError(format!(
+                "Initial price must be positive and the annual volatility non-negative. Initial price provided: {} \n Annual drift provided: {} \n Annual volatility provided: {}",
+                initial_price, annual_drift, annual_volatility
+            )));
+        }
+        self.initial_price = initial_price;
+        self.annual_drift = annual_drift;
+        self.annual_volatility = annual_volatility;
+        Ok(self)
+    }
+
+    pub fn with_volume(
+        mut self,
+        mean_volume: f32,
+        autocorrelation: f32,
+    ) -> Result<Self, CalculationError> {
+        if !(1.0..=i32::MAX as f32 / 10.0).contains(&mean_volume)
+            || !(0.0..1.0).contains(&autocorrelation)
+        {
+            return Err(CalculationError::InvalidParameterError(format!(
+                "Mean volume must be within [1, {}] and the autocorrelation within [0, 1). Mean volume provided: {} \n Autocorrelation provided: {}",
+                i32::MAX / 10,
+                mean_volume,
+                autocorrelation
+            )));
+        }
+        self.mean_volume = mean_volume;
+        self.volume_autocorrelation = autocorrelation;
+        Ok(self)
+    }
+
+    /// Replaces the default holidays, on which no bars are generated.
+    pub fn with_holidays(mut self, holidays: Vec<chrono::NaiveDate>) -> Self {
+        self.holidays = holidays;
+        self
+    }
+
+    pub fn with_split(mut self, split: structs::Split) -> Self {
+        self.splits.push(split);
+        self
+    }
+
+    /// Probability of each trading day's row being dropped, as with gaps in the data source.
+    pub fn with_missing_rate(mut self, missing_rate: f32) -> Result<Self, CalculationError> {
+        if !(0.0..1.0).contains(&missing_rate) {
+            return Err(CalculationError::InvalidParameterError(format!(
+                "Missing rate must be within [0, 1). Missing rate provided: {}",
+                missing_rate
+            )));
+        }
+        self.missing_rate = missing_rate;
+        Ok(self)
+    }
+}
+
+/// Holidays falling on the same date every year at the exchange of the currency. Holidays on a weekend are not
+/// observed on another day.
+pub fn fixed_date_holidays(year: i32, currency: Currency) -> Vec<chrono::NaiveDate> {
+    let dates: &[(u32, u32)] = match currency {
+        Currency::Usd => &[(1, 1), (6, 19), (7, 4), (12, 25)],
+        Currency::Sgd => &[(1, 1), (5, 1), (8, 9), (12, 25)],
+    };
+    dates
+        .iter()
+        .map(|(month, day)| chrono::NaiveDate::from_ymd(year, *month, *day))
+        .collect()
+}
+
+/// Consecutive weekdays from the start date, excluding the holidays.
+pub fn trading_days(
+    start_date: chrono::NaiveDate,
+    num_of_days: usize,
+    holidays: &[chrono::NaiveDate],
+) -> Vec<chrono::NaiveDate> {
+    start_date
+        .iter_days()
+        .filter(|date| {
+            !matches!(date.weekday(), chrono::Weekday::Sat | chrono::Weekday::Sun)
+                && !holidays.contains(date)
+        })
+        .take(num_of_days)
+        .collect()
+}
+
+/// Daily bars of a geometric random walk over the trading days. Each bar opens at a gap from the previous close,
+/// with the high and low extending beyond the open and close. The log volume follows an AR(1) process. Prices and
+/// volume before each split are unadjusted, while the adjusted close is continuous across the splits.
+pub fn generate_record(
+    config: &structs::SyntheticConfig,
+) -> Result<YahooFinancePriceRecord, CalculationError> {
+    let daily_volatility = config.annual_volatility as f64 / TRADING_DAYS_PER_YEAR.sqrt();
+    // Drift of the log price, such that the expected price grows at the annual drift
+    let daily_drift = (1.0 + config.annual_drift as f64).ln() / TRADING_DAYS_PER_YEAR;
+    let overnight_volatility = daily_volatility * OVERNIGHT_VOLATILITY_SHARE.sqrt();
+    let intraday_volatility = daily_volatility * (1.0 - OVERNIGHT_VOLATILITY_SHARE).sqrt();
+    let to_error =
+        |e: rand_distr::NormalError| CalculationError::InvalidParameterError(format!("{}", e));
+    let overnight = rand_distr::Normal::new(
+        daily_drift * OVERNIGHT_VOLATILITY_SHARE,
+        overnight_volatility,
+    )
+    .map_err(to_error)?;
+    let intraday = rand_distr::Normal::new(
+        daily_drift * (1.0 - OVERNIGHT_VOLATILITY_SHARE),
+        intraday_volatility,
+    )
+    .map_err(to_error)?;
+    let range = rand_distr::Normal::new(0.0, daily_volatility * RANGE_VOLATILITY_SCALE)
+        .map_err(to_error)?;
+    let mut rng = rand::rngs::StdRng::seed_from_u64(config.seed);
+
+    let days = trading_days(config.start_date, config.num_of_days, &config.holidays);
+    let mut record = YahooFinancePriceRecord::new(&config.ticker, days.len(), config.currency);
+    let autocorrelation = config.volume_autocorrelation as f64;
+    // Log volume of unit variance, centred such that the volume averages to the mean volume
+    let mut volume_state: f64 = rng.sample(rand_distr::StandardNormal);
+    let volume_offset = (config.mean_volume as f64).ln() - VOLUME_DISPERSION.powi(2) / 2.0;
+    let mut close = config.initial_price as f64;
+
+    for (idx, date) in days.iter().enumerate() {
+        let open = if idx == 0 {
+            close
+        } else {
+            close * overnight.sample(&mut rng).exp()
+        };
+        close = open * intraday.sample(&mut rng).exp();
+        let high = open.max(close) * range.sample(&mut rng).abs().exp();
+        let low = open.min(close) * (-range.sample(&mut rng).abs()).exp();
+        let innovation: f64 = rng.sample(rand_distr::StandardNormal);
+        volume_state =
+            autocorrelation * volume_state + (1.0 - autocorrelation.powi(2)).sqrt() * innovation;
+        let volume = (volume_offset + VOLUME_DISPERSION * volume_state).exp();
+
+        // Dropped rows still advance the price and volume processes, which are unaffected by the missing rate
+        if rng.gen::<f32>() < config.missing_rate {
+            continue;
+        }
+
+        // Shares before a split are worth more and traded in fewer numbers than the shares after it
+        let split_factor = config
+            .splits
+            .iter()
+            .filter(|split| split.date > *date)
+            .map(|split| split.ratio as f64)
+            .product::<f64>();
+        record.push_record(
+            chrono::Date::from_utc(*date, chrono::Utc).and_hms(0, 0, 0),
+            (open * split_factor) as f32,
+            (high * split_factor) as f32,
+            (low * split_factor) as f32,
+            (close * split_factor) as f32,
+            close as f32,
+            (volume / split_factor).round().min(i32::MAX as f64) as i32,
+        );
+    }
+    Ok(record)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datasets::traits::{Prices, Timestamps, Volume};
+    use crate::functions::{self, AggregationFunctions, Grouping};
+    use crate::inputs::enums::AggregationPeriod;
+    use crate::statistics;
+
+    fn config(seed: u64) -> structs::SyntheticConfig {
+        structs::SyntheticConfig::new(
+            "SYNTH",
+            Currency::Usd,
+            chrono::NaiveDate::from_ymd(2021, 12, 20),
+            300,
+            seed,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_generate_record() {
+        for seed in 0..20 {
+            let record = generate_record(&config(seed)).unwrap();
+            assert!(record.get_timestamps().len() == 300);
+            assert!(record
+                .get_timestamps()
+                .windows(2)
+                .all(|window| window[0] < window[1]));
+            for (idx, timestamp) in record.get_timestamps().iter().enumerate() {
+                assert!(timestamp.weekday().number_from_monday() <= 5);
+                assert!(!(timestamp.month() == 12 && timestamp.day() == 25));
+                assert!(!(timestamp.month() == 1 && timestamp.day() == 1));
+                let high = record.get_high_prices()[idx];
+                let low = record.get_low_prices()[idx];
+                assert!(low > 0.0 && low <= high);
+                assert!(high >= record.get_open_prices()[idx]);
+                assert!(high >= record.get_close_prices()[idx]);
+                assert!(low <= record.get_open_prices()[idx]);
+                assert!(low <= record.get_close_prices()[idx]);
+                assert!(record.get_volume()[idx] > 0);
+            }
+            // Without splits the close and adjusted close coincide
+            assert!(record.get_close_prices() == record.get_adj_close_prices());
+        }
+
+        // Same seed, same record
+        let first = generate_record(&config(3)).unwrap();
+        let second = generate_record(&config(3)).unwrap();
+        assert!(first.get_close_prices() == second.get_close_prices());
+        assert!(first.get_volume() == second.get_volume());
+
+        // Volume is autocorrelated
+        let volume = first
+            .get_volume()
+            .iter()
+            .map(|x| (*x as f32).ln())
+            .collect::<Vec<f32>>();
+        let lag_correlation = statistics::correlation(&volume[..volume.len() - 1], &volume[1..]);
+        assert!(lag_correlation > 0.5);
+
+        assert!(structs::SyntheticConfig::new(
+            "SYNTH",
+            Currency::Usd,
+            chrono::NaiveDate::from_ymd(2022, 1, 3),
+            0,
+            1
+        )
+        .is_err());
+        assert!(config(1).with_volume(1000.0, 1.0).is_err());
+        assert!(config(1).with_price_process(-1.0, 0.0, 0.2).is_err());
+    }
+
+    #[test]
+    fn test_generate_record_splits_and_missing_rows() {
+        let split_date = chrono::NaiveDate::from_ymd(2022, 6, 1);
+        let config = config(5)
+            .with_split(structs::Split::new(split_date, 4.0).unwrap())
+            .with_missing_rate(0.1)
+            .unwrap();
+        let record = generate_record(&config).unwrap();
+        assert!(record.get_timestamps().len() < 300);
+        assert!(record.get_timestamps().len() > 200);
+
+        let split_idx = record
+            .get_timestamps()
+            .iter()
+            .position(|x| x.naive_utc().date() >= split_date)
+            .unwrap();
+        for idx in 0..record.get_timestamps().len() {
+            let ratio = record.get_close_prices()[idx] / record.get_adj_close_prices()[idx];
+            let expected = if idx < split_idx { 4.0 } else { 1.0 };
+            assert!((ratio - expected).abs() < 1e-4);
+        }
+
+        // Same price process as without the split and missing rows
+        let complete = generate_record(&super::tests::config(5)).unwrap();
+        for (timestamp, adj_close) in record
+            .get_timestamps()
+            .iter()
+            .zip(record.get_adj_close_prices())
+        {
+            let idx = complete
+                .get_timestamps()
+                .iter()
+                .position(|x| x == timestamp)
+                .unwrap();
+            assert!((adj_close - complete.get_close_prices()[idx]).abs() < 1e-3);
+        }
+        assert!(structs::Split::new(split_date, 0.0).is_err());
+        assert!(super::tests::config(5).with_missing_rate(1.0).is_err());
+    }
+
+    #[test]
+    fn test_trading_days() {
+        let holidays = fixed_date_holidays(2022, Currency::Sgd);
+        // Friday 5 Aug 2022 to Friday 12 Aug 2022, around National Day on Tuesday 9 Aug 2022
+        let days = trading_days(chrono::NaiveDate::from_ymd(2022, 8, 5), 5, &holidays);
+        assert!(
+            days == [
+                chrono::NaiveDate::from_ymd(2022, 8, 5),
+                chrono::NaiveDate::from_ymd(2022, 8, 8),
+                chrono::NaiveDate::from_ymd(2022, 8, 10),
+                chrono::NaiveDate::from_ymd(2022, 8, 11),
+                chrono::NaiveDate::from_ymd(2022, 8, 12),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_weekly_grouping_of_synthetic_records() {
+        // Starts from December 2015 to 2024, such that the records cross the year ends at which 29 to 31 Dec fall
+        // in ISO week 1 of the next year (e.g. Monday 30 Dec 2024)
+        for seed in 0..10 {
+            let config = structs::SyntheticConfig::new(
+                "SYNTH",
+                Currency::Usd,
+                chrono::NaiveDate::from_ymd(2015 + seed as i32, 12, 2),
+                300,
+                seed,
+            )
+            .unwrap()
+            .with_missing_rate(0.05)
+            .unwrap();
+            let record = generate_record(&config).unwrap();
+            let grouped = Grouping::groupby_period(
+                record.get_timestamps(),
+                record.get_high_prices(),
+                AggregationPeriod::Weekly,
+            )
+            .unwrap();
+            let mut num_of_bars = 0;
+            for (week, group) in grouped.into_iter() {
+                assert!(week.weekday() == chrono::Weekday::Mon);
+                let group = group.collect::<Vec<_>>();
+                assert!(!group.is_empty() && group.len() <= 5);
+                assert!(group.iter().all(|(timestamp, _)| Grouping::period_start(
+                    *timestamp,
+                    AggregationPeriod::Weekly
+                ) == week));
+                num_of_bars += group.len();
+            }
+            assert!(num_of_bars == record.get_timestamps().len());
+
+            // Weekly maximum of the highs bounds every daily high of the week
+            let grouped = Grouping::groupby_period(
+                record.get_timestamps(),
+                record.get_high_prices(),
+                AggregationPeriod::Weekly,
+            )
+            .unwrap();
+            let weekly_max = AggregationFunctions::max(grouped).unwrap();
+            for (timestamp, high) in record.get_timestamps().iter().zip(record.get_high_prices()) {
+                let week = Grouping::period_start(timestamp, AggregationPeriod::Weekly);
+                assert!(weekly_max[&week] >= *high);
+            }
+        }
+    }
+
+    #[test]
+    fn test_percentiles_of_synthetic_records() {
+        for seed in 0..10 {
+            let record = generate_record(&config(seed)).unwrap();
+            let open_close = record
+                .get_open_prices()
+                .iter()
+                .zip(record.get_close_prices())
+                .map(|(a, b)| (*a, *b))
+                .collect::<Vec<(f32, f32)>>();
+            for period in [
+                AggregationPeriod::Weekly,
+                AggregationPeriod::Monthly,
+                AggregationPeriod::Quarterly,
+            ] {
+                let grouped =
+                    Grouping::groupby_period(record.get_timestamps(), &open_close, period).unwrap();
+                let mut deltas = AggregationFunctions::openclose_delta(grouped)
+                    .unwrap()
+                    .into_values()
+                    .collect::<Vec<f32>>();
+                deltas.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                let mut previous = f32::NEG_INFINITY;
+                for percentile in (0..=100).step_by(5) {
+                    let value =
+                        functions::percentile_from_sorted_array(percentile, &deltas).unwrap();
+                    assert!(value >= previous);
+                    assert!(value >= deltas[0] && value <= deltas[deltas.len() - 1]);
+                    previous = value;
+                }
+            }
+        }
+    }
+}
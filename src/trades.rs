@@ -0,0 +1,318 @@
+//! Objective: Store intraday trade prints, and aggregate them into time, tick, volume and dollar bars.
+//! The bars are returned as YahooFinancePriceRecords, such that the indicators and analytics over OHLCV records apply.
+
+use chrono::TimeZone;
+
+use crate::datasets::structs::YahooFinancePriceRecord;
+use crate::datasets::traits::{Description, Timestamps};
+use crate::enums;
+use crate::errors::CalculationError;
+
+pub mod structs {
+    use super::*;
+
+    /// Trade prints of a single ticker, stored as aligned arrays in chronological order.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct TradeRecord {
+        pub(super) ticker: String,
+        pub(super) currency: enums::Currency,
+        pub(super) timestamps: Vec<chrono::DateTime<chrono::Utc>>,
+        pub(super) prices: Vec<f32>,
+        pub(super) sizes: Vec<i32>,
+    }
+
+    impl TradeRecord {
+        pub fn new(ticker_symbol: &str, num_of_trades: usize, currency: enums::Currency) -> Self {
+            TradeRecord {
+                ticker: ticker_symbol.to_owned(),
+                currency,
+                timestamps: Vec::with_capacity(num_of_trades),
+                prices: Vec::with_capacity(num_of_trades),
+                sizes: Vec::with_capacity(num_of_trades),
+            }
+        }
+
+        /// Appends a trade print. Trades timestamped earlier than the latest trade are rejected.
+        pub fn push_trade(
+            &mut self,
+            timestamp: chrono::DateTime<chrono::Utc>,
+            price: f32,
+            size: i32,
+        ) -> Result<(), CalculationError> {
+            if let Some(latest) = self.timestamps.last() {
+                if timestamp < *latest {
+                    return Err(CalculationError::InvalidParameterError(format!(
+                        "Trades must be pushed in chronological order. Trade timestamp: {} \n Latest timestamp: {}",
+                        timestamp, latest
+                    )));
+                }
+            }
+            self.timestamps.push(timestamp);
+            self.prices.push(price);
+            self.sizes.push(size);
+            Ok(())
+        }
+
+        pub fn get_prices(&self) -> &[f32] {
+            &self.prices
+        }
+
+        pub fn get_sizes(&self) -> &[i32] {
+            &self.sizes
+        }
+
+        pub fn get_currency(&self) -> enums::Currency {
+            self.currency
+        }
+
+        pub fn len(&self) -> usize {
+            self.prices.len()
+        }
+
+        pub fn is_empty(&self) -> bool {
+            self.prices.is_empty()
+        }
+    }
+
+    impl Timestamps for TradeRecord {
+        fn get_timestamps(&self) -> &[chrono::DateTime<chrono::Utc>] {
+            &self.timestamps
+        }
+    }
+
+    impl Description for TradeRecord {
+        fn get_ticker_symbol(&self) -> &str {
+            &self.ticker
+        }
+    }
+}
+
+/// OHLCV bar in progress.
+struct BarBuilder {
+    timestamp: chrono::DateTime<chrono::Utc>,
+    open: f32,
+    high: f32,
+    low: f32,
+    close: f32,
+    volume: i64,
+    dollar_value: f64,
+    num_of_ticks: usize,
+}
+
+impl BarBuilder {
+    fn new(timestamp: chrono::DateTime<chrono::Utc>, price: f32) -> Self {
+        BarBuilder {
+            timestamp,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            volume: 0,
+            dollar_value: 0.0,
+            num_of_ticks: 0,
+        }
+    }
+
+    fn update(&mut self, price: f32, size: i32) {
+        self.high = self.high.max(price);
+        self.low = self.low.min(price);
+        self.close = price;
+        self.volume += size as i64;
+        self.dollar_value += price as f64 * size as f64;
+        self.num_of_ticks += 1;
+    }
+
+    fn push_into(&self, record: &mut YahooFinancePriceRecord) {
+        record.push_record(
+            self.timestamp,
+            self.open,
+            self.high,
+            self.low,
+            self.close,
+            self.close,
+            self.volume.min(i32::MAX as i64) as i32,
+        );
+    }
+}
+
+/// Aggregates the trades into bars, closing the bar in progress once the predicate over it is satisfied.
+/// Each bar is dated on its last trade, with the trailing incomplete bar excluded.
+fn aggregate_until<F>(trades: &structs::TradeRecord, is_complete: F) -> YahooFinancePriceRecord
+where
+    F: Fn(&BarBuilder) -> bool,
+{
+    let mut result =
+        YahooFinancePriceRecord::new(trades.get_ticker_symbol(), 0, trades.get_currency());
+    let mut bar: Option<BarBuilder> = None;
+    for idx in 0..trades.len() {
+        let timestamp = trades.timestamps[idx];
+        let price = trades.prices[idx];
+        let current = bar.get_or_insert_with(|| BarBuilder::new(timestamp, price));
+        current.update(price, trades.sizes[idx]);
+        current.timestamp = timestamp;
+        if is_complete(current) {
+            current.push_into(&mut result);
+            bar = None;
+        }
+    }
+    result
+}
+
+/// Time bars over fixed intervals, aligned to multiples of the interval since the Unix epoch
+/// (e.g. 5 minute bars start at 09:00, 09:05, ...). Each bar is dated on the start of its interval,
+/// and intervals without any trades are skipped.
+pub fn time_bars(
+    trades: &structs::TradeRecord,
+    interval: chrono::Duration,
+) -> Result<YahooFinancePriceRecord, CalculationError> {
+    let interval_seconds = interval.num_seconds();
+    if interval_seconds <= 0 {
+        return Err(CalculationError::InvalidParameterError(format!(
+            "Time bar interval must be at least 1 second. Interval provided: {}",
+            interval
+        )));
+    }
+
+    let mut result =
+        YahooFinancePriceRecord::new(trades.get_ticker_symbol(), 0, trades.get_currency());
+    let mut bar: Option<BarBuilder> = None;
+    for idx in 0..trades.len() {
+        let seconds = trades.timestamps[idx].timestamp();
+        let interval_start =
+            chrono::Utc.timestamp(seconds - seconds.rem_euclid(interval_seconds), 0);
+        if let Some(current) = &bar {
+            if current.timestamp != interval_start {
+                current.push_into(&mut result);
+                bar = None;
+            }
+        }
+        bar.get_or_insert_with(|| BarBuilder::new(interval_start, trades.prices[idx]))
+            .update(trades.prices[idx], trades.sizes[idx]);
+    }
+    if let Some(current) = &bar {
+        current.push_into(&mut result);
+    }
+    Ok(result)
+}
+
+/// Tick bars, each aggregating the given number of trades.
+pub fn tick_bars(
+    trades: &structs::TradeRecord,
+    num_of_ticks: usize,
+) -> Result<YahooFinancePriceRecord, CalculationError> {
+    if num_of_ticks == 0 {
+        return Err(CalculationError::InvalidParameterError(
+            "Number of ticks per bar must be greater than 0.".to_string(),
+        ));
+    }
+    Ok(aggregate_until(trades, |bar| {
+        bar.num_of_ticks >= num_of_ticks
+    }))
+}
+
+/// Volume bars, each closed once the traded volume reaches the threshold. Trades are not split across bars,
+/// hence a bar may overshoot the threshold.
+pub fn volume_bars(
+    trades: &structs::TradeRecord,
+    volume_threshold: i64,
+) -> Result<YahooFinancePriceRecord, CalculationError> {
+    if volume_threshold <= 0 {
+        return Err(CalculationError::InvalidParameterError(format!(
+            "Volume threshold must be positive. Threshold provided: {}",
+            volume_threshold
+        )));
+    }
+    Ok(aggregate_until(trades, |bar| {
+        bar.volume >= volume_threshold
+    }))
+}
+
+/// Dollar bars, each closed once the traded value (price * size) reaches the threshold. Trades are not split across
+/// bars, hence a bar may overshoot the threshold.
+pub fn dollar_bars(
+    trades: &structs::TradeRecord,
+    dollar_threshold: f64,
+) -> Result<YahooFinancePriceRecord, CalculationError> {
+    if dollar_threshold.is_nan() || dollar_threshold <= 0.0 {
+        return Err(CalculationError::InvalidParameterError(format!(
+            "Dollar threshold must be positive. Threshold provided: {}",
+            dollar_threshold
+        )));
+    }
+    Ok(aggregate_until(trades, |bar| {
+        bar.dollar_value >= dollar_threshold
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datasets::traits::{Prices, Volume};
+
+    fn test_trades() -> structs::TradeRecord {
+        let start = chrono::Utc.ymd(2022, 1, 3).and_hms(9, 0, 0);
+        let mut trades = structs::TradeRecord::new("TEST", 6, enums::Currency::Sgd);
+        [
+            (0, 10.0, 100),
+            (70, 10.5, 200),
+            (130, 9.5, 100),
+            (290, 10.0, 300),
+            (320, 11.0, 100),
+            (400, 10.5, 100),
+        ]
+        .iter()
+        .for_each(|(seconds, price, size)| {
+            trades
+                .push_trade(start + chrono::Duration::seconds(*seconds), *price, *size)
+                .unwrap()
+        });
+        trades
+    }
+
+    #[test]
+    fn test_time_bars() {
+        let trades = test_trades();
+        let result = time_bars(&trades, chrono::Duration::minutes(5)).unwrap();
+        assert!(
+            result.get_timestamps()
+                == [
+                    chrono::Utc.ymd(2022, 1, 3).and_hms(9, 0, 0),
+                    chrono::Utc.ymd(2022, 1, 3).and_hms(9, 5, 0)
+                ]
+        );
+        assert!(result.get_open_prices() == [10.0, 11.0]);
+        assert!(result.get_high_prices() == [10.5, 11.0]);
+        assert!(result.get_low_prices() == [9.5, 10.5]);
+        assert!(result.get_close_prices() == [10.0, 10.5]);
+        assert!(result.get_volume() == [700, 200]);
+        assert!(result.get_currency() == enums::Currency::Sgd);
+        assert!(time_bars(&trades, chrono::Duration::zero()).is_err());
+    }
+
+    #[test]
+    fn test_threshold_bars() {
+        let trades = test_trades();
+        let ticks = tick_bars(&trades, 4).unwrap();
+        // The trailing 2 trades do not complete a bar
+        assert!(ticks.get_close_prices() == [10.0]);
+        assert!(ticks.get_timestamps()[0] == chrono::Utc.ymd(2022, 1, 3).and_hms(9, 4, 50));
+
+        let volume = volume_bars(&trades, 300).unwrap();
+        assert!(volume.get_volume() == [300, 400]);
+        assert!(volume.get_open_prices() == [10.0, 9.5]);
+
+        // Traded values: 1000, 2100, 950, 3000, 1100, 1050
+        let dollar = dollar_bars(&trades, 3000.0).unwrap();
+        assert!(dollar.get_volume() == [300, 400]);
+        assert!(dollar_bars(&trades, 0.0).is_err());
+    }
+
+    #[test]
+    fn test_push_trade() {
+        let mut trades = test_trades();
+        assert!(trades
+            .push_trade(chrono::Utc.ymd(2022, 1, 3).and_hms(8, 0, 0), 10.0, 100)
+            .is_err());
+        assert!(trades.len() == 6);
+    }
+}
@@ -0,0 +1,432 @@
+//! Objective: Provide Value-at-Risk and expected shortfall estimates for a single ticker or a weighted basket.
+//! Both measures are reported as positive fractions of the position value (e.g. 0.02 for a 2% loss).
+
+use std::collections::HashMap;
+
+use rand::SeedableRng;
+use rand_distr::Distribution;
+
+use crate::datasets::traits::{Prices, Timestamps};
+use crate::errors::CalculationError;
+use crate::functions;
+use crate::returns;
+use crate::statistics;
+
+pub mod enums {
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum VarMethod {
+        Historical,
+        Gaussian,
+        CornishFisher,
+        MonteCarlo {
+            num_of_simulations: usize,
+            seed: u64,
+        },
+    }
+}
+
+pub mod structs {
+    use super::*;
+
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct RiskEstimate {
+        pub(super) method: enums::VarMethod,
+        pub(super) confidence: usize,
+        pub(super) horizon: usize,
+        pub(super) value_at_risk: f32,
+        pub(super) expected_shortfall: f32,
+    }
+
+    impl RiskEstimate {
+        pub fn get_method(&self) -> enums::VarMethod {
+            self.method
+        }
+
+        /// Confidence level in percent (e.g. 99).
+        pub fn get_confidence(&self) -> usize {
+            self.confidence
+        }
+
+        /// Horizon in number of bars.
+        pub fn get_horizon(&self) -> usize {
+            self.horizon
+        }
+
+        pub fn get_value_at_risk(&self) -> f32 {
+            self.value_at_risk
+        }
+
+        /// Expected shortfall (CVaR): the average loss beyond the Value-at-Risk.
+        pub fn get_expected_shortfall(&self) -> f32 {
+            self.expected_shortfall
+        }
+    }
+
+    /// Result of the Kupiec proportion of failures test over a rolling VaR backtest.
+    #[derive(Debug, Clone, PartialEq)]
+    pub struct KupiecTestResult {
+        pub(super) num_of_observations: usize,
+        pub(super) num_of_exceptions: usize,
+        pub(super) expected_exceptions: f32,
+        pub(super) likelihood_ratio: f32,
+        pub(super) p_value: f32,
+    }
+
+    impl KupiecTestResult {
+        pub fn get_num_of_observations(&self) -> usize {
+            self.num_of_observations
+        }
+
+        pub fn get_num_of_exceptions(&self) -> usize {
+            self.num_of_exceptions
+        }
+
+        pub fn get_expected_exceptions(&self) -> f32 {
+            self.expected_exceptions
+        }
+
+        pub fn get_likelihood_ratio(&self) -> f32 {
+            self.likelihood_ratio
+        }
+
+        /// P-value of the likelihood ratio under the chi-squared distribution with 1 degree of freedom.
+        pub fn get_p_value(&self) -> f32 {
+            self.p_value
+        }
+
+        /// Whether the VaR model is rejected at the provided significance level (e.g. 0.05).
+        pub fn is_rejected(&self, significance: f32) -> bool {
+            self.p_value < significance
+        }
+    }
+}
+
+/// Estimates the VaR and expected shortfall from a series of per-period returns. NaN values are skipped.
+/// The confidence is provided in percent (e.g. 95 or 99), and the horizon in number of bars.
+pub fn estimate_risk(
+    returns: &[f32],
+    method: enums::VarMethod,
+    confidence: usize,
+    horizon: usize,
+) -> Result<structs::RiskEstimate, CalculationError> {
+    if confidence == 0 || confidence >= 100 {
+        return Err(CalculationError::InvalidParameterError(format!(
+            "Confidence level must be between 1 and 99 percent, got {}.",
+            confidence
+        )));
+    }
+    if horizon == 0 {
+        return Err(CalculationError::InvalidParameterError(
+            "Horizon must be at least 1 bar.".to_string(),
+        ));
+    }
+
+    let valid_returns = statistics::drop_nan(returns);
+    if valid_returns.len() < horizon + 2 {
+        return Err(CalculationError::InsufficientDataError(format!(
+            "At least {} returns are required for a {} bar horizon, got {}.",
+            horizon + 2,
+            horizon,
+            valid_returns.len()
+        )));
+    }
+
+    let tail_probability = (100 - confidence) as f32 / 100.0;
+    let (value_at_risk, expected_shortfall) = match method {
+        enums::VarMethod::Historical => {
+            let horizon_returns = compound_over_horizon(&valid_returns, horizon);
+            empirical_risk(horizon_returns, confidence)
+        }
+        enums::VarMethod::Gaussian => {
+            let (mean, std) = horizon_moments(&valid_returns, horizon);
+            let z = statistics::normal_quantile(tail_probability);
+            (
+                -(mean + z * std),
+                -(mean - std * statistics::normal_pdf(z) / tail_probability),
+            )
+        }
+        enums::VarMethod::CornishFisher => {
+            let (mean, std) = horizon_moments(&valid_returns, horizon);
+            let skewness = statistics::skewness(&valid_returns);
+            let kurtosis = statistics::excess_kurtosis(&valid_returns);
+            let _cornish_fisher_var = |probability: f32| {
+                let z = statistics::normal_quantile(probability);
+                let adjusted_z = z
+                    + (z.powi(2) - 1.0) * skewness / 6.0
+                    + (z.powi(3) - 3.0 * z) * kurtosis / 24.0
+                    - (2.0 * z.powi(3) - 5.0 * z) * skewness.powi(2) / 36.0;
+                -(mean + adjusted_z * std)
+            };
+
+            // Expected shortfall is approximated by averaging the VaR over the tail probabilities
+            const TAIL_STEPS: usize = 100;
+            let expected_shortfall = (0..TAIL_STEPS)
+                .map(|step| {
+                    _cornish_fisher_var(tail_probability * (step as f32 + 0.5) / TAIL_STEPS as f32)
+                })
+                .sum::<f32>()
+                / TAIL_STEPS as f32;
+            (_cornish_fisher_var(tail_probability), expected_shortfall)
+        }
+        enums::VarMethod::MonteCarlo {
+            num_of_simulations,
+            seed,
+        } => {
+            if num_of_simulations < 2 {
+                return Err(CalculationError::InvalidParameterError(format!(
+                    "At least 2 simulations are required, got {}.",
+                    num_of_simulations
+                )));
+            }
+            let mean = statistics::mean(&valid_returns);
+            let std = statistics::sample_std(&valid_returns);
+            let distribution = rand_distr::Normal::new(mean, std)
+                .map_err(|e| CalculationError::InvalidParameterError(format!("{}", e)))?;
+            let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+
+            let simulated_returns = (0..num_of_simulations)
+                .map(|_| {
+                    (0..horizon).fold(1.0_f32, |acc, _| {
+                        acc * (1.0 + distribution.sample(&mut rng))
+                    }) - 1.0
+                })
+                .collect::<Vec<f32>>();
+            empirical_risk(simulated_returns, confidence)
+        }
+    };
+
+    Ok(structs::RiskEstimate {
+        method,
+        confidence,
+        horizon,
+        value_at_risk,
+        expected_shortfall,
+    })
+}
+
+/// Compounds the per-period returns over overlapping windows of the horizon length.
+fn compound_over_horizon(returns: &[f32], horizon: usize) -> Vec<f32> {
+    returns
+        .windows(horizon)
+        .map(|window| window.iter().fold(1.0_f32, |acc, x| acc * (1.0 + x)) - 1.0)
+        .collect()
+}
+
+/// Mean and standard deviation of the returns, scaled to the horizon under the i.i.d. assumption.
+fn horizon_moments(returns: &[f32], horizon: usize) -> (f32, f32) {
+    let horizon = horizon as f32;
+    (
+        statistics::mean(returns) * horizon,
+        statistics::sample_std(returns) * horizon.sqrt(),
+    )
+}
+
+/// VaR and expected shortfall from an empirical distribution of returns, using the percentile function.
+fn empirical_risk(mut returns: Vec<f32>, confidence: usize) -> (f32, f32) {
+    returns.sort_by(|a, b| a.partial_cmp(b).unwrap()); // NaN values were filtered out prior
+    let cutoff = functions::percentile_from_sorted_array(100 - confidence, &returns).unwrap(); // Infallible for f32 arrays
+    let tail = returns
+        .iter()
+        .copied()
+        .filter(|&x| x <= cutoff)
+        .collect::<Vec<f32>>();
+    (-cutoff, -statistics::mean(&tail))
+}
+
+/// Estimates the VaR and expected shortfall of a single record.
+pub fn ticker_risk<T>(
+    record: &T,
+    basis: returns::enums::ReturnBasis,
+    method: enums::VarMethod,
+    confidence: usize,
+    horizon: usize,
+) -> Result<structs::RiskEstimate, CalculationError>
+where
+    T: Prices,
+{
+    let period_returns = returns::simple_returns(returns::get_basis_prices(record, basis));
+    estimate_risk(&period_returns, method, confidence, horizon)
+}
+
+/// Per-period returns of a basket rebalanced to the provided weights every bar.
+/// Only the timestamps present in all of the records are considered.
+pub fn basket_returns<T>(
+    records: &[&T],
+    weights: &[f32],
+    basis: returns::enums::ReturnBasis,
+) -> Result<Vec<f32>, CalculationError>
+where
+    T: Prices + Timestamps,
+{
+    if records.len() != weights.len() {
+        return Err(CalculationError::InconsistentLengthError(format!(
+            "Number of records: {} \n Number of weights: {}",
+            records.len(),
+            weights.len()
+        )));
+    }
+    if records.is_empty() {
+        return Err(CalculationError::InsufficientDataError(
+            "At least 1 record is required for the basket.".to_string(),
+        ));
+    }
+
+    // Identifying the timestamps common to all the records
+    let mut timestamp_counts: HashMap<chrono::DateTime<chrono::Utc>, usize> = HashMap::new();
+    records.iter().for_each(|record| {
+        record
+            .get_timestamps()
+            .iter()
+            .for_each(|timestamp| *timestamp_counts.entry(*timestamp).or_insert(0) += 1)
+    });
+
+    let mut basket = Vec::new();
+    for (record, weight) in records.iter().zip(weights) {
+        let prices = record
+            .get_timestamps()
+            .iter()
+            .zip(returns::get_basis_prices(*record, basis))
+            .filter(|(timestamp, _)| timestamp_counts[*timestamp] == records.len())
+            .map(|(_, price)| *price)
+            .collect::<Vec<f32>>();
+        let record_returns = returns::simple_returns(&prices);
+
+        if basket.is_empty() {
+            basket = vec![0.0; record_returns.len()];
+        }
+        basket
+            .iter_mut()
+            .zip(record_returns)
+            .for_each(|(acc, ret)| *acc += weight * ret);
+    }
+
+    Ok(basket)
+}
+
+/// Estimates the VaR and expected shortfall of a weighted basket of records.
+pub fn basket_risk<T>(
+    records: &[&T],
+    weights: &[f32],
+    basis: returns::enums::ReturnBasis,
+    method: enums::VarMethod,
+    confidence: usize,
+    horizon: usize,
+) -> Result<structs::RiskEstimate, CalculationError>
+where
+    T: Prices + Timestamps,
+{
+    let period_returns = basket_returns(records, weights, basis)?;
+    estimate_risk(&period_returns, method, confidence, horizon)
+}
+
+/// Backtests a 1-bar VaR model estimated over a rolling window, and applies the Kupiec proportion of failures test.
+/// An exception is recorded whenever the loss of the next bar exceeds the estimated VaR.
+pub fn kupiec_backtest(
+    returns: &[f32],
+    method: enums::VarMethod,
+    confidence: usize,
+    window: usize,
+) -> Result<structs::KupiecTestResult, CalculationError> {
+    let valid_returns = statistics::drop_nan(returns);
+    if valid_returns.len() <= window {
+        return Err(CalculationError::InsufficientDataError(format!(
+            "Backtest requires more returns than the window length of {}, got {}.",
+            window,
+            valid_returns.len()
+        )));
+    }
+
+    let mut num_of_exceptions = 0;
+    for idx in window..valid_returns.len() {
+        let estimate = estimate_risk(&valid_returns[idx - window..idx], method, confidence, 1)?;
+        if -valid_returns[idx] > estimate.value_at_risk {
+            num_of_exceptions += 1;
+        }
+    }
+
+    let num_of_observations = valid_returns.len() - window;
+    let n = num_of_observations as f64;
+    let x = num_of_exceptions as f64;
+    let p = (100 - confidence) as f64 / 100.0;
+    let observed_rate = x / n;
+
+    // Log-likelihoods, with 0 * ln(0) treated as 0
+    let _log_likelihood = |rate: f64| {
+        let failures = if x > 0.0 { x * rate.ln() } else { 0.0 };
+        let successes = if n - x > 0.0 {
+            (n - x) * (1.0 - rate).ln()
+        } else {
+            0.0
+        };
+        failures + successes
+    };
+    let likelihood_ratio = (-2.0 * (_log_likelihood(p) - _log_likelihood(observed_rate))) as f32;
+    let p_value = 2.0 * (1.0 - statistics::normal_cdf(likelihood_ratio.max(0.0).sqrt())); // Chi-squared (1 dof) survival function
+
+    Ok(structs::KupiecTestResult {
+        num_of_observations,
+        num_of_exceptions,
+        expected_exceptions: (n * p) as f32,
+        likelihood_ratio,
+        p_value,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_returns() -> Vec<f32> {
+        (0..100)
+            .map(|x| ((x * 37) % 100) as f32 / 1000.0 - 0.05) // Uniformly spread between -5% and +5%
+            .collect()
+    }
+
+    #[test]
+    fn test_historical_var() {
+        let estimate = estimate_risk(&test_returns(), enums::VarMethod::Historical, 95, 1).unwrap();
+        // Sorted returns are -0.050, -0.049, ..., 0.049 - 5th percentile falls at index 4.95
+        assert!((estimate.get_value_at_risk() - 0.04505).abs() < 1e-5);
+        assert!((estimate.get_expected_shortfall() - 0.048).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_gaussian_var() {
+        let returns = test_returns();
+        let estimate = estimate_risk(&returns, enums::VarMethod::Gaussian, 99, 4).unwrap();
+        let mean = statistics::mean(&returns) * 4.0;
+        let std = statistics::sample_std(&returns) * 2.0;
+        assert!((estimate.get_value_at_risk() - (2.326348 * std - mean)).abs() < 1e-4);
+        assert!(estimate.get_expected_shortfall() > estimate.get_value_at_risk());
+    }
+
+    #[test]
+    fn test_cornish_fisher_and_monte_carlo_var() {
+        let returns = test_returns();
+        let gaussian = estimate_risk(&returns, enums::VarMethod::Gaussian, 99, 1).unwrap();
+        let cornish_fisher =
+            estimate_risk(&returns, enums::VarMethod::CornishFisher, 99, 1).unwrap();
+        // Uniform returns have negative excess kurtosis, hence thinner tails than the Gaussian
+        assert!(cornish_fisher.get_value_at_risk() < gaussian.get_value_at_risk());
+
+        let method = enums::VarMethod::MonteCarlo {
+            num_of_simulations: 20000,
+            seed: 42,
+        };
+        let monte_carlo = estimate_risk(&returns, method, 99, 1).unwrap();
+        assert!((monte_carlo.get_value_at_risk() - gaussian.get_value_at_risk()).abs() < 0.003);
+        assert!(monte_carlo == estimate_risk(&returns, method, 99, 1).unwrap());
+        // Seeded simulations are reproducible
+    }
+
+    #[test]
+    fn test_kupiec_backtest() {
+        let returns = test_returns().repeat(3);
+        let result = kupiec_backtest(&returns, enums::VarMethod::Historical, 95, 100).unwrap();
+        assert!(result.get_num_of_observations() == 200);
+        assert!((result.get_expected_exceptions() - 10.0).abs() < 1e-6);
+        assert!(!result.is_rejected(0.05));
+        assert!(estimate_risk(&returns, enums::VarMethod::Historical, 100, 1).is_err());
+    }
+}
@@ -0,0 +1,333 @@
+//! Objective: Provide volatility estimators making use of the full OHLC data of a record.
+
+use crate::datasets::traits::{Prices, Timestamps};
+use crate::errors::CalculationError;
+use crate::returns;
+use crate::statistics::sample_variance;
+
+pub mod enums {
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum VolatilityEstimator {
+        CloseToClose,
+        Parkinson,
+        GarmanKlass,
+        RogersSatchell,
+        YangZhang,
+    }
+
+    impl VolatilityEstimator {
+        /// Estimators relying on the previous close require an additional bar for each window.
+        pub fn requires_previous_close(&self) -> bool {
+            matches!(
+                self,
+                VolatilityEstimator::CloseToClose | VolatilityEstimator::YangZhang
+            )
+        }
+    }
+}
+
+/// Validates that the OHLC arrays are of equal lengths, returning the common length.
+pub(crate) fn validate_ohlc_lengths(
+    open: &[f32],
+    high: &[f32],
+    low: &[f32],
+    close: &[f32],
+) -> Result<usize, CalculationError> {
+    let length = close.len();
+    if open.len() != length || high.len() != length || low.len() != length {
+        return Err(CalculationError::InconsistentLengthError(format!(
+            "Length of the open array: {} \n Length of the high array: {} \n Length of the low array: {} \n Length of the close array: {}",
+            open.len(),
+            high.len(),
+            low.len(),
+            length
+        )));
+    }
+    Ok(length)
+}
+
+fn rogers_satchell_variance(open: &[f32], high: &[f32], low: &[f32], close: &[f32]) -> f32 {
+    let sum: f32 = (0..close.len())
+        .map(|idx| {
+            (high[idx] / close[idx]).ln() * (high[idx] / open[idx]).ln()
+                + (low[idx] / close[idx]).ln() * (low[idx] / open[idx]).ln()
+        })
+        .sum();
+    sum / close.len() as f32
+}
+
+/// Per-period (non-annualized) volatility over the whole of the provided OHLC arrays.
+pub fn estimate_volatility(
+    estimator: enums::VolatilityEstimator,
+    open: &[f32],
+    high: &[f32],
+    low: &[f32],
+    close: &[f32],
+) -> Result<f32, CalculationError> {
+    let length = validate_ohlc_lengths(open, high, low, close)?;
+    let minimum_length = if estimator.requires_previous_close() {
+        3
+    } else {
+        1
+    };
+    if length < minimum_length {
+        return Err(CalculationError::InsufficientDataError(format!(
+            "{:?} estimator requires at least {} bars, got {}.",
+            estimator, minimum_length, length
+        )));
+    }
+
+    let variance = match estimator {
+        enums::VolatilityEstimator::CloseToClose => {
+            let log_returns = returns::log_returns(close);
+            sample_variance(&log_returns[1..])
+        }
+        enums::VolatilityEstimator::Parkinson => {
+            let sum: f32 = high
+                .iter()
+                .zip(low)
+                .map(|(h, l)| (h / l).ln().powi(2))
+                .sum();
+            sum / (4.0 * 2.0_f32.ln() * length as f32)
+        }
+        enums::VolatilityEstimator::GarmanKlass => {
+            let sum: f32 = (0..length)
+                .map(|idx| {
+                    0.5 * (high[idx] / low[idx]).ln().powi(2)
+                        - (2.0 * 2.0_f32.ln() - 1.0) * (close[idx] / open[idx]).ln().powi(2)
+                })
+                .sum();
+            sum / length as f32
+        }
+        enums::VolatilityEstimator::RogersSatchell => {
+            rogers_satchell_variance(open, high, low, close)
+        }
+        enums::VolatilityEstimator::YangZhang => {
+            let num_of_bars = (length - 1) as f32; // First bar is only used for its close price
+            let overnight_returns = (1..length)
+                .map(|idx| (open[idx] / close[idx - 1]).ln())
+                .collect::<Vec<f32>>();
+            let open_close_returns = (1..length)
+                .map(|idx| (close[idx] / open[idx]).ln())
+                .collect::<Vec<f32>>();
+            let k = 0.34 / (1.34 + (num_of_bars + 1.0) / (num_of_bars - 1.0));
+
+            sample_variance(&overnight_returns)
+                + k * sample_variance(&open_close_returns)
+                + (1.0 - k)
+                    * rogers_satchell_variance(&open[1..], &high[1..], &low[1..], &close[1..])
+        }
+    };
+
+    Ok(variance.max(0.0).sqrt()) // Garman-Klass can produce small negative variances due to rounding
+}
+
+/// Per-period volatility over a trailing window. Output is aligned with the input arrays, with NaN warm-up values.
+pub fn rolling_volatility(
+    estimator: enums::VolatilityEstimator,
+    open: &[f32],
+    high: &[f32],
+    low: &[f32],
+    close: &[f32],
+    window: usize,
+) -> Result<Vec<f32>, CalculationError> {
+    let length = validate_ohlc_lengths(open, high, low, close)?;
+    if window < 2 {
+        return Err(CalculationError::InvalidParameterError(format!(
+            "Rolling window must be at least 2 bars, got {}.",
+            window
+        )));
+    }
+
+    let lookback = if estimator.requires_previous_close() {
+        window + 1
+    } else {
+        window
+    };
+
+    let mut result = Vec::with_capacity(length);
+    for idx in 0..length {
+        if idx + 1 < lookback {
+            result.push(f32::NAN);
+            continue;
+        }
+        let start = idx + 1 - lookback;
+        result.push(estimate_volatility(
+            estimator,
+            &open[start..=idx],
+            &high[start..=idx],
+            &low[start..=idx],
+            &close[start..=idx],
+        )?);
+    }
+
+    Ok(result)
+}
+
+/// Per-period exponentially weighted volatility of the log returns (RiskMetrics), using the provided decay factor.
+pub fn ewma_volatility(close: &[f32], decay: f32) -> Result<Vec<f32>, CalculationError> {
+    if !(0.0..1.0).contains(&decay) {
+        return Err(CalculationError::InvalidParameterError(format!(
+            "Decay factor must be within [0, 1), got {}.",
+            decay
+        )));
+    }
+
+    let mut variance: Option<f32> = None;
+    let result = returns::log_returns(close)
+        .into_iter()
+        .map(|log_return| {
+            if log_return.is_nan() {
+                return f32::NAN;
+            }
+            let updated = match variance {
+                Some(previous) => decay * previous + (1.0 - decay) * log_return.powi(2),
+                None => log_return.powi(2), // Seeding with the first squared return
+            };
+            variance = Some(updated);
+            updated.sqrt()
+        })
+        .collect();
+
+    Ok(result)
+}
+
+/// Annualized volatility of the record over the whole period, using the detected bar frequency.
+pub fn historical_volatility<T>(
+    record: &T,
+    estimator: enums::VolatilityEstimator,
+) -> Result<f32, CalculationError>
+where
+    T: Prices + Timestamps,
+{
+    let frequency = returns::detect_frequency(record.get_timestamps())?;
+    let volatility = estimate_volatility(
+        estimator,
+        record.get_open_prices(),
+        record.get_high_prices(),
+        record.get_low_prices(),
+        record.get_close_prices(),
+    )?;
+    Ok(returns::annualize_volatility(volatility, frequency))
+}
+
+/// Annualized rolling volatility of the record, aligned with the timestamps of the record.
+pub fn historical_rolling_volatility<T>(
+    record: &T,
+    estimator: enums::VolatilityEstimator,
+    window: usize,
+) -> Result<Vec<f32>, CalculationError>
+where
+    T: Prices + Timestamps,
+{
+    let frequency = returns::detect_frequency(record.get_timestamps())?;
+    let result = rolling_volatility(
+        estimator,
+        record.get_open_prices(),
+        record.get_high_prices(),
+        record.get_low_prices(),
+        record.get_close_prices(),
+        window,
+    )?
+    .into_iter()
+    .map(|x| returns::annualize_volatility(x, frequency))
+    .collect();
+    Ok(result)
+}
+
+/// Annualized EWMA volatility of the record, aligned with the timestamps of the record.
+pub fn historical_ewma_volatility<T>(record: &T, decay: f32) -> Result<Vec<f32>, CalculationError>
+where
+    T: Prices + Timestamps,
+{
+    let frequency = returns::detect_frequency(record.get_timestamps())?;
+    let result = ewma_volatility(record.get_close_prices(), decay)?
+        .into_iter()
+        .map(|x| returns::annualize_volatility(x, frequency))
+        .collect();
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::enums::VolatilityEstimator;
+    use super::*;
+
+    const OPEN: [f32; 4] = [100.0, 102.0, 101.0, 104.0];
+    const HIGH: [f32; 4] = [103.0, 104.0, 105.0, 106.0];
+    const LOW: [f32; 4] = [99.0, 100.0, 100.0, 102.0];
+    const CLOSE: [f32; 4] = [102.0, 101.0, 104.0, 105.0];
+
+    #[test]
+    fn test_parkinson() {
+        let expected = ((103.0_f32 / 99.0).ln().powi(2)
+            + (104.0_f32 / 100.0).ln().powi(2)
+            + (105.0_f32 / 100.0).ln().powi(2)
+            + (106.0_f32 / 102.0).ln().powi(2))
+            / (4.0 * 4.0 * 2.0_f32.ln());
+        let result =
+            estimate_volatility(VolatilityEstimator::Parkinson, &OPEN, &HIGH, &LOW, &CLOSE)
+                .unwrap();
+        assert!((result - expected.sqrt()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_close_to_close() {
+        let log_returns = [
+            (101.0_f32 / 102.0).ln(),
+            (104.0_f32 / 101.0).ln(),
+            (105.0_f32 / 104.0).ln(),
+        ];
+        let mean = log_returns.iter().sum::<f32>() / 3.0;
+        let expected = (log_returns.iter().map(|x| (x - mean).powi(2)).sum::<f32>() / 2.0).sqrt();
+        let result = estimate_volatility(
+            VolatilityEstimator::CloseToClose,
+            &OPEN,
+            &HIGH,
+            &LOW,
+            &CLOSE,
+        )
+        .unwrap();
+        assert!((result - expected).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_rolling_volatility_alignment() {
+        let result = rolling_volatility(
+            VolatilityEstimator::YangZhang,
+            &OPEN,
+            &HIGH,
+            &LOW,
+            &CLOSE,
+            2,
+        )
+        .unwrap();
+        assert!(result.len() == 4);
+        assert!(result[0].is_nan() && result[1].is_nan());
+        assert!(result[2] > 0.0 && result[3] > 0.0);
+
+        let result = rolling_volatility(
+            VolatilityEstimator::GarmanKlass,
+            &OPEN,
+            &HIGH,
+            &LOW,
+            &CLOSE,
+            2,
+        )
+        .unwrap();
+        assert!(result[0].is_nan() && !result[1].is_nan());
+    }
+
+    #[test]
+    fn test_ewma_volatility() {
+        let result = ewma_volatility(&CLOSE, 0.94).unwrap();
+        let first_return = (101.0_f32 / 102.0).ln();
+        let second_return = (104.0_f32 / 101.0).ln();
+        assert!(result[0].is_nan());
+        assert!((result[1] - first_return.abs()).abs() < 1e-6);
+        let expected = (0.94 * first_return.powi(2) + 0.06 * second_return.powi(2)).sqrt();
+        assert!((result[2] - expected).abs() < 1e-6);
+        assert!(ewma_volatility(&CLOSE, 1.5).is_err());
+    }
+}